@@ -0,0 +1,72 @@
+CREATE TABLE IF NOT EXISTS throttle (
+    token_ca TEXT NOT NULL,
+    chat_id INTEGER NOT NULL,
+    thread_id INTEGER,
+    sent_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS aliases (
+    chat_id INTEGER NOT NULL,
+    phrase TEXT NOT NULL,
+    token_ca TEXT NOT NULL,
+    PRIMARY KEY (chat_id, phrase)
+);
+
+CREATE TABLE IF NOT EXISTS mentions (
+    chat_id INTEGER NOT NULL,
+    chain_id TEXT NOT NULL,
+    token_ca TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    mcap TEXT,
+    latest_mcap TEXT,
+    caller TEXT,
+    mentioned_at INTEGER NOT NULL,
+    PRIMARY KEY (chat_id, token_ca)
+);
+
+CREATE TABLE IF NOT EXISTS watches (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    user_name TEXT NOT NULL,
+    chat_id INTEGER NOT NULL,
+    thread_id INTEGER,
+    chain_id TEXT NOT NULL,
+    token_ca TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    base_mcap TEXT NOT NULL,
+    target_mcap TEXT NOT NULL,
+    dm INTEGER NOT NULL,
+    UNIQUE (user_id, chat_id, token_ca)
+);
+
+CREATE TABLE IF NOT EXISTS outbox (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chat_id INTEGER NOT NULL,
+    thread_id INTEGER,
+    reply_to INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    buttons TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    next_attempt_at INTEGER NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS token_list (
+    source TEXT NOT NULL,
+    chain_id TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    token_ca TEXT NOT NULL,
+    PRIMARY KEY (chain_id, symbol)
+);
+
+CREATE TABLE IF NOT EXISTS token_tags (
+    chain_id TEXT NOT NULL,
+    token_ca TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (chain_id, token_ca, tag)
+);
+
+CREATE TABLE IF NOT EXISTS archived_chats (
+    chat_id INTEGER PRIMARY KEY,
+    archived_at INTEGER NOT NULL
+);
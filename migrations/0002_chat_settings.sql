@@ -0,0 +1,8 @@
+CREATE TABLE chat_settings (
+    chat_id INTEGER PRIMARY KEY,
+    throttle_mins INTEGER,
+    link_set TEXT,
+    min_mcap REAL,
+    auto_delete_mins INTEGER,
+    silent INTEGER NOT NULL DEFAULT 1
+);
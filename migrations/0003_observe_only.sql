@@ -0,0 +1 @@
+ALTER TABLE chat_settings ADD COLUMN observe_only INTEGER NOT NULL DEFAULT 0;
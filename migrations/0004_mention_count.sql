@@ -0,0 +1 @@
+ALTER TABLE mentions ADD COLUMN mention_count INTEGER NOT NULL DEFAULT 1;
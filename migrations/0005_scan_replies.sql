@@ -0,0 +1 @@
+ALTER TABLE chat_settings ADD COLUMN scan_replies INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,4 @@
+CREATE TABLE private_chat_users (
+    user_id INTEGER PRIMARY KEY,
+    started_at INTEGER NOT NULL
+);
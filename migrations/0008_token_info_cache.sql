@@ -0,0 +1,7 @@
+CREATE TABLE token_info_cache (
+    chain_id TEXT NOT NULL,
+    token_ca TEXT NOT NULL,
+    info TEXT NOT NULL,
+    saved_at INTEGER NOT NULL,
+    PRIMARY KEY (chain_id, token_ca)
+);
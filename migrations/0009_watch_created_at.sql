@@ -0,0 +1,2 @@
+ALTER TABLE watches ADD COLUMN created_at INTEGER NOT NULL DEFAULT 0;
+UPDATE watches SET created_at = CAST(strftime('%s', 'now') AS INTEGER);
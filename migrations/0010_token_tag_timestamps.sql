@@ -0,0 +1,2 @@
+ALTER TABLE token_tags ADD COLUMN tagged_at INTEGER NOT NULL DEFAULT 0;
+UPDATE token_tags SET tagged_at = CAST(strftime('%s', 'now') AS INTEGER);
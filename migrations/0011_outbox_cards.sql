@@ -0,0 +1,2 @@
+ALTER TABLE outbox ADD COLUMN tokens TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE outbox ADD COLUMN full_text TEXT;
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use teloxide::Bot;
+use teloxide::payloads::{AnswerCallbackQuerySetters, SendMessageSetters};
+use teloxide::prelude::{Requester, ResponseResult};
+use teloxide::types::{CallbackQuery, Chat, ChatId, InlineKeyboardButton, InlineKeyboardMarkup};
+
+use crate::APP_CONFIG;
+use crate::auto_leave::schedule_leave_unless_whitelisted;
+use crate::config::{persist_blocked_chats, persist_whitelist};
+use crate::whitelist::add_to_whitelist;
+
+pub const ABUSE_CALLBACK_PREFIX: &str = "abuse:";
+
+#[derive(Debug)]
+struct ChatTraffic {
+    window_start: Instant,
+    messages: u32,
+    alerted: bool,
+}
+
+/// Messages from non-whitelisted chats within the current window
+static TRAFFIC: LazyLock<Mutex<HashMap<ChatId, ChatTraffic>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn is_blocked(chat_id: ChatId) -> bool {
+    let ChatId(id) = chat_id;
+    APP_CONFIG
+        .get()
+        .unwrap()
+        .app_config()
+        .blocked_chats
+        .contains(&id)
+}
+
+/// Counts the message from a non-whitelisted chat and alerts the admin chat once
+/// the chat keeps using the bot. Blocked chats are left right away.
+/// Private chats and the admin chat itself are never tracked.
+pub async fn track_non_whitelisted_message(bot: &Bot, chat: &Chat) {
+    let ChatId(id) = chat.id;
+    let cfg = APP_CONFIG.get().unwrap().app_config();
+    if chat.is_private() || cfg.admin_chat_id == Some(id) {
+        return;
+    }
+
+    if is_blocked(chat.id) {
+        leave_chat(bot, chat.id).await;
+        return;
+    }
+
+    schedule_leave_unless_whitelisted(bot, chat.id);
+
+    let window = Duration::from_secs(cfg.abuse_detection.window_mins * 60);
+
+    let should_alert = {
+        let mut traffic = TRAFFIC.lock().unwrap();
+        traffic.retain(|_, chat_traffic| chat_traffic.window_start.elapsed() < window);
+
+        let chat_traffic = traffic.entry(chat.id).or_insert(ChatTraffic {
+            window_start: Instant::now(),
+            messages: 0,
+            alerted: false,
+        });
+        chat_traffic.messages += 1;
+
+        let should_alert = !chat_traffic.alerted
+            && chat_traffic.messages >= cfg.abuse_detection.messages_threshold;
+        chat_traffic.alerted |= should_alert;
+        should_alert
+    };
+
+    if !should_alert {
+        return;
+    }
+
+    let Some(admin_chat_id) = cfg.admin_chat_id else {
+        warn!(
+            "Non-whitelisted chat {} keeps using the bot but admin_chat_id is not configured",
+            chat.id
+        );
+        return;
+    };
+
+    let title = chat
+        .title()
+        .or_else(|| chat.username())
+        .unwrap_or("untitled");
+    let text = format!(
+        "🚨 Non-whitelisted chat \"{title}\" ({}) sent {} messages within {} minutes",
+        chat.id, cfg.abuse_detection.messages_threshold, cfg.abuse_detection.window_mins
+    );
+    let keyboard = InlineKeyboardMarkup::new([[
+        InlineKeyboardButton::callback(
+            "✅ Whitelist this chat",
+            format!("{ABUSE_CALLBACK_PREFIX}whitelist:{}", chat.id),
+        ),
+        InlineKeyboardButton::callback(
+            "⛔ Block & leave",
+            format!("{ABUSE_CALLBACK_PREFIX}block:{}", chat.id),
+        ),
+    ]]);
+
+    if let Err(e) = bot
+        .send_message(ChatId(admin_chat_id), text)
+        .reply_markup(keyboard)
+        .await
+    {
+        warn!("Failed to send abuse alert about {} - {e:?}", chat.id);
+    }
+}
+
+/// Handles the buttons of abuse alerts, only admins may use them
+pub async fn handle_abuse_action(bot: &Bot, query: &CallbackQuery) -> ResponseResult<()> {
+    let cfg = APP_CONFIG.get().unwrap();
+    if !cfg.is_admin(&query.from) {
+        bot.answer_callback_query(query.id.clone())
+            .text("Only admins can do that")
+            .await?;
+        return Ok(());
+    }
+
+    let action = query
+        .data
+        .as_deref()
+        .and_then(|data| data.strip_prefix(ABUSE_CALLBACK_PREFIX))
+        .and_then(|data| data.split_once(':'))
+        .and_then(|(action, id)| Some((action, ChatId(id.parse().ok()?))));
+
+    let outcome = match action {
+        Some(("whitelist", chat_id)) => add_to_whitelist(chat_id),
+        Some(("block", chat_id)) => {
+            let outcome = block_chat(chat_id);
+            leave_chat(bot, chat_id).await;
+            outcome
+        }
+        _ => {
+            warn!("Unknown abuse action {:?}", query.data);
+            bot.answer_callback_query(query.id.clone()).await?;
+            return Ok(());
+        }
+    };
+
+    bot.answer_callback_query(query.id.clone())
+        .text(outcome.clone())
+        .await?;
+
+    // drop the buttons so the action isn't taken twice
+    if let Some(alert) = query.regular_message() {
+        let text = format!("{}\n\n{outcome}", alert.text().unwrap_or_default());
+        if let Err(e) = bot.edit_message_text(alert.chat.id, alert.id, text).await {
+            warn!("Failed to update abuse alert {} - {e:?}", alert.id);
+        }
+    }
+
+    Ok(())
+}
+
+fn block_chat(chat_id: ChatId) -> String {
+    let cfg = APP_CONFIG.get().unwrap();
+    let ChatId(id) = chat_id;
+
+    cfg.update_app_config(|config| {
+        config.whitelisted_chats.retain(|chat| *chat != id);
+        if !config.blocked_chats.contains(&id) {
+            config.blocked_chats.push(id);
+        }
+    });
+    TRAFFIC.lock().unwrap().remove(&chat_id);
+
+    let mut outcome = format!("Chat {id} blocked");
+    info!("{outcome}");
+
+    let config = cfg.app_config();
+    let persisted = persist_blocked_chats(&cfg.config_path, &config.blocked_chats)
+        .and_then(|()| persist_whitelist(&cfg.config_path, &config.whitelisted_chats));
+    if let Err(e) = persisted {
+        warn!("Failed to persist blocked chats - {e:?}");
+        outcome.push_str(" (not saved to the config file, it will be lost on restart)");
+    }
+
+    outcome
+}
+
+pub async fn leave_chat(bot: &Bot, chat_id: ChatId) {
+    match bot.leave_chat(chat_id).await {
+        Ok(_) => info!("Left chat {chat_id}"),
+        Err(e) => warn!("Failed to leave chat {chat_id} - {e:?}"),
+    }
+}
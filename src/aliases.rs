@@ -0,0 +1,158 @@
+use log::{debug, info, warn};
+use sqlx::SqlitePool;
+use teloxide::Bot;
+use teloxide::prelude::Requester;
+use teloxide::types::{Chat, ChatId, ChatMemberStatus, Message, User};
+
+use crate::APP_CONFIG;
+use crate::storage::{delete_alias, load_aliases, save_alias};
+use crate::token_info::{extract_evm_cas, extract_solana_cas, extract_ton_cas, extract_tron_cas};
+
+const USAGE: &str = "Usage: /alias add <phrase> <ca> | /alias remove <phrase> | /alias list";
+
+pub fn is_token_ca(text: &str) -> bool {
+    [
+        extract_solana_cas(text),
+        extract_evm_cas(text),
+        extract_ton_cas(text),
+        extract_tron_cas(text),
+    ]
+    .iter()
+    .any(|cas| cas.iter().any(|ca| ca == text))
+}
+
+fn normalize_phrase(phrase: &str) -> String {
+    phrase
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Chat admins and the bot admins may manage per-chat settings like aliases
+pub async fn is_chat_admin(bot: &Bot, message: &Message) -> bool {
+    let Some(user) = &message.from else {
+        return false;
+    };
+
+    is_chat_admin_user(bot, &message.chat, user).await
+}
+
+/// Same as [`is_chat_admin`] for users who aren't the message author, e.g. pressing a button
+pub async fn is_chat_admin_user(bot: &Bot, chat: &Chat, user: &User) -> bool {
+    if chat.is_private() || APP_CONFIG.get().unwrap().is_admin(user) {
+        return true;
+    }
+
+    match bot.get_chat_member(chat.id, user.id).await {
+        Ok(member) => matches!(
+            member.status(),
+            ChatMemberStatus::Owner | ChatMemberStatus::Administrator
+        ),
+        Err(e) => {
+            warn!("Failed to get chat member {} - {e:?}", user.id);
+            false
+        }
+    }
+}
+
+pub async fn handle_alias(bot: &Bot, message: &Message, storage: &SqlitePool, args: &str) {
+    let chat_id = message.chat.id;
+    let (action, rest) = args.trim().split_once(' ').unwrap_or((args.trim(), ""));
+
+    let text = match action.to_lowercase().as_str() {
+        "list" => list_aliases(storage, chat_id).await,
+        "add" | "remove" if !is_chat_admin(bot, message).await => {
+            debug!("Skipping /alias {action} from a non-admin user");
+            return;
+        }
+        "add" => match rest.trim().rsplit_once(' ') {
+            Some((phrase, token_ca)) if is_token_ca(token_ca) && !phrase.trim().is_empty() => {
+                add_alias(storage, chat_id, &normalize_phrase(phrase), token_ca).await
+            }
+            _ => USAGE.to_owned(),
+        },
+        "remove" if !rest.trim().is_empty() => {
+            remove_alias(storage, chat_id, &normalize_phrase(rest)).await
+        }
+        _ => USAGE.to_owned(),
+    };
+
+    if let Err(e) = bot.send_message(chat_id, text).await {
+        warn!("Failed to reply to /alias - {e:?}");
+    }
+}
+
+async fn list_aliases(storage: &SqlitePool, chat_id: ChatId) -> String {
+    match load_aliases(storage, chat_id).await {
+        Ok(aliases) if aliases.is_empty() => "No aliases in this chat".to_owned(),
+        Ok(aliases) => {
+            let aliases = aliases
+                .iter()
+                .map(|(phrase, token_ca)| format!("{phrase} → {token_ca}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("Aliases:\n{aliases}")
+        }
+        Err(e) => {
+            warn!("Failed to load aliases of {chat_id} - {e:?}");
+            "Failed to load aliases".to_owned()
+        }
+    }
+}
+
+async fn add_alias(storage: &SqlitePool, chat_id: ChatId, phrase: &str, token_ca: &str) -> String {
+    match save_alias(storage, chat_id, phrase, token_ca).await {
+        Ok(()) => {
+            info!("Added alias \"{phrase}\" -> {token_ca} in {chat_id}");
+            format!("\"{phrase}\" now points to {token_ca}")
+        }
+        Err(e) => {
+            warn!("Failed to save alias \"{phrase}\" in {chat_id} - {e:?}");
+            "Failed to save the alias".to_owned()
+        }
+    }
+}
+
+async fn remove_alias(storage: &SqlitePool, chat_id: ChatId, phrase: &str) -> String {
+    match delete_alias(storage, chat_id, phrase).await {
+        Ok(true) => format!("Alias \"{phrase}\" removed"),
+        Ok(false) => format!("There is no alias \"{phrase}\""),
+        Err(e) => {
+            warn!("Failed to delete alias \"{phrase}\" in {chat_id} - {e:?}");
+            "Failed to remove the alias".to_owned()
+        }
+    }
+}
+
+fn contains_phrase(text: &str, phrase: &str) -> bool {
+    text.match_indices(phrase).any(|(start, _)| {
+        let end = start + phrase.len();
+        let before = text[..start].chars().next_back();
+        let after = text[end..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// CAs of the chat aliases mentioned in the text, separated by spaces so they
+/// can go through the usual CA extraction
+pub async fn find_aliased_cas(storage: &SqlitePool, chat_id: ChatId, text: &str) -> String {
+    let aliases = match load_aliases(storage, chat_id).await {
+        Ok(aliases) => aliases,
+        Err(e) => {
+            warn!("Failed to load aliases of {chat_id} - {e:?}");
+            return String::new();
+        }
+    };
+
+    let text = normalize_phrase(text);
+    aliases
+        .into_iter()
+        .filter(|(phrase, _)| contains_phrase(&text, phrase))
+        .map(|(phrase, token_ca)| {
+            debug!("Message mentions alias \"{phrase}\" of {token_ca}");
+            token_ca
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
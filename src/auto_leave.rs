@@ -0,0 +1,92 @@
+use std::collections::HashSet;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use log::{debug, info};
+use serde::Deserialize;
+use teloxide::Bot;
+use teloxide::prelude::ResponseResult;
+use teloxide::types::{ChatId, ChatMemberUpdated};
+
+use crate::APP_CONFIG;
+use crate::abuse::leave_chat;
+
+/// Leave chats which are not whitelisted within the grace period after the bot is
+/// added there (or after the first message seen from a chat the bot is already in)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AutoLeaveConfig {
+    pub enabled: bool,
+    pub grace_period_mins: u64,
+}
+
+impl Default for AutoLeaveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            grace_period_mins: 10,
+        }
+    }
+}
+
+/// Chats to be left once their grace period is over
+static PENDING_LEAVES: LazyLock<Mutex<HashSet<ChatId>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+fn is_whitelisted(chat_id: ChatId) -> bool {
+    let ChatId(id) = chat_id;
+    APP_CONFIG
+        .get()
+        .unwrap()
+        .app_config()
+        .whitelisted_chats
+        .contains(&id)
+}
+
+/// Handles the bot being added to or removed from chats
+pub async fn handle_my_chat_member(bot: Bot, update: ChatMemberUpdated) -> ResponseResult<()> {
+    debug!("Got {update:?}");
+
+    if update.chat.is_private() || !update.new_chat_member.kind.is_present() {
+        return Ok(());
+    }
+
+    info!("Bot was added to chat {}", update.chat.id);
+    schedule_leave_unless_whitelisted(&bot, update.chat.id);
+
+    Ok(())
+}
+
+/// Leaves the chat after the grace period unless it gets whitelisted meanwhile,
+/// private chats and the admin chat are never left
+pub fn schedule_leave_unless_whitelisted(bot: &Bot, chat_id: ChatId) {
+    let cfg = APP_CONFIG.get().unwrap().app_config();
+    if !cfg.auto_leave.enabled || is_whitelisted(chat_id) {
+        return;
+    }
+
+    let ChatId(id) = chat_id;
+    if chat_id.is_user() || cfg.admin_chat_id == Some(id) {
+        return;
+    }
+
+    if !PENDING_LEAVES.lock().unwrap().insert(chat_id) {
+        return;
+    }
+
+    let grace_period = Duration::from_secs(cfg.auto_leave.grace_period_mins * 60);
+    info!("Leaving non-whitelisted chat {chat_id} in {grace_period:?} unless it gets whitelisted");
+
+    let bot = bot.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(grace_period).await;
+        PENDING_LEAVES.lock().unwrap().remove(&chat_id);
+
+        if is_whitelisted(chat_id) {
+            debug!("Chat {chat_id} got whitelisted during the grace period - staying");
+            return;
+        }
+
+        leave_chat(&bot, chat_id).await;
+    });
+}
@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use teloxide::Bot;
+use teloxide::net::Download;
+use teloxide::prelude::Requester;
+use teloxide::types::{ChatId, Document, Message};
+
+use crate::APP_CONFIG;
+#[cfg(feature = "mtproto")]
+use crate::companion::{is_companion_running, read_history};
+use crate::dexscreener::retrieve_token_pairs;
+use crate::maintenance::retention_cutoff;
+use crate::mentions::{FirstMention, record_mention_in};
+use crate::router::route_addresses;
+use crate::token_info::Chain;
+
+/// DexScreener accepts up to 30 addresses per request
+const MAX_TOKENS_PER_REQUEST: usize = 30;
+
+/// EVM chains tried in turn for the CAs whose chain no link in the history tells
+const EVM_CHAIN_IDS: [&str; 4] = ["bsc", "base", "arbitrum", "polygon"];
+
+/// `result.json` of a chat exported from Telegram Desktop as machine-readable JSON
+#[derive(Debug, Deserialize)]
+struct ChatExport {
+    #[serde(default)]
+    messages: Vec<ExportedMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportedMessage {
+    /// `message` or `service`
+    #[serde(rename = "type")]
+    kind: String,
+    date_unixtime: Option<String>,
+    /// Display name of the sender, the export has no usernames
+    from: Option<String>,
+    #[serde(default)]
+    text_entities: Vec<TextEntity>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextEntity {
+    text: String,
+    /// Target of a hidden link
+    href: Option<String>,
+}
+
+/// A message of the history which may carry CAs
+#[derive(Debug, PartialEq, Eq)]
+pub struct PastMessage {
+    /// Display name of the sender
+    pub caller: Option<String>,
+    pub sent_at: DateTime<Utc>,
+    /// Text followed by the targets of its hidden links
+    pub text: String,
+}
+
+/// Messages of the export sent after `since`, oldest first
+fn parse_export(json: &str, since: DateTime<Utc>) -> anyhow::Result<Vec<PastMessage>> {
+    let export: ChatExport = serde_json::from_str(json)?;
+
+    let mut messages: Vec<PastMessage> = export
+        .messages
+        .into_iter()
+        .filter(|message| message.kind == "message")
+        .filter_map(|message| {
+            let sent_at = message
+                .date_unixtime?
+                .parse::<i64>()
+                .ok()
+                .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))?;
+            if sent_at <= since {
+                return None;
+            }
+
+            let text: String = message
+                .text_entities
+                .iter()
+                .map(|e| e.text.as_str())
+                .collect();
+            let hrefs = message
+                .text_entities
+                .iter()
+                .filter_map(|entity| entity.href.as_deref())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let text = format!("{text}\n{hrefs}");
+            if text.trim().is_empty() {
+                return None;
+            }
+
+            Some(PastMessage {
+                caller: message.from,
+                sent_at,
+                text,
+            })
+        })
+        .collect();
+    messages.sort_by_key(|message| message.sent_at);
+
+    Ok(messages)
+}
+
+/// A CA found in the history, the chain is unknown for EVM CAs without a link
+struct PastMention {
+    chain_id: Option<&'static str>,
+    token_ca: String,
+    caller: Option<String>,
+    sent_at: DateTime<Utc>,
+}
+
+async fn find_past_mentions(
+    messages: Vec<PastMessage>,
+    client: reqwest::Client,
+) -> Vec<PastMention> {
+    let mut mentions = Vec::new();
+    for message in messages {
+        let routed = route_addresses(&message.text, client.clone()).await;
+
+        let known_chain =
+            |chain_id: &'static str| move |token_ca: String| (Some(chain_id), token_ca);
+        let tokens = routed
+            .solana
+            .into_iter()
+            .map(known_chain("solana"))
+            .chain(routed.ton.into_iter().map(known_chain("ton")))
+            .chain(routed.tron.into_iter().map(known_chain("tron")))
+            .chain(routed.evm.into_iter().map(|token_ca| {
+                let chain = routed.evm_chains.get(&token_ca.to_lowercase());
+                (chain.map(Chain::dexscreener_id), token_ca)
+            }));
+
+        for (chain_id, token_ca) in tokens {
+            mentions.push(PastMention {
+                chain_id,
+                token_ca,
+                caller: message.caller.clone(),
+                sent_at: message.sent_at,
+            });
+        }
+    }
+
+    mentions
+}
+
+/// Chain id and symbol of the tokens DexScreener knows, by lowercased CA.
+/// Neither is in the export, the CAs of unknown EVM chains are tried on each of them.
+async fn resolve_tokens(
+    mentions: &[PastMention],
+    client: reqwest::Client,
+) -> HashMap<String, (&'static str, String)> {
+    let mut by_chain: HashMap<&'static str, Vec<&str>> = HashMap::new();
+    let mut unknown_chain: Vec<&str> = Vec::new();
+    for mention in mentions {
+        let token_cas = match mention.chain_id {
+            Some(chain_id) => by_chain.entry(chain_id).or_default(),
+            None => &mut unknown_chain,
+        };
+        if !token_cas.contains(&mention.token_ca.as_str()) {
+            token_cas.push(&mention.token_ca);
+        }
+    }
+
+    let mut resolved = HashMap::new();
+    for (chain_id, token_cas) in by_chain {
+        resolve_on_chain(chain_id, &token_cas, &mut resolved, client.clone()).await;
+    }
+    for chain_id in EVM_CHAIN_IDS {
+        unknown_chain.retain(|token_ca| !resolved.contains_key(&token_ca.to_lowercase()));
+        if unknown_chain.is_empty() {
+            break;
+        }
+        resolve_on_chain(chain_id, &unknown_chain, &mut resolved, client.clone()).await;
+    }
+
+    resolved
+}
+
+async fn resolve_on_chain(
+    chain_id: &'static str,
+    token_cas: &[&str],
+    resolved: &mut HashMap<String, (&'static str, String)>,
+    client: reqwest::Client,
+) {
+    for chunk in token_cas.chunks(MAX_TOKENS_PER_REQUEST) {
+        let pairs = match retrieve_token_pairs(chain_id, chunk, client.clone()).await {
+            Ok(pairs) => pairs,
+            Err(e) => {
+                warn!("Failed to resolve {chain_id} tokens of the history - {e:?}");
+                continue;
+            }
+        };
+
+        for pair in pairs {
+            // DexScreener may return EVM addresses checksummed differently
+            if chunk
+                .iter()
+                .any(|ca| ca.eq_ignore_ascii_case(&pair.base_token.address))
+            {
+                resolved.insert(
+                    pair.base_token.address.to_lowercase(),
+                    (chain_id, pair.base_token.symbol),
+                );
+            }
+        }
+    }
+}
+
+/// Records the CAs posted in the history as mentions in the chat,
+/// returns how many mentions of how many tokens were recorded
+async fn import_messages(
+    storage: &SqlitePool,
+    chat_id: ChatId,
+    messages: Vec<PastMessage>,
+    client: reqwest::Client,
+) -> (usize, usize) {
+    debug!(
+        "Importing {} messages of the history of {chat_id}",
+        messages.len()
+    );
+
+    let mentions = find_past_mentions(messages, client.clone()).await;
+    let tokens = resolve_tokens(&mentions, client).await;
+
+    let mut recorded = 0;
+    for mention in mentions {
+        let Some((chain_id, symbol)) = tokens.get(&mention.token_ca.to_lowercase()) else {
+            debug!(
+                "{} is unknown to DexScreener - skipping it",
+                mention.token_ca
+            );
+            continue;
+        };
+
+        // the mcap at the time of the call isn't in the history
+        let first = FirstMention {
+            mcap: None,
+            caller: mention.caller,
+            mentioned_at: mention.sent_at,
+            count: 1,
+        };
+        record_mention_in(storage, chat_id, chain_id, &mention.token_ca, symbol, first).await;
+        recorded += 1;
+    }
+
+    (recorded, tokens.len())
+}
+
+async fn read_export(
+    bot: &Bot,
+    document: &Document,
+    since: DateTime<Utc>,
+) -> anyhow::Result<Vec<PastMessage>> {
+    let file = bot.get_file(document.file.id.clone()).await?;
+    let mut json = Vec::new();
+    bot.download_file(&file.path, &mut json)
+        .await
+        .context("Failed to download the export")?;
+
+    parse_export(&String::from_utf8(json)?, since)
+}
+
+/// Handles `/import`, the caller must be checked to be an admin beforehand.
+/// Sent in reply to a chat export it imports the export, otherwise the companion
+/// of the `mtproto` build reads the history. Meant for a freshly whitelisted chat,
+/// importing the same history twice counts its mentions twice.
+pub async fn handle_import(
+    bot: &Bot,
+    message: &Message,
+    storage: &SqlitePool,
+    client: reqwest::Client,
+) {
+    let chat_id = message.chat.id;
+    let retention_days = APP_CONFIG
+        .get()
+        .unwrap()
+        .app_config()
+        .retention
+        .call_history_days;
+    let since = retention_cutoff(Utc::now(), retention_days);
+
+    let history = match message.reply_to_message().and_then(Message::document) {
+        Some(document) => read_export(bot, document, since).await,
+        #[cfg(feature = "mtproto")]
+        None if is_companion_running() => read_history(chat_id, since).await,
+        None => {
+            let usage =
+                "Reply with /import to the result.json of this chat exported from Telegram Desktop";
+            if let Err(e) = bot.send_message(chat_id, usage).await {
+                warn!("Failed to reply to /import - {e:?}");
+            }
+            return;
+        }
+    };
+
+    let text = match history {
+        Ok(messages) => {
+            let (mentions, tokens) = import_messages(storage, chat_id, messages, client).await;
+            info!("Imported {mentions} mentions of {tokens} tokens into {chat_id}");
+            format!("Imported {mentions} mentions of {tokens} tokens")
+        }
+        Err(e) => {
+            warn!("Failed to read the history of {chat_id} - {e:?}");
+            format!("Failed to import the history - {e:#}")
+        }
+    };
+
+    if let Err(e) = bot.send_message(chat_id, text).await {
+        warn!("Failed to reply to /import - {e:?}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_export() {
+        let json = r#"{
+            "name": "Calls",
+            "type": "public_supergroup",
+            "id": 1234567890,
+            "messages": [
+                {
+                    "id": 2, "type": "message", "date_unixtime": "1760000200", "from": "Bob",
+                    "text": "", "text_entities": [
+                        {"type": "plain", "text": "aped "},
+                        {"type": "text_link", "text": "this", "href": "https://dexscreener.com/solana/abc"}
+                    ]
+                },
+                {
+                    "id": 1, "type": "message", "date_unixtime": "1760000100", "from": "Alice",
+                    "text": "gm", "text_entities": [{"type": "plain", "text": "gm"}]
+                },
+                {
+                    "id": 3, "type": "service", "date_unixtime": "1760000300", "actor": "Carol",
+                    "action": "invite_members", "text": "", "text_entities": []
+                },
+                {
+                    "id": 4, "type": "message", "date_unixtime": "1760000400", "from": "Dave",
+                    "photo": "photos/photo_1.jpg", "text": "", "text_entities": []
+                },
+                {
+                    "id": 5, "type": "message", "date_unixtime": "1000", "from": "Eve",
+                    "text": "old", "text_entities": [{"type": "plain", "text": "old"}]
+                }
+            ]
+        }"#;
+        let since = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        let messages = parse_export(json, since).unwrap();
+
+        assert_eq!(
+            messages,
+            vec![
+                PastMessage {
+                    caller: Some("Alice".to_owned()),
+                    sent_at: DateTime::from_timestamp(1_760_000_100, 0).unwrap(),
+                    text: "gm\n".to_owned(),
+                },
+                PastMessage {
+                    caller: Some("Bob".to_owned()),
+                    sent_at: DateTime::from_timestamp(1_760_000_200, 0).unwrap(),
+                    text: "aped this\nhttps://dexscreener.com/solana/abc".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_non_export() {
+        let since = DateTime::<Utc>::MIN_UTC;
+
+        assert!(parse_export("not json", since).is_err());
+        assert_eq!(parse_export("{}", since).unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,87 @@
+use log::debug;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::APP_CONFIG;
+use crate::token_info::{JupiterTokenStats, SolanaTokenInfo, TokenInfoError};
+
+#[derive(Debug, Deserialize)]
+struct BirdeyeResponse {
+    data: Option<BirdeyeTokenOverview>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BirdeyeTokenOverview {
+    address: String,
+    name: Option<String>,
+    symbol: Option<String>,
+    price: Option<Decimal>,
+    #[serde(alias = "mc")]
+    market_cap: Option<Decimal>,
+    fdv: Option<Decimal>,
+    liquidity: Option<Decimal>,
+    price_change24h_percent: Option<Decimal>,
+    #[serde(rename = "vBuy24hUSD")]
+    buy_volume_24h: Option<Decimal>,
+    #[serde(rename = "vSell24hUSD")]
+    sell_volume_24h: Option<Decimal>,
+    holder: Option<u64>,
+}
+
+/// Solana token overview from Birdeye, used when Jupiter doesn't know a fresh mint yet.
+/// Birdeye has no launchpad, audit and socials data.
+pub async fn fetch_birdeye_token_info(
+    token_ca: &str,
+    api_key: &str,
+    client: reqwest::Client,
+) -> anyhow::Result<SolanaTokenInfo> {
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+    let url = format!(
+        "{}/defi/token_overview?address={token_ca}",
+        app_config.provider_urls.birdeye
+    );
+    debug!("Going to hit url - {url}");
+
+    let response = client
+        .get(url)
+        .header("X-API-KEY", api_key)
+        .header("x-chain", "solana")
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<BirdeyeResponse>()
+        .await?;
+
+    let Some(overview) = response.data.filter(|overview| overview.symbol.is_some()) else {
+        debug!("Token {token_ca} not found on Birdeye");
+        return Err(TokenInfoError::NotFound.into());
+    };
+
+    Ok(SolanaTokenInfo {
+        id: overview.address,
+        name: overview.name.unwrap_or_default(),
+        symbol: overview.symbol.unwrap_or_default(),
+        launchpad: None,
+        mcap: overview.market_cap,
+        fdv: overview.fdv,
+        price_usd: overview.price,
+        liquidity: overview.liquidity,
+        stats_24h: Some(JupiterTokenStats {
+            price_change: overview.price_change24h_percent,
+            buy_volume: overview.buy_volume_24h,
+            sell_volume: overview.sell_volume_24h,
+        }),
+        tags: Vec::new(),
+        audit: None,
+        twitter: None,
+        website: None,
+        telegram: None,
+        first_pool: None,
+        holders: overview.holder,
+        bonding_curve: None,
+        graduated_pool: None,
+        is_verified: None,
+        organic_score: None,
+    })
+}
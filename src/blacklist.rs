@@ -0,0 +1,107 @@
+use log::{debug, info, warn};
+use teloxide::Bot;
+use teloxide::prelude::Requester;
+use teloxide::types::{ChatId, Message};
+
+use crate::APP_CONFIG;
+use crate::aliases::{is_chat_admin, is_token_ca};
+use crate::config::persist_chat_blacklist;
+
+const USAGE: &str = "Usage: /blacklist add <ca> | /blacklist remove <ca> | /blacklist list";
+
+/// Blacklisted tokens are skipped before any lookup is made
+pub fn is_blacklisted(chat_id: ChatId, token_ca: &str) -> bool {
+    let ChatId(id) = chat_id;
+    APP_CONFIG
+        .get()
+        .unwrap()
+        .app_config()
+        .chats
+        .get(&id)
+        .is_some_and(|chat| {
+            chat.blacklisted_cas
+                .iter()
+                .any(|ca| ca.eq_ignore_ascii_case(token_ca))
+        })
+}
+
+/// Handles `/blacklist`, only chat admins may change the blacklist
+pub async fn handle_blacklist(bot: &Bot, message: &Message, args: &str) {
+    let mut args = args.split_whitespace();
+    let action = args.next().unwrap_or_default().to_lowercase();
+    let token_ca = args.next();
+    let chat_id = message.chat.id;
+
+    let text = match (action.as_str(), token_ca, args.next()) {
+        ("list", None, None) => list_blacklist(chat_id),
+        ("add" | "remove", Some(_), None) if !is_chat_admin(bot, message).await => {
+            debug!("Skipping /blacklist {action} from a non-admin user");
+            return;
+        }
+        ("add", Some(token_ca), None) if is_token_ca(token_ca) => {
+            update_blacklist(chat_id, |cas| {
+                if cas.iter().any(|ca| ca.eq_ignore_ascii_case(token_ca)) {
+                    return format!("{token_ca} is already blacklisted");
+                }
+                cas.push(token_ca.to_owned());
+                format!("{token_ca} added to the blacklist")
+            })
+        }
+        ("remove", Some(token_ca), None) => update_blacklist(chat_id, |cas| {
+            let len = cas.len();
+            cas.retain(|ca| !ca.eq_ignore_ascii_case(token_ca));
+            if cas.len() == len {
+                format!("{token_ca} is not blacklisted")
+            } else {
+                format!("{token_ca} removed from the blacklist")
+            }
+        }),
+        _ => USAGE.to_owned(),
+    };
+
+    if let Err(e) = bot.send_message(chat_id, text).await {
+        warn!("Failed to reply to /blacklist - {e:?}");
+    }
+}
+
+fn list_blacklist(chat_id: ChatId) -> String {
+    let ChatId(id) = chat_id;
+    let cfg = APP_CONFIG.get().unwrap().app_config();
+    let cas = cfg
+        .chats
+        .get(&id)
+        .map(|chat| chat.blacklisted_cas.join("\n"))
+        .unwrap_or_default();
+
+    if cas.is_empty() {
+        return "Blacklist is empty".to_owned();
+    }
+
+    format!("Blacklisted tokens:\n{cas}")
+}
+
+fn update_blacklist(chat_id: ChatId, update: impl FnOnce(&mut Vec<String>) -> String) -> String {
+    let ChatId(id) = chat_id;
+    let cfg = APP_CONFIG.get().unwrap();
+    let mut reply = String::new();
+
+    cfg.update_app_config(|config| {
+        let chat = config.chats.entry(id).or_default();
+        reply = update(&mut chat.blacklisted_cas);
+    });
+
+    info!("{reply} in {chat_id}");
+
+    let blacklisted_cas = cfg
+        .app_config()
+        .chats
+        .get(&id)
+        .map(|chat| chat.blacklisted_cas.clone())
+        .unwrap_or_default();
+    if let Err(e) = persist_chat_blacklist(&cfg.config_path, id, &blacklisted_cas) {
+        warn!("Failed to persist blacklist of {chat_id} - {e:?}");
+        reply.push_str(" (not saved to the config file, it will be lost on restart)");
+    }
+
+    reply
+}
@@ -0,0 +1,361 @@
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use log::{info, warn};
+use moka::future::Cache;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use teloxide::types::{ChatId, MessageId, ThreadId};
+
+use crate::followups::CardToken;
+use crate::storage::{load_token_infos, save_token_infos};
+use crate::token_info::{EvmTokenInfo, SolanaTokenInfo, TonTokenInfo, TronTokenInfo};
+use crate::unlocks::UnlockSchedule;
+
+const BYTES_IN_MB: u64 = 1024 * 1024;
+
+const STATS_REPORT_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Share of the global memory cap (in percents) each cache may use
+const TRANSLATIONS_SHARE: u64 = 5;
+
+const TOKEN_INFO_SHARE: u64 = 20;
+
+const UNLOCKS_SHARE: u64 = 5;
+
+const CARDS_SHARE: u64 = 5;
+
+const FULL_CARDS_SHARE: u64 = 5;
+
+const POSTED_MCAPS_SHARE: u64 = 2;
+
+const RECENT_TEXTS_SHARE: u64 = 1;
+
+const TRANSLATIONS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+const UNLOCKS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+const CARDS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Same as the longest throttle window chats may pick, older cards aren't throttled anyway
+const POSTED_MCAPS_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Rough size of a single unlock schedule entry
+const UNLOCK_ENTRY_WEIGHT: usize = 128;
+
+/// Size-bounded cache which keeps hit/miss/eviction counters
+pub struct MeteredCache<K, V> {
+    name: &'static str,
+    inner: Cache<K, V>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: Arc<AtomicU64>,
+}
+
+impl<K, V> MeteredCache<K, V>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    /// `max_weight` is the cap of the summed weights (approximate bytes) of all entries
+    pub fn new(
+        name: &'static str,
+        max_weight: u64,
+        ttl: Duration,
+        weigher: impl Fn(&K, &V) -> u32 + Send + Sync + 'static,
+    ) -> Self {
+        let evictions = Arc::new(AtomicU64::new(0));
+        let listener_evictions = evictions.clone();
+
+        let inner = Cache::builder()
+            .name(name)
+            .max_capacity(max_weight)
+            .weigher(weigher)
+            .time_to_live(ttl)
+            .eviction_listener(move |_, _, cause| {
+                if cause.was_evicted() {
+                    listener_evictions.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+            .build();
+
+        Self {
+            name,
+            inner,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions,
+        }
+    }
+
+    pub async fn get(&self, key: &K) -> Option<V> {
+        let value = self.inner.get(key).await;
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    pub async fn insert(&self, key: K, value: V) {
+        self.inner.insert(key, value).await;
+    }
+
+    /// Live entries of the cache
+    pub fn entries(&self) -> Vec<(K, V)>
+    where
+        K: Clone,
+    {
+        self.inner
+            .iter()
+            .map(|(key, value)| (K::clone(&key), value))
+            .collect()
+    }
+
+    /// Drops the expired entries right away instead of waiting for the cache to do it lazily
+    pub async fn run_pending_tasks(&self) {
+        self.inner.run_pending_tasks().await;
+    }
+
+    pub fn log_stats(&self) {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        let hit_rate = if lookups > 0 {
+            hits as f64 * 100.0 / lookups as f64
+        } else {
+            0.0
+        };
+
+        info!(
+            "Cache {} - {} entries, {} weight, hit rate {hit_rate:.1}% ({hits}/{lookups}), {} evictions",
+            self.name,
+            self.inner.entry_count(),
+            self.inner.weighted_size(),
+            self.evictions.load(Ordering::Relaxed),
+        );
+    }
+}
+
+/// Successful token info lookup of any supported chain
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum CachedTokenInfo {
+    Evm(EvmTokenInfo),
+    Solana(SolanaTokenInfo),
+    Ton(TonTokenInfo),
+    Tron(TronTokenInfo),
+}
+
+impl CachedTokenInfo {
+    /// Approximate number of bytes the entry occupies
+    fn weight(&self) -> usize {
+        let strings = match self {
+            CachedTokenInfo::Evm(info) => [&info.id, &info.name, &info.symbol],
+            CachedTokenInfo::Solana(info) => [&info.id, &info.name, &info.symbol],
+            CachedTokenInfo::Ton(info) => [&info.id, &info.name, &info.symbol],
+            CachedTokenInfo::Tron(info) => [&info.id, &info.name, &info.symbol],
+        };
+        size_of::<Self>() + strings.iter().map(|s| s.len()).sum::<usize>()
+    }
+}
+
+/// Chain and token CA
+pub type TokenInfoKey = (&'static str, String);
+
+/// Chains of the token info keys, for reading the keys back from storage
+const TOKEN_INFO_CHAINS: [&str; 7] = [
+    "solana", "ton", "tron", "bsc", "base", "arbitrum", "polygon",
+];
+
+/// All in-memory caches of the bot, sharing the global memory cap
+pub struct Caches {
+    /// Token name -> english translation
+    pub translations: MeteredCache<String, String>,
+    /// Recent token info lookups shared by all chats
+    pub token_info: MeteredCache<TokenInfoKey, CachedTokenInfo>,
+    /// The whole DefiLlama unlock schedule, refreshed daily
+    pub unlocks: MeteredCache<(), Arc<UnlockSchedule>>,
+    /// Tokens shown in the sent cards, for answering replies to them
+    pub cards: MeteredCache<(ChatId, MessageId), Arc<Vec<CardToken>>>,
+    /// Full text of the sent compact cards, shown by their "More" button
+    pub full_cards: MeteredCache<(ChatId, MessageId), String>,
+    /// Mcap shown in the latest card of the token in the chat/thread
+    pub posted_mcaps: MeteredCache<PostedCardKey, Decimal>,
+    /// Hashes of the normalized texts recently seen in the chat
+    pub recent_texts: MeteredCache<(ChatId, u64), ()>,
+}
+
+/// Token CA, chat and thread the card was posted to
+pub type PostedCardKey = (String, ChatId, Option<ThreadId>);
+
+impl Caches {
+    pub async fn run_pending_tasks(&self) {
+        self.translations.run_pending_tasks().await;
+        self.token_info.run_pending_tasks().await;
+        self.unlocks.run_pending_tasks().await;
+        self.cards.run_pending_tasks().await;
+        self.full_cards.run_pending_tasks().await;
+        self.posted_mcaps.run_pending_tasks().await;
+        self.recent_texts.run_pending_tasks().await;
+    }
+
+    fn log_stats(&self) {
+        self.translations.log_stats();
+        self.token_info.log_stats();
+        self.unlocks.log_stats();
+        self.cards.log_stats();
+        self.full_cards.log_stats();
+        self.posted_mcaps.log_stats();
+        self.recent_texts.log_stats();
+    }
+}
+
+static CACHES: OnceLock<Caches> = OnceLock::new();
+
+fn weigh_strings(key: &str, value: &str) -> u32 {
+    (key.len() + value.len()).try_into().unwrap_or(u32::MAX)
+}
+
+pub fn init_caches(memory_limit_mb: u64, token_info_ttl: Duration, recent_texts_ttl: Duration) {
+    let memory_limit = memory_limit_mb * BYTES_IN_MB;
+    let share = |percents: u64| memory_limit * percents / 100;
+
+    let caches = Caches {
+        translations: MeteredCache::new(
+            "translations",
+            share(TRANSLATIONS_SHARE),
+            TRANSLATIONS_TTL,
+            |key: &String, value: &String| weigh_strings(key, value),
+        ),
+        token_info: MeteredCache::new(
+            "token_info",
+            // zero TTL means token info must not be cached at all
+            if token_info_ttl.is_zero() {
+                0
+            } else {
+                share(TOKEN_INFO_SHARE)
+            },
+            token_info_ttl,
+            |(_, token_ca): &TokenInfoKey, value: &CachedTokenInfo| {
+                (token_ca.len() + value.weight())
+                    .try_into()
+                    .unwrap_or(u32::MAX)
+            },
+        ),
+        unlocks: MeteredCache::new(
+            "unlocks",
+            share(UNLOCKS_SHARE),
+            UNLOCKS_TTL,
+            |_: &(), schedule: &Arc<UnlockSchedule>| {
+                (schedule.len() * UNLOCK_ENTRY_WEIGHT)
+                    .try_into()
+                    .unwrap_or(u32::MAX)
+            },
+        ),
+        cards: MeteredCache::new(
+            "cards",
+            share(CARDS_SHARE),
+            CARDS_TTL,
+            |_: &(ChatId, MessageId), tokens: &Arc<Vec<CardToken>>| {
+                tokens
+                    .iter()
+                    .map(|token| size_of::<CardToken>() + token.token_ca.len())
+                    .sum::<usize>()
+                    .try_into()
+                    .unwrap_or(u32::MAX)
+            },
+        ),
+        full_cards: MeteredCache::new(
+            "full_cards",
+            share(FULL_CARDS_SHARE),
+            CARDS_TTL,
+            |_: &(ChatId, MessageId), text: &String| {
+                (size_of::<(ChatId, MessageId)>() + text.len())
+                    .try_into()
+                    .unwrap_or(u32::MAX)
+            },
+        ),
+        posted_mcaps: MeteredCache::new(
+            "posted_mcaps",
+            share(POSTED_MCAPS_SHARE),
+            POSTED_MCAPS_TTL,
+            |(token_ca, _, _): &PostedCardKey, _: &Decimal| {
+                (size_of::<PostedCardKey>() + size_of::<Decimal>() + token_ca.len())
+                    .try_into()
+                    .unwrap_or(u32::MAX)
+            },
+        ),
+        recent_texts: MeteredCache::new(
+            "recent_texts",
+            // zero TTL means the dedupe is disabled
+            if recent_texts_ttl.is_zero() {
+                0
+            } else {
+                share(RECENT_TEXTS_SHARE)
+            },
+            recent_texts_ttl,
+            |_: &(ChatId, u64), _: &()| size_of::<(ChatId, u64)>().try_into().unwrap_or(u32::MAX),
+        ),
+    };
+
+    CACHES
+        .set(caches)
+        .unwrap_or_else(|_| panic!("init_caches must be called just once in the main fn"));
+}
+
+pub fn caches() -> &'static Caches {
+    CACHES.get().unwrap()
+}
+
+/// Persists the token info cache so a restart doesn't look the discussed tokens up again
+pub async fn save_token_info_cache(storage: &SqlitePool) {
+    let token_infos = caches().token_info.entries();
+    if let Err(e) = save_token_infos(storage, &token_infos).await {
+        warn!("Failed to save token info cache to storage - {e:?}");
+    }
+}
+
+/// Warms the token info cache up with the entries saved within the last `ttl`.
+/// They are up to `ttl` old already, so live up to twice as long as fresh ones.
+pub async fn restore_token_info_cache(storage: &SqlitePool, ttl: Duration) {
+    let since = Utc::now() - TimeDelta::from_std(ttl).unwrap_or_default();
+    let token_infos = match load_token_infos(storage, since).await {
+        Ok(token_infos) => token_infos,
+        Err(e) => {
+            warn!("Failed to load token info cache from storage - {e:?}");
+            return;
+        }
+    };
+
+    let mut restored = 0;
+    for (chain_id, token_ca, info) in token_infos {
+        let Some(chain_id) = TOKEN_INFO_CHAINS
+            .into_iter()
+            .find(|chain| *chain == chain_id)
+        else {
+            continue;
+        };
+        caches().token_info.insert((chain_id, token_ca), info).await;
+        restored += 1;
+    }
+    info!("Loaded {restored} token infos from storage");
+}
+
+pub fn spawn_cache_stats_reporter() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(STATS_REPORT_INTERVAL);
+        // the first tick completes immediately
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            caches().log_stats();
+        }
+    });
+}
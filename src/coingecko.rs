@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use log::debug;
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::APP_CONFIG;
+use crate::token_info::Chain;
+
+const SOLANA_PLATFORM: &str = "solana";
+
+#[derive(Debug, Deserialize)]
+struct CoinInfo {
+    /// Platform the coin was originally issued on
+    asset_platform_id: Option<String>,
+    /// Platform -> contract address of the coin on it, including bridged versions
+    #[serde(default)]
+    platforms: HashMap<String, Option<String>>,
+}
+
+fn coingecko_platform_id(chain: Chain) -> &'static str {
+    match chain {
+        Chain::Bsc => "binance-smart-chain",
+        Chain::Base => "base",
+        Chain::Arbitrum => "arbitrum-one",
+        Chain::Polygon => "polygon-pos",
+    }
+}
+
+/// Returns the CA of the Solana original if the EVM token is a bridged/wrapped version of it
+pub async fn retrieve_solana_origin(
+    token_ca: &str,
+    chain: Chain,
+    client: reqwest::Client,
+) -> anyhow::Result<Option<String>> {
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+    let url = format!(
+        "{}/api/v3/coins/{}/contract/{token_ca}",
+        app_config.provider_urls.coingecko,
+        coingecko_platform_id(chain)
+    );
+    debug!("Going to hit url - {url}");
+
+    let response = client.get(url).send().await?;
+
+    // CoinGecko doesn't list most of the fresh tokens
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    let coin = response.error_for_status()?.json::<CoinInfo>().await?;
+
+    if coin.asset_platform_id.as_deref() != Some(SOLANA_PLATFORM) {
+        return Ok(None);
+    }
+
+    Ok(coin
+        .platforms
+        .get(SOLANA_PLATFORM)
+        .cloned()
+        .flatten()
+        .filter(|ca| !ca.is_empty()))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoinMarket {
+    pub id: String,
+    pub current_price: Option<Decimal>,
+    pub price_change_percentage_1h_in_currency: Option<Decimal>,
+}
+
+/// Current USD prices and 1h changes of the coins with the given CoinGecko ids
+pub async fn retrieve_coin_markets(
+    ids: &[&str],
+    client: reqwest::Client,
+) -> anyhow::Result<Vec<CoinMarket>> {
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+    let url = format!(
+        "{}/api/v3/coins/markets?vs_currency=usd&ids={}&price_change_percentage=1h",
+        app_config.provider_urls.coingecko,
+        ids.join(",")
+    );
+    debug!("Going to hit url - {url}");
+
+    Ok(client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<CoinMarket>>()
+        .await?)
+}
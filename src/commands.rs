@@ -0,0 +1,280 @@
+use std::sync::atomic::Ordering;
+
+use log::{debug, warn};
+use sqlx::SqlitePool;
+use teloxide::Bot;
+use teloxide::prelude::{Requester, ResponseResult};
+use teloxide::types::{Message, User};
+use teloxide::utils::command::BotCommands;
+
+use crate::aliases::handle_alias;
+use crate::backfill::handle_import;
+use crate::blacklist::handle_blacklist;
+use crate::context::MessageContext;
+use crate::deep_links::verify_start_parameter;
+use crate::delivery::register_private_chat_user;
+use crate::quota::handle_quota;
+use crate::settings::handle_settings;
+use crate::stats::handle_stats;
+use crate::token_lists::resolve_symbol;
+use crate::trending::send_trending;
+use crate::user_limits::{record_user_cards, user_card_budget};
+use crate::watchlist::{handle_alerts, handle_unwatch, handle_watch};
+use crate::whitelist::handle_whitelist;
+use crate::{
+    APP_CONFIG, Cache, is_allowed_private_chat, is_message_too_old, is_paused_chat,
+    is_whitelisted_chat, process_token_ca, send_cards,
+};
+
+#[derive(BotCommands, Clone, Debug)]
+#[command(rename_rule = "lowercase", description = "These commands are supported:")]
+pub enum Command {
+    #[command(description = "start a private chat with the bot to receive alerts via DM")]
+    Start(String),
+    #[command(
+        description = "list tokens boosted on DexScreener (paid promotions) - /trending [sol|bsc|base|ton|tron]"
+    )]
+    Trending(String),
+    #[command(
+        description = "manage whitelisted chats (admins only) - /whitelist add|remove|pause|resume|archive|list"
+    )]
+    Whitelist(String),
+    #[command(description = "reload the config (admins only)")]
+    Reload,
+    #[command(description = "provider usage today and the remaining quota (admins only)")]
+    Quota,
+    #[command(
+        description = "seed the call history of this chat from its Telegram Desktop export (admins only) - reply to result.json"
+    )]
+    Import,
+    #[command(
+        description = "map phrases to tokens - /alias add <phrase> <ca> | remove <phrase> | list"
+    )]
+    Alias(String),
+    #[command(description = "skip tokens in this chat - /blacklist add <ca> | remove <ca> | list")]
+    Blacklist(String),
+    #[command(description = "leaderboard of the best token callers in this chat")]
+    Stats,
+    #[command(description = "alert me when the token hits the target - /watch <ca> 2x|500k [--dm]")]
+    Watch(String),
+    #[command(description = "remove my alert on the token - /unwatch <ca>")]
+    Unwatch(String),
+    #[command(description = "list my alerts in this chat")]
+    Alerts,
+    #[command(description = "change the settings of this chat (chat admins only)")]
+    Settings,
+    #[command(description = "look up a token even if it was shown recently - /ca <address>")]
+    Ca(String),
+    #[command(description = "look up a token by its ticker - /t <ticker>")]
+    T(String),
+}
+
+pub async fn command_handler(
+    bot: Bot,
+    message: Message,
+    command: Command,
+    client: reqwest::Client,
+    cache: Cache,
+    storage: SqlitePool,
+) -> ResponseResult<()> {
+    debug!("Got command {command:?} in {message:?}");
+
+    if is_message_too_old(&message) {
+        debug!("Command is too old - skipping it");
+        return Ok(());
+    }
+
+    if let Command::Start(payload) = command {
+        handle_start(&bot, &message, client, &cache, &storage, &payload).await;
+        return Ok(());
+    }
+
+    let app_cfg = APP_CONFIG.get().unwrap();
+
+    // deliberate lookups work in the private chats served apart from the whitelist as well
+    if let Command::Ca(_) | Command::T(_) = command
+        && message.chat.is_private()
+    {
+        if is_allowed_private_chat(&message, app_cfg) {
+            handle_lookup(&bot, &message, client, &cache, &storage, command).await;
+        } else {
+            debug!("Skipping lookup since private chats aren't allowed for this user");
+        }
+        return Ok(());
+    }
+
+    // admin commands work in any chat, including not yet whitelisted ones
+    if let Command::Whitelist(_) | Command::Reload | Command::Quota | Command::Import = command {
+        match &message.from {
+            Some(user) if app_cfg.is_admin(user) => {
+                handle_admin_command(&bot, &message, &storage, client, command).await
+            }
+            _ => debug!("Skipping admin command from a non-admin user"),
+        }
+        return Ok(());
+    }
+
+    if !is_whitelisted_chat(&message.chat, app_cfg) {
+        debug!("Skipping command since it is not coming from whitelisted chat");
+        return Ok(());
+    }
+
+    if is_paused_chat(&message.chat, app_cfg) {
+        debug!("Skipping command since the chat is paused");
+        return Ok(());
+    }
+
+    match command {
+        Command::Start(_)
+        | Command::Whitelist(_)
+        | Command::Reload
+        | Command::Quota
+        | Command::Import => {}
+        Command::Trending(chain) => send_trending(&bot, &message, client, &chain).await,
+        Command::Alias(args) => handle_alias(&bot, &message, &storage, &args).await,
+        Command::Blacklist(args) => handle_blacklist(&bot, &message, &args).await,
+        Command::Stats => handle_stats(&bot, &message, &storage).await,
+        Command::Watch(args) => handle_watch(&bot, &message, &storage, client, &args).await,
+        Command::Unwatch(args) => handle_unwatch(&bot, &message, &storage, &args).await,
+        Command::Alerts => handle_alerts(&bot, &message, &storage).await,
+        Command::Settings => handle_settings(&bot, &message).await,
+        Command::Ca(_) | Command::T(_) => {
+            handle_lookup(&bot, &message, client, &cache, &storage, command).await;
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_admin_command(
+    bot: &Bot,
+    message: &Message,
+    storage: &SqlitePool,
+    client: reqwest::Client,
+    command: Command,
+) {
+    match command {
+        Command::Whitelist(args) => handle_whitelist(bot, message, storage, &args).await,
+        Command::Reload => handle_reload(bot, message).await,
+        Command::Quota => handle_quota(bot, message, storage).await,
+        Command::Import => handle_import(bot, message, storage, client).await,
+        Command::Start(_)
+        | Command::Trending(_)
+        | Command::Alias(_)
+        | Command::Blacklist(_)
+        | Command::Stats
+        | Command::Watch(_)
+        | Command::Unwatch(_)
+        | Command::Alerts
+        | Command::Settings
+        | Command::Ca(_)
+        | Command::T(_) => {}
+    }
+}
+
+/// Handles `/ca <address>` and `/t <ticker>`, both bypass the token throttle
+async fn handle_lookup(
+    bot: &Bot,
+    message: &Message,
+    client: reqwest::Client,
+    cache: &Cache,
+    storage: &SqlitePool,
+    command: Command,
+) {
+    let (query, usage) = match &command {
+        Command::Ca(address) => (address.trim(), "Usage: /ca <address>"),
+        Command::T(ticker) => (ticker.trim().trim_start_matches('$'), "Usage: /t <ticker>"),
+        _ => return,
+    };
+
+    let card_budget = user_card_budget(message.chat.id, message.from.as_ref());
+    let reply = if query.is_empty() {
+        Some(usage.to_owned())
+    } else if card_budget == Some(0) {
+        Some("Too many lookups recently, try again later".to_owned())
+    } else {
+        let token_cas = match command {
+            Command::T(_) => resolve_symbol(storage, query, client.clone())
+                .await
+                .join(" "),
+            _ => query.to_owned(),
+        };
+
+        let mut ctx =
+            MessageContext::new(bot.clone(), message, client, cache.clone(), storage.clone());
+        ctx.bypass_throttle = true;
+        let mut cards = process_token_ca(&ctx, &token_cas).await;
+        if let Some(budget) = card_budget {
+            cards.truncate(budget);
+        }
+        record_user_cards(ctx.chat_id(), message.from.as_ref(), cards.len());
+        if cards.is_empty() && ctx.out_of_mcap_range.load(Ordering::Relaxed) > 0 {
+            Some(format!("{query} is outside this chat's mcap range"))
+        } else if cards.is_empty() {
+            Some(format!("No token found for {query}"))
+        } else {
+            send_cards(&ctx, cards).await;
+            None
+        }
+    };
+
+    if let Some(reply) = reply
+        && let Err(e) = bot.send_message(message.chat.id, reply).await
+    {
+        warn!("Failed to reply to {command:?} - {e:?}");
+    }
+}
+
+async fn handle_reload(bot: &Bot, message: &Message) {
+    let text = match APP_CONFIG.get().unwrap().reload() {
+        Ok(()) => "Config reloaded".to_owned(),
+        Err(e) => {
+            warn!("Failed to reload config - {e:?}");
+            format!("Failed to reload config, keeping the current one - {e}")
+        }
+    };
+
+    if let Err(e) = bot.send_message(message.chat.id, text).await {
+        warn!("Failed to reply to /reload - {e:?}");
+    }
+}
+
+/// Registers the user for DM alerts, `/start <payload>` coming from
+/// an "open in DM" deep link also delivers the token card
+async fn handle_start(
+    bot: &Bot,
+    message: &Message,
+    client: reqwest::Client,
+    cache: &Cache,
+    storage: &SqlitePool,
+    payload: &str,
+) {
+    if !message.chat.is_private() {
+        return;
+    }
+
+    let Some(User { id, .. }) = message.from else {
+        return;
+    };
+
+    register_private_chat_user(storage, id).await;
+
+    if !payload.is_empty() {
+        let Some(token_ca) = verify_start_parameter(payload) else {
+            warn!("Got /start with forged or corrupted payload {payload}");
+            return;
+        };
+
+        let ctx = MessageContext::new(bot.clone(), message, client, cache.clone(), storage.clone());
+        let cards = process_token_ca(&ctx, token_ca).await;
+        send_cards(&ctx, cards).await;
+        return;
+    }
+
+    if let Err(e) = bot
+        .send_message(message.chat.id, "Hi! From now on I can deliver your alerts here.")
+        .await
+    {
+        warn!("Failed to reply to /start - {e:?}");
+    }
+}
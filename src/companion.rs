@@ -0,0 +1,460 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use anyhow::{Context, anyhow, bail};
+use chrono::{DateTime, Utc};
+use grammers_client::client::{UpdateStream, UpdatesConfiguration};
+use grammers_client::peer::Peer;
+use grammers_client::session::types::{
+    ChannelState, DcOption, PeerId, PeerInfo, PeerRef, UpdateState, UpdatesState,
+};
+use grammers_client::session::{BoxFuture, Session, SessionData};
+use grammers_client::update::Update;
+use grammers_client::{Client, SenderPool, SignInError, tl};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use teloxide::Bot;
+use teloxide::payloads::SendMessageSetters;
+use teloxide::prelude::Requester;
+use teloxide::sugar::request::RequestLinkPreviewExt;
+use teloxide::types::ChatId;
+
+use crate::backfill::PastMessage;
+use crate::config::{CompanionConfig, RelayedChannel};
+use crate::formatter::telegram_text;
+use crate::inline::{InlineCard, lookup_inline_card};
+use crate::render::render_card;
+use crate::router::route_addresses;
+use crate::settings::chat_settings;
+use crate::{APP_CONFIG, Cache, read_secret};
+
+/// Signed-in user account, set once the companion is started
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// What [`FileSession`] keeps on disk, peers are keyed by their own ids
+#[derive(Serialize, Deserialize)]
+struct StoredSession {
+    home_dc: i32,
+    dc_options: Vec<DcOption>,
+    peers: Vec<PeerInfo>,
+    updates_state: UpdatesState,
+}
+
+/// Session saved as JSON on every change, so the account stays signed in across restarts
+struct FileSession {
+    path: PathBuf,
+    data: Mutex<SessionData>,
+}
+
+impl FileSession {
+    /// Starts with the known datacenters when the file is missing
+    fn open(path: PathBuf) -> anyhow::Result<Self> {
+        let mut data = SessionData::default();
+        match std::fs::read_to_string(&path) {
+            Ok(json) => {
+                let stored: StoredSession = serde_json::from_str(&json)
+                    .with_context(|| format!("Invalid companion session {path:?}"))?;
+                data.home_dc = stored.home_dc;
+                data.dc_options.extend(
+                    stored
+                        .dc_options
+                        .into_iter()
+                        .map(|dc_option| (dc_option.id, dc_option)),
+                );
+                data.peer_infos = stored
+                    .peers
+                    .into_iter()
+                    .map(|peer| (peer.id(), peer))
+                    .collect();
+                data.updates_state = stored.updates_state;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read companion session {path:?}"));
+            }
+        }
+
+        Ok(Self {
+            path,
+            data: Mutex::new(data),
+        })
+    }
+
+    fn data(&self) -> io::Result<MutexGuard<'_, SessionData>> {
+        self.data
+            .lock()
+            .map_err(|_| io::Error::other("Companion session lock is poisoned"))
+    }
+
+    /// Applies the change and saves the session if it returns whether anything changed
+    fn update(&self, change: impl FnOnce(&mut SessionData) -> bool) -> io::Result<()> {
+        let mut data = self.data()?;
+        if !change(&mut data) {
+            return Ok(());
+        }
+
+        let stored = StoredSession {
+            home_dc: data.home_dc,
+            dc_options: data.dc_options.values().cloned().collect(),
+            peers: data.peer_infos.values().cloned().collect(),
+            updates_state: data.updates_state.clone(),
+        };
+        // written aside first, a crash mid-write would sign the account out
+        let temp_path = self.path.with_extension("tmp");
+        std::fs::write(&temp_path, serde_json::to_vec(&stored)?)?;
+        std::fs::rename(&temp_path, &self.path)
+    }
+}
+
+impl Session for FileSession {
+    type Error = io::Error;
+
+    fn home_dc_id(&self) -> io::Result<i32> {
+        Ok(self.data()?.home_dc)
+    }
+
+    fn set_home_dc_id(&self, dc_id: i32) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move {
+            self.update(|data| {
+                let changed = data.home_dc != dc_id;
+                data.home_dc = dc_id;
+                changed
+            })
+        })
+    }
+
+    fn dc_option(&self, dc_id: i32) -> io::Result<Option<DcOption>> {
+        Ok(self.data()?.dc_options.get(&dc_id).cloned())
+    }
+
+    fn set_dc_option(&self, dc_option: &DcOption) -> BoxFuture<'_, io::Result<()>> {
+        let dc_option = dc_option.clone();
+        Box::pin(async move {
+            self.update(|data| {
+                let previous = data.dc_options.insert(dc_option.id, dc_option.clone());
+                previous.as_ref() != Some(&dc_option)
+            })
+        })
+    }
+
+    fn peer(&self, peer: PeerId) -> BoxFuture<'_, io::Result<Option<PeerInfo>>> {
+        Box::pin(async move { Ok(self.data()?.peer_infos.get(&peer).cloned()) })
+    }
+
+    fn cache_peer(&self, peer: &PeerInfo) -> BoxFuture<'_, io::Result<()>> {
+        let peer = peer.clone();
+        Box::pin(async move {
+            self.update(|data| match data.peer_infos.entry(peer.id()) {
+                Entry::Occupied(mut known) => {
+                    let previous = known.get().clone();
+                    known.get_mut().extend_info(&peer);
+                    *known.get() != previous
+                }
+                Entry::Vacant(unknown) => {
+                    unknown.insert(peer);
+                    true
+                }
+            })
+        })
+    }
+
+    fn updates_state(&self) -> BoxFuture<'_, io::Result<UpdatesState>> {
+        Box::pin(async move { Ok(self.data()?.updates_state.clone()) })
+    }
+
+    fn set_update_state(&self, update: UpdateState) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move {
+            self.update(|data| {
+                let state = &mut data.updates_state;
+                match update {
+                    UpdateState::All(updates_state) => *state = updates_state,
+                    UpdateState::Primary { pts, date, seq } => {
+                        state.pts = pts;
+                        state.date = date;
+                        state.seq = seq;
+                    }
+                    UpdateState::Secondary { qts } => state.qts = qts,
+                    UpdateState::Channel { id, pts } => {
+                        state.channels.retain(|channel| channel.id != id);
+                        state.channels.push(ChannelState { id, pts });
+                    }
+                }
+                true
+            })
+        })
+    }
+}
+
+/// Login code Telegram sends to the account's other sessions, asked for on the first start only
+async fn read_login_code(phone: &str) -> anyhow::Result<String> {
+    info!("Enter the login code Telegram sent to {phone}:");
+    let code = tokio::task::spawn_blocking(|| {
+        let mut code = String::new();
+        std::io::stdin().read_line(&mut code).map(|_| code)
+    })
+    .await??;
+
+    Ok(code.trim().to_owned())
+}
+
+async fn sign_in(client: &Client) -> anyhow::Result<()> {
+    let (Some(phone), Some(api_hash)) = (read_secret("TG_PHONE"), read_secret("TG_API_HASH"))
+    else {
+        bail!("TG_PHONE and TG_API_HASH are required to sign the companion in");
+    };
+
+    let token = client.request_login_code(&phone, &api_hash).await?;
+    let code = read_login_code(&phone).await?;
+    match client.sign_in(&token, &code).await {
+        Ok(_) => {}
+        Err(SignInError::PasswordRequired(password_token)) => {
+            let password = read_secret("TG_PASSWORD")
+                .context("The companion account has 2FA enabled, TG_PASSWORD is required")?;
+            client.check_password(password_token, password).await?;
+        }
+        Err(e) => return Err(e.into()),
+    }
+    info!("Signed the companion in as {phone}");
+
+    Ok(())
+}
+
+/// Relayed channels by their peer ids, the ones which can't be resolved are skipped
+async fn resolve_channels(client: &Client, channels: &[RelayedChannel]) -> HashMap<PeerId, ChatId> {
+    let mut resolved = HashMap::new();
+    for channel in channels {
+        let username = channel.username.trim_start_matches('@');
+        match client.resolve_username(username).await {
+            Ok(Some(peer)) => {
+                resolved.insert(peer.id(), ChatId(channel.chat_id));
+            }
+            Ok(None) => warn!("Channel @{username} not found - not relaying it"),
+            Err(e) => warn!("Failed to resolve channel @{username} - {e:?}"),
+        }
+    }
+
+    resolved
+}
+
+/// Cards the CAs of a channel post in the chat it is relayed to, throttled like the cards of the chat
+async fn relay_post(
+    bot: &Bot,
+    chat_id: ChatId,
+    text: &str,
+    client: reqwest::Client,
+    cache: &Cache,
+) {
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+    let ChatId(id) = chat_id;
+    if !app_config.whitelisted_chats.contains(&id) || app_config.paused_chats.contains(&id) {
+        debug!("Skipping relayed post since {chat_id} is not whitelisted or paused");
+        return;
+    }
+
+    let routed = route_addresses(text, client.clone()).await;
+    let token_cas = routed
+        .solana
+        .iter()
+        .chain(&routed.ton)
+        .chain(&routed.tron)
+        .chain(&routed.evm);
+    for token_ca in token_cas {
+        let key = (Cow::Owned(token_ca.clone()), chat_id, None);
+        let throttle_window = chat_settings(chat_id).throttle_window();
+        let throttled = cache
+            .read()
+            .await
+            .get(&key)
+            .is_some_and(|sent_at| Utc::now() - *sent_at < throttle_window);
+        if throttled {
+            debug!("Skipping relayed {token_ca} since it was carded in {chat_id} recently");
+            continue;
+        }
+
+        let Some(InlineCard { kind, context }) =
+            lookup_inline_card(token_ca, chat_id, client.clone()).await
+        else {
+            continue;
+        };
+        let (text, parse_mode) = telegram_text(&render_card(chat_id, kind, &context));
+        match bot
+            .send_message(chat_id, text)
+            .parse_mode(parse_mode)
+            .disable_link_preview(true)
+            .await
+        {
+            Ok(_) => {
+                cache.write().await.insert(key, Utc::now());
+            }
+            Err(e) => warn!("Failed to relay {token_ca} to {chat_id} - {e:?}"),
+        }
+    }
+}
+
+async fn relay_channel_posts(
+    mut updates: UpdateStream,
+    channels: HashMap<PeerId, ChatId>,
+    bot: Bot,
+    client: reqwest::Client,
+    cache: Cache,
+) {
+    loop {
+        let message = match updates.next().await {
+            Ok(Update::NewMessage(message)) if !message.outgoing() => message,
+            Ok(_) => continue,
+            Err(e) => {
+                warn!("Companion stopped receiving updates, posts aren't relayed anymore - {e:?}");
+                return;
+            }
+        };
+
+        if let Some(&chat_id) = channels.get(&message.peer_id()) {
+            relay_post(&bot, chat_id, message.text(), client.clone(), &cache).await;
+        }
+    }
+}
+
+/// Signs the user account in, asking for the login code on the first start,
+/// and starts relaying the posts of the configured channels
+pub async fn start_companion(
+    bot: Bot,
+    config: &CompanionConfig,
+    client: reqwest::Client,
+    cache: Cache,
+) -> anyhow::Result<()> {
+    let api_id = read_secret("TG_API_ID")
+        .context("TG_API_ID is required by the companion")?
+        .parse()
+        .context("Invalid TG_API_ID")?;
+    let session = Arc::new(FileSession::open(config.session_path.clone())?);
+
+    let SenderPool {
+        runner,
+        updates,
+        handle,
+    } = SenderPool::new(session, api_id);
+    let user_client = Client::new(handle);
+    tokio::spawn(runner.run());
+
+    if !user_client.is_authorized().await? {
+        sign_in(&user_client).await?;
+    }
+
+    let channels = resolve_channels(&user_client, &config.relayed_channels).await;
+    let updates = user_client
+        .stream_updates(updates, UpdatesConfiguration::default())
+        .await
+        .map_err(|e| anyhow!(e))?;
+    if CLIENT.set(user_client).is_err() {
+        bail!("the companion is already started");
+    }
+    info!("Companion started, relaying {} channels", channels.len());
+    tokio::spawn(relay_channel_posts(updates, channels, bot, client, cache));
+
+    Ok(())
+}
+
+pub fn is_companion_running() -> bool {
+    CLIENT.get().is_some()
+}
+
+/// The account must be a member of the chat, the bot api ids have no access hash
+async fn find_chat(client: &Client, chat_id: ChatId) -> anyhow::Result<PeerRef> {
+    let ChatId(id) = chat_id;
+    let peer_id = PeerId::from_bot_api_dialog_id(id).context("Invalid chat id")?;
+
+    let mut dialogs = client.iter_dialogs();
+    while let Some(dialog) = dialogs.next().await? {
+        if dialog.peer_id() == peer_id {
+            return Ok(dialog.peer_ref());
+        }
+    }
+
+    bail!("the companion account is not a member of this chat")
+}
+
+/// Messages of the chat sent after `since`, oldest first, up to `history_limit` of them
+pub async fn read_history(
+    chat_id: ChatId,
+    since: DateTime<Utc>,
+) -> anyhow::Result<Vec<PastMessage>> {
+    let client = CLIENT.get().context("the companion is not running")?;
+    let history_limit = APP_CONFIG
+        .get()
+        .unwrap()
+        .app_config()
+        .companion
+        .clone()
+        .unwrap_or_default()
+        .history_limit;
+
+    let chat = find_chat(client, chat_id).await?;
+    let mut history = client.iter_messages(chat).limit(history_limit);
+    let mut messages = Vec::new();
+    // newest first
+    while let Some(message) = history.next().await? {
+        if message.date() <= since {
+            break;
+        }
+
+        let hrefs = message
+            .fmt_entities()
+            .into_iter()
+            .flatten()
+            .filter_map(|entity| match entity {
+                tl::enums::MessageEntity::TextUrl(link) => Some(link.url.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let text = format!("{}\n{hrefs}", message.text());
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        messages.push(PastMessage {
+            caller: message.sender().and_then(Peer::name).map(str::to_owned),
+            sent_at: message.date(),
+            text,
+        });
+    }
+    messages.reverse();
+
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn keeps_session_across_restarts() {
+        let path =
+            std::env::temp_dir().join(format!("companion_session_{}.json", std::process::id()));
+        let group = PeerInfo::Chat { id: 42 };
+
+        let session = FileSession::open(path.clone()).unwrap();
+        session.set_home_dc_id(4).await.unwrap();
+        session.cache_peer(&group).await.unwrap();
+        session
+            .set_update_state(UpdateState::Channel { id: 7, pts: 100 })
+            .await
+            .unwrap();
+
+        let reopened = FileSession::open(path.clone()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(reopened.home_dc_id().unwrap(), 4);
+        assert_eq!(reopened.peer(group.id()).await.unwrap(), Some(group));
+        assert_eq!(
+            reopened.updates_state().await.unwrap().channels,
+            vec![ChannelState { id: 7, pts: 100 }]
+        );
+        assert!(reopened.dc_option(2).unwrap().is_some());
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use sqlx::SqlitePool;
+use teloxide::Bot;
+use teloxide::types::{ChatId, Message, ThreadId, User};
+
+use crate::config::{ChatConfig, Config};
+use crate::{APP_CONFIG, Cache};
+
+static NEXT_TRACE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Everything needed to process a single message, created once per message and
+/// passed through detection, lookup and rendering
+pub struct MessageContext<'a> {
+    pub bot: Bot,
+    pub message: &'a Message,
+    pub client: reqwest::Client,
+    pub throttle: Cache,
+    pub storage: SqlitePool,
+    /// Config snapshot, a reload doesn't affect the message being processed
+    pub app_config: Arc<Config>,
+    /// `@username` or the name of whoever posted the message, bots are not credited
+    pub caller: Option<String>,
+    /// Prefixes the log lines about this message
+    pub trace_id: u64,
+    /// Deliberate lookups, e.g. `/ca`, are answered even if the token was carded recently
+    pub bypass_throttle: bool,
+    /// Tokens skipped for being out of the chat's mcap range
+    pub out_of_mcap_range: AtomicUsize,
+}
+
+impl<'a> MessageContext<'a> {
+    pub fn new(
+        bot: Bot,
+        message: &'a Message,
+        client: reqwest::Client,
+        throttle: Cache,
+        storage: SqlitePool,
+    ) -> Self {
+        Self {
+            bot,
+            message,
+            client,
+            throttle,
+            storage,
+            app_config: APP_CONFIG.get().unwrap().app_config(),
+            caller: caller_name(message),
+            trace_id: NEXT_TRACE_ID.fetch_add(1, Ordering::Relaxed),
+            bypass_throttle: false,
+            out_of_mcap_range: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn chat_id(&self) -> ChatId {
+        self.message.chat.id
+    }
+
+    pub fn thread_id(&self) -> Option<ThreadId> {
+        self.message.thread_id
+    }
+
+    /// Per-chat overrides, if any
+    pub fn chat_config(&self) -> Option<&ChatConfig> {
+        let ChatId(chat_id) = self.chat_id();
+        self.app_config.chats.get(&chat_id)
+    }
+}
+
+fn caller_name(message: &Message) -> Option<String> {
+    match &message.from {
+        Some(User {
+            is_bot: false,
+            username: Some(username),
+            ..
+        }) => Some(format!("@{username}")),
+        Some(User {
+            is_bot: false,
+            first_name,
+            ..
+        }) => Some(first_name.clone()),
+        _ => message
+            .sender_chat
+            .as_ref()
+            .and_then(|chat| chat.title().map(str::to_owned)),
+    }
+}
@@ -0,0 +1,40 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use teloxide::types::ChatId;
+
+use crate::APP_CONFIG;
+use crate::cache::caches;
+
+/// Lowercased alphanumeric characters of the text, so reformatted copies match
+fn normalize_text(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Whether the same text was seen in the chat within the dedupe window,
+/// the text is remembered for the following messages otherwise
+pub async fn is_duplicate_text(chat_id: ChatId, text: &str) -> bool {
+    let config = &APP_CONFIG.get().unwrap().app_config().duplicate_texts;
+    if config.window_mins == 0 {
+        return false;
+    }
+
+    let normalized = normalize_text(text);
+    if normalized.chars().count() < config.min_length {
+        return false;
+    }
+
+    let mut hasher = DefaultHasher::new();
+    normalized.hash(&mut hasher);
+    let key = (chat_id, hasher.finish());
+
+    let recent_texts = &caches().recent_texts;
+    if recent_texts.get(&key).await.is_some() {
+        return true;
+    }
+
+    recent_texts.insert(key, ()).await;
+    false
+}
@@ -0,0 +1,61 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::APP_CONFIG;
+
+/// Length of the base64 encoded signature appended to the token address
+const SIGNATURE_LEN: usize = 16;
+
+/// Telegram limits start parameters to 64 chars of `A-Za-z0-9_-`
+const MAX_START_PARAMETER_LEN: usize = 64;
+
+/// Signature is truncated to this many bytes, 16 chars when encoded
+const SIGNATURE_BYTES: usize = 12;
+
+fn token_mac(token_ca: &str) -> Hmac<Sha256> {
+    let secret = &APP_CONFIG.get().unwrap().deep_link_secret;
+    // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(token_ca.as_bytes());
+    mac
+}
+
+fn sign(token_ca: &str) -> String {
+    let signature = token_mac(token_ca).finalize().into_bytes();
+    URL_SAFE_NO_PAD.encode(&signature[..SIGNATURE_BYTES])
+}
+
+/// `t.me` link opening the card of the token in the private chat with the bot,
+/// if the address fits into a start parameter
+pub fn open_in_dm_url(token_ca: &str) -> Option<String> {
+    let payload = format!("{token_ca}{}", sign(token_ca));
+    let fits = payload.len() <= MAX_START_PARAMETER_LEN
+        && payload
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !fits {
+        return None;
+    }
+
+    let username = APP_CONFIG.get().unwrap().bot_info.username.clone()?;
+    Some(format!("https://t.me/{username}?start={payload}"))
+}
+
+/// Token address from a start parameter produced by [`open_in_dm_url`], None if it's forged
+pub fn verify_start_parameter(payload: &str) -> Option<&str> {
+    let split_at = payload.len().checked_sub(SIGNATURE_LEN)?;
+    if !payload.is_char_boundary(split_at) {
+        return None;
+    }
+
+    let (token_ca, signature) = payload.split_at(split_at);
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+
+    token_mac(token_ca)
+        .verify_truncated_left(&signature)
+        .ok()
+        .filter(|_| !token_ca.is_empty())
+        .map(|_| token_ca)
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use teloxide::Bot;
+use teloxide::payloads::AnswerCallbackQuerySetters;
+use teloxide::prelude::{Requester, ResponseResult};
+use teloxide::types::{CallbackQuery, ChatId, InlineKeyboardButton, MessageId};
+
+use crate::aliases::is_chat_admin_user;
+
+pub const DELETE_CALLBACK_DATA: &str = "delete";
+
+pub fn delete_button() -> InlineKeyboardButton {
+    InlineKeyboardButton::callback("🗑", DELETE_CALLBACK_DATA)
+}
+
+/// Deletes the reply once the auto-delete period picked via /settings passes.
+/// Pending deletions are lost on restart.
+pub fn schedule_auto_delete(bot: Bot, chat_id: ChatId, message_id: MessageId, ttl: Duration) {
+    tokio::spawn(async move {
+        tokio::time::sleep(ttl).await;
+        if let Err(e) = bot.delete_message(chat_id, message_id).await {
+            warn!("Failed to auto-delete reply {message_id} in {chat_id} - {e:?}");
+        }
+    });
+}
+
+/// Deletes the reply the button is attached to, e.g. when the bot carded an obvious scam paste.
+/// Only chat admins may do that
+pub async fn handle_delete(bot: &Bot, query: &CallbackQuery) -> ResponseResult<()> {
+    let Some(reply) = query.regular_message() else {
+        debug!("Reply to delete is inaccessible - skipping it");
+        bot.answer_callback_query(query.id.clone()).await?;
+        return Ok(());
+    };
+
+    if !is_chat_admin_user(bot, &reply.chat, &query.from).await {
+        bot.answer_callback_query(query.id.clone())
+            .text("Only chat admins can delete my replies")
+            .await?;
+        return Ok(());
+    }
+
+    bot.answer_callback_query(query.id.clone()).await?;
+
+    match bot.delete_message(reply.chat.id, reply.id).await {
+        Ok(_) => info!(
+            "Deleted reply {} in {} on request of {}",
+            reply.id, reply.chat.id, query.from.id
+        ),
+        Err(e) => warn!(
+            "Failed to delete reply {} in {} - {e:?}",
+            reply.id, reply.chat.id
+        ),
+    }
+
+    Ok(())
+}
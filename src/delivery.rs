@@ -0,0 +1,117 @@
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use log::{debug, info, warn};
+use sqlx::SqlitePool;
+use teloxide::payloads::SendMessageSetters;
+use teloxide::prelude::Requester;
+use teloxide::sugar::request::RequestLinkPreviewExt;
+use teloxide::types::{ChatId, ThreadId, UserId};
+use teloxide::{ApiError, Bot, RequestError};
+use tokio::sync::RwLock;
+
+use crate::formatter::telegram_text;
+use crate::pacing::{record_send_error, wait_for_send_slot};
+use crate::storage::{delete_private_chat_user, load_private_chat_users, save_private_chat_user};
+
+/// Users who have started a private chat with the bot, so we are able to DM them.
+/// Persisted so a restart doesn't make DM alerts fall back to the chats.
+static PRIVATE_CHAT_USERS: LazyLock<RwLock<HashSet<UserId>>> =
+    LazyLock::new(|| RwLock::new(HashSet::new()));
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AlertDestination {
+    /// Post the alert into the chat where it was set up
+    Chat,
+    /// Send the alert to the user's private chat with the bot
+    DirectMessage,
+}
+
+/// Loads the users who started a private chat before the restart
+pub async fn init_private_chat_users(storage: &SqlitePool) {
+    match load_private_chat_users(storage).await {
+        Ok(users) => {
+            info!("Loaded {} users with a private chat", users.len());
+            PRIVATE_CHAT_USERS.write().await.extend(users);
+        }
+        Err(e) => warn!("Failed to load private chat users - {e:?}"),
+    }
+}
+
+pub async fn register_private_chat_user(storage: &SqlitePool, user_id: UserId) {
+    let inserted = PRIVATE_CHAT_USERS.write().await.insert(user_id);
+    if inserted {
+        info!("User {user_id} has started a private chat with the bot");
+        if let Err(e) = save_private_chat_user(storage, user_id).await {
+            warn!("Failed to save private chat user {user_id} - {e:?}");
+        }
+    }
+}
+
+pub async fn has_private_chat(user_id: UserId) -> bool {
+    PRIVATE_CHAT_USERS.read().await.contains(&user_id)
+}
+
+/// Delivers an alert (MarkdownV2 text) to the requested destination.
+/// DM alerts fall back to the originating chat if the user has never started
+/// a private chat with the bot or has blocked it since then.
+pub async fn deliver_alert(
+    bot: &Bot,
+    storage: &SqlitePool,
+    destination: AlertDestination,
+    user_id: UserId,
+    chat_id: ChatId,
+    thread_id: Option<ThreadId>,
+    text: String,
+) {
+    if destination == AlertDestination::DirectMessage {
+        if has_private_chat(user_id).await {
+            match send_alert(bot, ChatId::from(user_id), None, text.clone()).await {
+                Ok(()) => return,
+                Err(RequestError::Api(
+                    ApiError::BotBlocked
+                    | ApiError::CantInitiateConversation
+                    | ApiError::UserDeactivated,
+                )) => {
+                    info!("User {user_id} doesn't accept DMs anymore - falling back to chat {chat_id}");
+                    PRIVATE_CHAT_USERS.write().await.remove(&user_id);
+                    if let Err(e) = delete_private_chat_user(storage, user_id).await {
+                        warn!("Failed to delete private chat user {user_id} - {e:?}");
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to DM alert to {user_id} - {e:?} - falling back to chat {chat_id}");
+                }
+            }
+        } else {
+            debug!("User {user_id} has no private chat with the bot - falling back to chat {chat_id}");
+        }
+    }
+
+    if let Err(e) = send_alert(bot, chat_id, thread_id, text).await {
+        warn!("Failed to send alert to chat {chat_id} - {e:?}");
+    }
+}
+
+async fn send_alert(
+    bot: &Bot,
+    chat_id: ChatId,
+    thread_id: Option<ThreadId>,
+    text: String,
+) -> Result<(), RequestError> {
+    let (text, parse_mode) = telegram_text(&text);
+    let mut request = bot
+        .send_message(chat_id, text)
+        .parse_mode(parse_mode)
+        .disable_link_preview(true);
+    if let Some(thread_id) = thread_id {
+        request = request.message_thread_id(thread_id);
+    }
+
+    wait_for_send_slot(chat_id).await;
+    let result = request.await.map(|_| ());
+    if let Err(e) = &result {
+        record_send_error(chat_id, e).await;
+    }
+    result
+}
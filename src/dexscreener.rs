@@ -0,0 +1,227 @@
+use log::debug;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::APP_CONFIG;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenBoost {
+    pub chain_id: String,
+    pub token_address: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PairToken {
+    pub address: String,
+    pub name: String,
+    pub symbol: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PairLiquidity {
+    pub usd: Option<Decimal>,
+    /// Pool reserve of the base token, in tokens
+    pub base: Option<Decimal>,
+    /// Pool reserve of the quote token, in tokens
+    pub quote: Option<Decimal>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PairPriceChange {
+    pub h24: Option<Decimal>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PairVolume {
+    pub h24: Option<Decimal>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PairWebsite {
+    pub label: Option<String>,
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PairSocial {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub url: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PairInfo {
+    #[serde(default)]
+    pub websites: Vec<PairWebsite>,
+    #[serde(default)]
+    pub socials: Vec<PairSocial>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PairsResponse {
+    pairs: Option<Vec<Pair>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pair {
+    pub chain_id: String,
+    pub pair_address: String,
+    pub base_token: PairToken,
+    pub price_usd: Option<Decimal>,
+    pub market_cap: Option<Decimal>,
+    pub fdv: Option<Decimal>,
+    #[serde(default)]
+    pub liquidity: PairLiquidity,
+    #[serde(default)]
+    pub price_change: PairPriceChange,
+    #[serde(default)]
+    pub volume: PairVolume,
+    pub info: Option<PairInfo>,
+}
+
+impl Pair {
+    pub fn liquidity_usd(&self) -> Decimal {
+        self.liquidity.usd.unwrap_or_default()
+    }
+
+    /// USD value of the quote side of the pool, half of the liquidity if reserves are unknown
+    pub fn quote_reserve_usd(&self) -> Option<Decimal> {
+        let liquidity = self.liquidity.usd?;
+        match (self.liquidity.base, self.price_usd) {
+            (Some(base), Some(price)) if base * price <= liquidity => {
+                Some(liquidity - base * price)
+            }
+            _ => Some(liquidity / Decimal::TWO),
+        }
+    }
+}
+
+/// Pairs matching the query (token symbol, name or address)
+pub async fn search_pairs(query: &str, client: reqwest::Client) -> anyhow::Result<Vec<Pair>> {
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+    let url = format!("{}/latest/dex/search", app_config.provider_urls.dexscreener);
+    debug!("Going to hit url - {url} with query {query}");
+
+    let response = client
+        .get(url)
+        .query(&[("q", query)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<PairsResponse>()
+        .await?;
+
+    Ok(response.pairs.unwrap_or_default())
+}
+
+/// Maps the chain argument used in bot commands to the DexScreener chain id
+pub fn dexscreener_chain_id(chain: &str) -> Option<&'static str> {
+    match chain {
+        "sol" | "solana" => Some("solana"),
+        "bsc" | "bnb" => Some("bsc"),
+        "base" => Some("base"),
+        "arb" | "arbitrum" => Some("arbitrum"),
+        "pol" | "polygon" | "matic" => Some("polygon"),
+        "ton" => Some("ton"),
+        "tron" | "trx" => Some("tron"),
+        _ => None,
+    }
+}
+
+pub async fn retrieve_top_boosted_tokens(client: reqwest::Client) -> anyhow::Result<Vec<TokenBoost>> {
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+    let url = format!(
+        "{}/token-boosts/top/v1",
+        app_config.provider_urls.dexscreener
+    );
+    debug!("Going to hit url - {url}");
+
+    let response = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<TokenBoost>>()
+        .await?;
+
+    Ok(response)
+}
+
+/// Returns the most liquid pair for each of the given token addresses.
+/// DexScreener accepts up to 30 addresses per request.
+pub async fn retrieve_token_pairs(
+    chain_id: &str,
+    token_cas: &[&str],
+    client: reqwest::Client,
+) -> anyhow::Result<Vec<Pair>> {
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+    let url = format!(
+        "{}/tokens/v1/{chain_id}/{}",
+        app_config.provider_urls.dexscreener,
+        token_cas.join(",")
+    );
+    debug!("Going to hit url - {url}");
+
+    let pairs = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<Pair>>()
+        .await?;
+
+    let mut best_pairs: Vec<Pair> = Vec::with_capacity(token_cas.len());
+    for pair in pairs {
+        match best_pairs
+            .iter_mut()
+            .find(|p| p.base_token.address == pair.base_token.address)
+        {
+            Some(best) if best.liquidity_usd() < pair.liquidity_usd() => *best = pair,
+            Some(_) => {}
+            None => best_pairs.push(pair),
+        }
+    }
+
+    Ok(best_pairs)
+}
+
+/// Most liquid pair of the token itself. DexScreener also returns the pairs
+/// where the token is the quote one, those describe other tokens.
+pub async fn retrieve_token_pair(
+    chain_id: &str,
+    token_ca: &str,
+    client: reqwest::Client,
+) -> anyhow::Result<Option<Pair>> {
+    let pairs = retrieve_token_pairs(chain_id, &[token_ca], client).await?;
+
+    // DexScreener may return EVM addresses checksummed differently
+    Ok(pairs
+        .into_iter()
+        .find(|pair| pair.base_token.address.eq_ignore_ascii_case(token_ca)))
+}
+
+/// Looks up a pair by its address, `None` means DexScreener doesn't know such pair
+pub async fn retrieve_pair(
+    chain_id: &str,
+    pair_address: &str,
+    client: reqwest::Client,
+) -> anyhow::Result<Option<Pair>> {
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+    let url = format!(
+        "{}/latest/dex/pairs/{chain_id}/{pair_address}",
+        app_config.provider_urls.dexscreener
+    );
+    debug!("Going to hit url - {url}");
+
+    let response = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<PairsResponse>()
+        .await?;
+
+    Ok(response.pairs.and_then(|mut pairs| pairs.pop()))
+}
@@ -0,0 +1,136 @@
+use std::cmp::Reverse;
+
+use chrono::{DurationRound, TimeDelta, Timelike, Utc};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use sqlx::SqlitePool;
+use teloxide::Bot;
+use teloxide::prelude::Requester;
+use teloxide::types::ChatId;
+
+use crate::APP_CONFIG;
+use crate::storage::load_recent_mentions;
+use crate::tags::category_breakdown;
+use crate::token_info::format_human_readable;
+
+const DIGEST_PERIOD: TimeDelta = TimeDelta::hours(24);
+
+/// Max number of tokens listed in a digest
+const MAX_DIGEST_TOKENS: usize = 20;
+
+/// Mention of a token in the digest period
+struct DigestEntry {
+    symbol: String,
+    mcap: Option<Decimal>,
+    latest_mcap: Option<Decimal>,
+}
+
+impl DigestEntry {
+    fn multiple(&self) -> Option<Decimal> {
+        match (self.mcap, self.latest_mcap) {
+            (Some(mcap), Some(latest_mcap)) if mcap > Decimal::ZERO => Some(latest_mcap / mcap),
+            _ => None,
+        }
+    }
+
+    fn describe(&self) -> String {
+        let mcap = |mcap: Option<Decimal>| {
+            mcap.map(|mcap| format_human_readable(mcap, 1))
+                .unwrap_or_else(|| "?".to_owned())
+        };
+
+        let mut line = format!(
+            "${} {} → {}",
+            self.symbol,
+            mcap(self.mcap),
+            mcap(self.latest_mcap)
+        );
+        if let Some(multiple) = self.multiple() {
+            line.push_str(&format!(" ({}x)", multiple.round_dp(1).normalize()));
+        }
+        line
+    }
+}
+
+async fn build_digest(storage: &SqlitePool, chat_id: ChatId) -> anyhow::Result<Option<String>> {
+    let since = Utc::now() - DIGEST_PERIOD;
+    let mentions = load_recent_mentions(storage, chat_id, since).await?;
+    if mentions.is_empty() {
+        return Ok(None);
+    }
+
+    let mut entries: Vec<DigestEntry> = mentions
+        .into_iter()
+        .map(|(symbol, mcap, latest_mcap)| DigestEntry {
+            symbol,
+            mcap: mcap.and_then(|mcap| mcap.parse().ok()),
+            latest_mcap: latest_mcap.and_then(|mcap| mcap.parse().ok()),
+        })
+        .collect();
+    // tokens with unknown performance go last
+    entries.sort_by_key(|entry| Reverse(entry.multiple()));
+
+    let total = entries.len();
+    let lines = entries
+        .iter()
+        .take(MAX_DIGEST_TOKENS)
+        .enumerate()
+        .map(|(i, entry)| format!("{}. {}", i + 1, entry.describe()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut digest = format!(
+        "📰 {total} tokens mentioned in the last 24h, mcap at the first mention → now:\n{lines}"
+    );
+    if let Some(categories) = category_breakdown(storage, chat_id, since).await {
+        digest.push_str(&format!("\n\n{categories}"));
+    }
+
+    Ok(Some(digest))
+}
+
+async fn post_digest(bot: &Bot, storage: &SqlitePool, chat_id: ChatId) {
+    let text = match build_digest(storage, chat_id).await {
+        Ok(Some(text)) => text,
+        Ok(None) => {
+            info!("No tokens mentioned in {chat_id} in the last 24h - skipping the digest");
+            return;
+        }
+        Err(e) => {
+            warn!("Failed to build the digest of {chat_id} - {e:?}");
+            return;
+        }
+    };
+
+    match bot.send_message(chat_id, text).await {
+        Ok(_) => info!("Posted the daily digest to {chat_id}"),
+        Err(e) => warn!("Failed to post the digest to {chat_id} - {e:?}"),
+    }
+}
+
+/// Posts the daily digest to the chats which have `digest_hour_utc` configured,
+/// the mcaps are the ones kept up to date for `/stats`
+pub fn spawn_digest_task(bot: Bot, storage: SqlitePool) {
+    tokio::spawn(async move {
+        loop {
+            let now = Utc::now();
+            let next_hour =
+                now.duration_trunc(TimeDelta::hours(1)).unwrap_or(now) + TimeDelta::hours(1);
+            tokio::time::sleep((next_hour - now).to_std().unwrap_or_default()).await;
+
+            let hour = Utc::now().hour();
+            let app_config = APP_CONFIG.get().unwrap().app_config();
+            let chats: Vec<ChatId> = app_config
+                .chats
+                .iter()
+                .filter(|(_, chat)| chat.digest_hour_utc == Some(hour))
+                .filter(|(chat_id, _)| !app_config.paused_chats.contains(chat_id))
+                .map(|(chat_id, _)| ChatId(*chat_id))
+                .collect();
+
+            for chat_id in chats {
+                post_digest(&bot, &storage, chat_id).await;
+            }
+        }
+    });
+}
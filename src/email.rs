@@ -0,0 +1,83 @@
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use log::debug;
+use serde::Deserialize;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Plaintext connection, only for trusted local relays
+    None,
+    /// Plaintext connection upgraded via STARTTLS (usually port 587)
+    #[default]
+    Starttls,
+    /// Implicit TLS from the start of the connection (usually port 465)
+    Tls,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    /// Defaults to the standard port of the chosen TLS mode
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    /// Only for self-signed certificates of private relays
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    pub from: String,
+}
+
+fn build_transport(cfg: &SmtpConfig) -> anyhow::Result<AsyncSmtpTransport<Tokio1Executor>> {
+    let tls_parameters = TlsParameters::builder(cfg.host.clone())
+        .dangerous_accept_invalid_certs(cfg.accept_invalid_certs)
+        .build()?;
+
+    let (tls, default_port) = match cfg.tls {
+        SmtpTls::None => (Tls::None, 25),
+        SmtpTls::Starttls => (Tls::Required(tls_parameters), 587),
+        SmtpTls::Tls => (Tls::Wrapper(tls_parameters), 465),
+    };
+
+    let mut builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(cfg.host.as_str())
+        .port(cfg.port.unwrap_or(default_port))
+        .tls(tls);
+
+    if let (Some(username), Some(password)) = (&cfg.username, &cfg.password) {
+        builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+
+    Ok(builder.build())
+}
+
+/// Sends a plain text email to all recipients
+pub async fn send_email(
+    cfg: &SmtpConfig,
+    recipients: &[String],
+    subject: &str,
+    body: String,
+) -> anyhow::Result<()> {
+    let mut message = Message::builder()
+        .from(cfg.from.parse::<Mailbox>()?)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN);
+    for recipient in recipients {
+        message = message.to(recipient.parse::<Mailbox>()?);
+    }
+    let message = message.body(body)?;
+
+    let transport = build_transport(cfg)?;
+    transport.send(message).await?;
+
+    debug!("Sent email {subject} to {recipients:?}");
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+use teloxide::Bot;
+use teloxide::prelude::Requester;
+use teloxide::types::ChatId;
+
+use crate::APP_CONFIG;
+use crate::providers::Provider;
+
+/// At most one report per error class is sent within this interval,
+/// errors happening in between are counted and included in the next report
+const REPORT_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// Outage-like failure of a provider call
+    Provider(Provider),
+    /// Failed to send a reply with token cards
+    SendReply,
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorClass::Provider(provider) => write!(f, "{provider:?} calls failing"),
+            ErrorClass::SendReply => write!(f, "Sending replies failing"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ClassReports {
+    last_sent_at: Option<Instant>,
+    /// Errors since the last sent report
+    count: u32,
+}
+
+static REPORTS: LazyLock<Mutex<HashMap<ErrorClass, ClassReports>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+static REPORTER_BOT: OnceLock<Bot> = OnceLock::new();
+
+pub fn init_error_reports(bot: Bot) {
+    // This is safe if init_error_reports is called just once directly in the main fn
+    REPORTER_BOT.set(bot).unwrap();
+}
+
+/// Forwards the error to `admin_chat_id` unless the class was reported recently
+pub fn report_error(class: ErrorClass, details: String) {
+    let Some(admin_chat_id) = APP_CONFIG
+        .get()
+        .and_then(|cfg| cfg.app_config().admin_chat_id)
+    else {
+        return;
+    };
+
+    let count = {
+        let mut reports = REPORTS.lock().unwrap();
+        let reports = reports.entry(class).or_default();
+        reports.count += 1;
+
+        if reports
+            .last_sent_at
+            .is_some_and(|sent_at| sent_at.elapsed() < REPORT_INTERVAL)
+        {
+            debug!("Suppressing {class:?} error report - already reported recently");
+            return;
+        }
+
+        reports.last_sent_at = Some(Instant::now());
+        std::mem::take(&mut reports.count)
+    };
+
+    let Some(bot) = REPORTER_BOT.get().cloned() else {
+        return;
+    };
+
+    let text = format!("⚠️ {class} - {count} error(s) since the last report\nLatest: {details}");
+    tokio::spawn(async move {
+        if let Err(e) = bot.send_message(ChatId(admin_chat_id), text).await {
+            warn!("Failed to send error report to the admin chat - {e:?}");
+        }
+    });
+}
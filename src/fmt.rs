@@ -0,0 +1,51 @@
+use teloxide::utils::markdown::{escape, escape_code, escape_link_url};
+
+/// Builds a MarkdownV2 message escaping every value it's given, so literal
+/// text never has to be escaped by hand
+#[derive(Debug, Default, Clone)]
+pub struct MessageBuilder {
+    text: String,
+}
+
+impl MessageBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(mut self, text: &str) -> Self {
+        self.text.push_str(&escape(text));
+        self
+    }
+
+    pub fn bold(mut self, text: &str) -> Self {
+        self.text.push_str(&format!("*{}*", escape(text)));
+        self
+    }
+
+    pub fn code(mut self, text: &str) -> Self {
+        self.text.push_str(&format!("`{}`", escape_code(text)));
+        self
+    }
+
+    pub fn link(mut self, caption: &str, url: &str) -> Self {
+        self.text
+            .push_str(&format!("[{}]({})", escape(caption), escape_link_url(url)));
+        self
+    }
+
+    /// Starts a new line
+    pub fn line(mut self) -> Self {
+        self.text.push('\n');
+        self
+    }
+
+    /// Appends another message, which is escaped already
+    pub fn append(mut self, other: MessageBuilder) -> Self {
+        self.text.push_str(&other.text);
+        self
+    }
+
+    pub fn build(self) -> String {
+        self.text
+    }
+}
@@ -0,0 +1,222 @@
+use std::sync::Arc;
+
+use log::{debug, warn};
+use teloxide::Bot;
+use teloxide::payloads::SendMessageSetters;
+use teloxide::prelude::Requester;
+use teloxide::sugar::request::{RequestLinkPreviewExt, RequestReplyExt};
+use teloxide::types::{ChatId, Message, MessageId, User};
+
+use crate::APP_CONFIG;
+use crate::cache::caches;
+use crate::dexscreener::retrieve_token_pair;
+use crate::fmt::MessageBuilder;
+use crate::formatter::telegram_text;
+use crate::providers::{Provider, guarded};
+use crate::security::retrieve_evm_token_security;
+use crate::token_info::Chain;
+
+/// Token shown in one of our cards
+#[derive(Debug, Clone)]
+pub struct CardToken {
+    /// DexScreener chain id
+    pub chain_id: &'static str,
+    pub token_ca: String,
+}
+
+impl CardToken {
+    pub fn chart_url(&self) -> String {
+        format!(
+            "https://dexscreener.com/{}/{}",
+            self.chain_id, self.token_ca
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum FollowUp {
+    Holders,
+    Chart,
+    Socials,
+    Security,
+}
+
+fn parse_follow_up(text: &str) -> Option<FollowUp> {
+    let keyword = text.trim().trim_end_matches('?').trim().to_lowercase();
+    match keyword.as_str() {
+        "holders" | "holder" => Some(FollowUp::Holders),
+        "chart" | "charts" => Some(FollowUp::Chart),
+        "socials" | "social" | "links" => Some(FollowUp::Socials),
+        "security" | "safe" | "rug" => Some(FollowUp::Security),
+        _ => None,
+    }
+}
+
+/// Remembers which tokens the sent card is about, so replies to it can be answered,
+/// and the full card if the compact one was sent, so it can be expanded
+pub async fn remember_card(
+    chat_id: ChatId,
+    message_id: MessageId,
+    tokens: Vec<CardToken>,
+    full_text: Option<String>,
+) {
+    caches()
+        .cards
+        .insert((chat_id, message_id), Arc::new(tokens))
+        .await;
+    if let Some(full_text) = full_text {
+        caches()
+            .full_cards
+            .insert((chat_id, message_id), full_text)
+            .await;
+    }
+}
+
+/// Answers a reply to one of our cards if it is a known follow-up keyword.
+/// Returns whether the message was handled.
+pub async fn answer_follow_up(bot: &Bot, message: &Message, client: reqwest::Client) -> bool {
+    let Some(card) = message.reply_to_message() else {
+        return false;
+    };
+
+    let bot_id = APP_CONFIG.get().unwrap().bot_info.id;
+    let Some(User { id, .. }) = &card.from else {
+        return false;
+    };
+    if *id != bot_id {
+        return false;
+    }
+
+    let Some(follow_up) = message.text().and_then(parse_follow_up) else {
+        return false;
+    };
+
+    let Some(tokens) = caches().cards.get(&(card.chat.id, card.id)).await else {
+        debug!("No context for card {} - ignoring {follow_up:?}", card.id);
+        return false;
+    };
+
+    let mut sections = Vec::with_capacity(tokens.len());
+    for token in tokens.iter() {
+        let section = match follow_up {
+            FollowUp::Holders => holders_section(token),
+            FollowUp::Chart => chart_section(token),
+            FollowUp::Socials => socials_section(token, client.clone()).await,
+            FollowUp::Security => security_section(token, client.clone()).await,
+        };
+        sections.push(
+            MessageBuilder::new()
+                .code(&token.token_ca)
+                .line()
+                .append(section)
+                .build(),
+        );
+    }
+
+    let (text, parse_mode) = telegram_text(&sections.join("\n\n"));
+    if let Err(e) = bot
+        .send_message(message.chat.id, text)
+        .parse_mode(parse_mode)
+        .disable_link_preview(true)
+        .reply_to(message.id)
+        .await
+    {
+        warn!("Failed to answer {follow_up:?} follow-up - {e:?}");
+    }
+
+    true
+}
+
+fn link(caption: &str, url: &str) -> MessageBuilder {
+    MessageBuilder::new().link(caption, url)
+}
+
+fn text(text: &str) -> MessageBuilder {
+    MessageBuilder::new().text(text)
+}
+
+fn holders_section(token: &CardToken) -> MessageBuilder {
+    let ca = &token.token_ca;
+    let url = match token.chain_id {
+        "bsc" => format!("https://bscscan.com/token/{ca}#balances"),
+        "base" => format!("https://basescan.org/token/{ca}#balances"),
+        "arbitrum" => format!("https://arbiscan.io/token/{ca}#balances"),
+        "polygon" => format!("https://polygonscan.com/token/{ca}#balances"),
+        "ton" => format!("https://tonviewer.com/{ca}?section=holders"),
+        "tron" => format!("https://tronscan.org/#/token20/{ca}/holders"),
+        _ => format!("https://solscan.io/token/{ca}#holders"),
+    };
+    link("Holders", &url)
+}
+
+fn chart_section(token: &CardToken) -> MessageBuilder {
+    link("Chart", &token.chart_url())
+}
+
+async fn socials_section(token: &CardToken, client: reqwest::Client) -> MessageBuilder {
+    let pair = retrieve_token_pair(token.chain_id, &token.token_ca, client).await;
+    let info = match pair {
+        Ok(pair) => pair.and_then(|pair| pair.info),
+        Err(e) => {
+            warn!("Failed to retrieve socials of {} - {e:?}", token.token_ca);
+            return text("Failed to retrieve socials");
+        }
+    };
+
+    let links: Vec<MessageBuilder> = info
+        .map(|info| {
+            let websites = info
+                .websites
+                .iter()
+                .map(|site| link(site.label.as_deref().unwrap_or("Website"), &site.url));
+            let socials = info
+                .socials
+                .iter()
+                .map(|social| link(&social.kind, &social.url));
+            websites.chain(socials).collect()
+        })
+        .unwrap_or_default();
+
+    if links.is_empty() {
+        return text("No socials listed");
+    }
+
+    let mut message = MessageBuilder::new();
+    for (i, link) in links.into_iter().enumerate() {
+        if i > 0 {
+            message = message.text("    ");
+        }
+        message = message.append(link);
+    }
+    message
+}
+
+async fn security_section(token: &CardToken, client: reqwest::Client) -> MessageBuilder {
+    let chain = match Chain::from_dexscreener_id(token.chain_id) {
+        Some(chain) => chain,
+        None if token.chain_id == "solana" => {
+            return link(
+                "Rugcheck",
+                &format!("https://rugcheck.xyz/tokens/{}", token.token_ca),
+            );
+        }
+        _ => return text("No security data for this chain"),
+    };
+
+    let security = guarded(
+        Provider::GoPlus,
+        retrieve_evm_token_security(&token.token_ca, chain, client),
+    )
+    .await;
+
+    match security {
+        Ok(security) => text(&security.risk_line()),
+        Err(e) => {
+            warn!(
+                "Failed to retrieve token security {} - {e:?}",
+                token.token_ca
+            );
+            text("Failed to retrieve security data")
+        }
+    }
+}
@@ -0,0 +1,451 @@
+use serde::Deserialize;
+use serde_json::json;
+use teloxide::types::ParseMode;
+use teloxide::utils::markdown::{escape, escape_code, escape_link_url};
+
+use crate::APP_CONFIG;
+
+/// Formatted span of a MarkdownV2 message
+#[derive(Debug, Clone)]
+pub enum Markup {
+    Text(String),
+    Bold(Vec<Markup>),
+    Italic(Vec<Markup>),
+    Underline(Vec<Markup>),
+    Strike(Vec<Markup>),
+    Spoiler(Vec<Markup>),
+    Code(String),
+    Pre(String),
+    Link { text: Vec<Markup>, url: String },
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn starts_with(&self, delimiter: &str) -> bool {
+        delimiter
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    /// Raw text of code blocks and link urls, where only `\` escapes are special
+    fn read_raw(&mut self, delimiter: &str) -> String {
+        let mut raw = String::new();
+        while self.pos < self.chars.len() && !self.starts_with(delimiter) {
+            if self.chars[self.pos] == '\\' && self.pos + 1 < self.chars.len() {
+                self.pos += 1;
+            }
+            raw.push(self.chars[self.pos]);
+            self.pos += 1;
+        }
+        self.pos += delimiter.chars().count();
+        raw
+    }
+
+    /// Spans up to the closing delimiter, or up to the end of the text
+    fn parse_until(&mut self, delimiter: Option<&str>) -> Vec<Markup> {
+        let mut spans = Vec::new();
+        let mut text = String::new();
+
+        while self.pos < self.chars.len() {
+            if let Some(delimiter) = delimiter
+                && self.starts_with(delimiter)
+            {
+                self.pos += delimiter.chars().count();
+                break;
+            }
+
+            let span = match self.chars[self.pos] {
+                '\\' if self.pos + 1 < self.chars.len() => {
+                    text.push(self.chars[self.pos + 1]);
+                    self.pos += 2;
+                    continue;
+                }
+                '*' => {
+                    self.pos += 1;
+                    Markup::Bold(self.parse_until(Some("*")))
+                }
+                '_' if self.starts_with("__") => {
+                    self.pos += 2;
+                    Markup::Underline(self.parse_until(Some("__")))
+                }
+                '_' => {
+                    self.pos += 1;
+                    Markup::Italic(self.parse_until(Some("_")))
+                }
+                '~' => {
+                    self.pos += 1;
+                    Markup::Strike(self.parse_until(Some("~")))
+                }
+                '|' if self.starts_with("||") => {
+                    self.pos += 2;
+                    Markup::Spoiler(self.parse_until(Some("||")))
+                }
+                '`' if self.starts_with("```") => {
+                    self.pos += 3;
+                    Markup::Pre(self.read_raw("```"))
+                }
+                '`' => {
+                    self.pos += 1;
+                    Markup::Code(self.read_raw("`"))
+                }
+                '[' => {
+                    self.pos += 1;
+                    let link_text = self.parse_until(Some("]"));
+                    if !self.starts_with("(") {
+                        // not a link after all, keep the text as is
+                        text.push('[');
+                        text.push_str(&plain_text(&link_text));
+                        text.push(']');
+                        continue;
+                    }
+                    self.pos += 1;
+                    Markup::Link {
+                        text: link_text,
+                        url: self.read_raw(")"),
+                    }
+                }
+                c => {
+                    text.push(c);
+                    self.pos += 1;
+                    continue;
+                }
+            };
+
+            if !text.is_empty() {
+                spans.push(Markup::Text(std::mem::take(&mut text)));
+            }
+            spans.push(span);
+        }
+
+        if !text.is_empty() {
+            spans.push(Markup::Text(text));
+        }
+        spans
+    }
+}
+
+/// Parses text rendered by the card templates, which is always valid MarkdownV2
+pub fn parse_markdown_v2(text: &str) -> Vec<Markup> {
+    Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+    }
+    .parse_until(None)
+}
+
+fn plain_text(spans: &[Markup]) -> String {
+    PlainText.format(spans)
+}
+
+/// Renders the parsed card for a particular output, escaping the text the way the output needs
+pub trait Formatter {
+    fn format(&self, spans: &[Markup]) -> String;
+}
+
+pub struct TelegramMarkdownV2;
+
+impl Formatter for TelegramMarkdownV2 {
+    fn format(&self, spans: &[Markup]) -> String {
+        spans
+            .iter()
+            .map(|span| match span {
+                Markup::Text(text) => escape(text),
+                Markup::Bold(inner) => format!("*{}*", self.format(inner)),
+                Markup::Italic(inner) => format!("_{}_", self.format(inner)),
+                Markup::Underline(inner) => format!("__{}__", self.format(inner)),
+                Markup::Strike(inner) => format!("~{}~", self.format(inner)),
+                Markup::Spoiler(inner) => format!("||{}||", self.format(inner)),
+                Markup::Code(code) => format!("`{}`", escape_code(code)),
+                Markup::Pre(code) => format!("```{}```", escape_code(code)),
+                Markup::Link { text, url } => {
+                    format!("[{}]({})", self.format(text), escape_link_url(url))
+                }
+            })
+            .collect()
+    }
+}
+
+pub struct TelegramHtml;
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Formatter for TelegramHtml {
+    fn format(&self, spans: &[Markup]) -> String {
+        spans
+            .iter()
+            .map(|span| match span {
+                Markup::Text(text) => escape_html(text),
+                Markup::Bold(inner) => format!("<b>{}</b>", self.format(inner)),
+                Markup::Italic(inner) => format!("<i>{}</i>", self.format(inner)),
+                Markup::Underline(inner) => format!("<u>{}</u>", self.format(inner)),
+                Markup::Strike(inner) => format!("<s>{}</s>", self.format(inner)),
+                Markup::Spoiler(inner) => {
+                    format!("<tg-spoiler>{}</tg-spoiler>", self.format(inner))
+                }
+                Markup::Code(code) => format!("<code>{}</code>", escape_html(code)),
+                Markup::Pre(code) => format!("<pre>{}</pre>", escape_html(code)),
+                Markup::Link { text, url } => {
+                    format!("<a href=\"{}\">{}</a>", escape_html(url), self.format(text))
+                }
+            })
+            .collect()
+    }
+}
+
+pub struct PlainText;
+
+impl Formatter for PlainText {
+    fn format(&self, spans: &[Markup]) -> String {
+        spans
+            .iter()
+            .map(|span| match span {
+                Markup::Text(text) | Markup::Code(text) | Markup::Pre(text) => text.clone(),
+                Markup::Bold(inner)
+                | Markup::Italic(inner)
+                | Markup::Underline(inner)
+                | Markup::Strike(inner)
+                | Markup::Spoiler(inner) => self.format(inner),
+                Markup::Link { text, url } => format!("{} ({url})", self.format(text)),
+            })
+            .collect()
+    }
+}
+
+/// `{"text": ..., "links": [{"text": ..., "url": ...}]}` with the text as plain text
+pub struct Json;
+
+fn collect_links(spans: &[Markup], links: &mut Vec<serde_json::Value>) {
+    for span in spans {
+        match span {
+            Markup::Link { text, url } => {
+                links.push(json!({ "text": plain_text(text), "url": url }));
+            }
+            Markup::Bold(inner)
+            | Markup::Italic(inner)
+            | Markup::Underline(inner)
+            | Markup::Strike(inner)
+            | Markup::Spoiler(inner) => collect_links(inner, links),
+            Markup::Text(_) | Markup::Code(_) | Markup::Pre(_) => {}
+        }
+    }
+}
+
+/// Plain text without the link urls, they are listed separately
+fn text_without_urls(spans: &[Markup]) -> String {
+    spans
+        .iter()
+        .map(|span| match span {
+            Markup::Text(text) | Markup::Code(text) | Markup::Pre(text) => text.clone(),
+            Markup::Bold(inner)
+            | Markup::Italic(inner)
+            | Markup::Underline(inner)
+            | Markup::Strike(inner)
+            | Markup::Spoiler(inner)
+            | Markup::Link { text: inner, .. } => text_without_urls(inner),
+        })
+        .collect()
+}
+
+impl Formatter for Json {
+    fn format(&self, spans: &[Markup]) -> String {
+        let mut links = Vec::new();
+        collect_links(spans, &mut links);
+        json!({ "text": text_without_urls(spans), "links": links }).to_string()
+    }
+}
+
+pub struct DiscordMarkdown;
+
+fn escape_discord(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '*' | '_' | '~' | '`' | '|' | '[' | ']' | '(' | ')' | '>'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl Formatter for DiscordMarkdown {
+    fn format(&self, spans: &[Markup]) -> String {
+        spans
+            .iter()
+            .map(|span| match span {
+                Markup::Text(text) => escape_discord(text),
+                Markup::Bold(inner) => format!("**{}**", self.format(inner)),
+                Markup::Italic(inner) => format!("_{}_", self.format(inner)),
+                Markup::Underline(inner) => format!("__{}__", self.format(inner)),
+                Markup::Strike(inner) => format!("~~{}~~", self.format(inner)),
+                Markup::Spoiler(inner) => format!("||{}||", self.format(inner)),
+                // Discord has no escapes inside code spans, so they are closed with double backticks
+                Markup::Code(code) => format!("``{code}``"),
+                Markup::Pre(code) => format!("```\n{code}\n```"),
+                Markup::Link { text, url } => {
+                    format!("[{}]({})", self.format(text), url.replace(')', "%29"))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Output the rendered cards are converted to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    TelegramMarkdownV2,
+    TelegramHtml,
+    PlainText,
+    Json,
+    DiscordMarkdown,
+}
+
+impl OutputFormat {
+    pub fn formatter(&self) -> &'static dyn Formatter {
+        match self {
+            OutputFormat::TelegramMarkdownV2 => &TelegramMarkdownV2,
+            OutputFormat::TelegramHtml => &TelegramHtml,
+            OutputFormat::PlainText => &PlainText,
+            OutputFormat::Json => &Json,
+            OutputFormat::DiscordMarkdown => &DiscordMarkdown,
+        }
+    }
+
+    /// Converts the MarkdownV2 text rendered by the card templates to this format
+    pub fn convert(&self, markdown: &str) -> String {
+        self.formatter().format(&parse_markdown_v2(markdown))
+    }
+}
+
+/// Parse mode of the messages sent to Telegram
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TelegramParseMode {
+    /// Forgiving of stray special characters, unlike MarkdownV2
+    #[default]
+    Html,
+    MarkdownV2,
+}
+
+/// Converts the MarkdownV2 text to the configured parse mode, returns it along with the mode
+pub fn telegram_text(markdown: &str) -> (String, ParseMode) {
+    match APP_CONFIG.get().unwrap().app_config().parse_mode {
+        TelegramParseMode::Html => (
+            OutputFormat::TelegramHtml.convert(markdown),
+            ParseMode::Html,
+        ),
+        TelegramParseMode::MarkdownV2 => (markdown.to_owned(), ParseMode::MarkdownV2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn html(markdown: &str) -> String {
+        OutputFormat::TelegramHtml.convert(markdown)
+    }
+
+    #[test]
+    fn escaped_text() {
+        assert_eq!(html(r"1\.5M \- 2\.0"), "1.5M - 2.0");
+        assert_eq!(
+            html(r"a\_b \*c\* \(d\) \[e\] \~f\~ \|g\| \`h\`"),
+            "a_b *c* (d) [e] ~f~ |g| `h`"
+        );
+        assert_eq!(html(r"\#tag \+1 \=2 \{x\} \!"), "#tag +1 =2 {x} !");
+        assert_eq!(html("<b> & \"q\""), "&lt;b&gt; &amp; &quot;q&quot;");
+    }
+
+    #[test]
+    fn backslashes() {
+        assert_eq!(html(r"a\\b"), r"a\b");
+        assert_eq!(html(r"\\\\"), r"\\");
+        assert_eq!(html(r"`a\\b\`c`"), r"<code>a\b`c</code>");
+        // a trailing backslash escapes nothing
+        assert_eq!(html(r"a\"), r"a\");
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            html("*bold* _italic_ __underline__ ~strike~ ||spoiler||"),
+            "<b>bold</b> <i>italic</i> <u>underline</u> <s>strike</s> <tg-spoiler>spoiler</tg-spoiler>"
+        );
+        assert_eq!(html("```let a = 1;```"), "<pre>let a = 1;</pre>");
+        assert_eq!(html("`a_b*c`"), "<code>a_b*c</code>");
+    }
+
+    #[test]
+    fn nested_entities() {
+        assert_eq!(
+            html(r"*bold `co\_de` _it\.alic_*"),
+            "<b>bold <code>co_de</code> <i>it.alic</i></b>"
+        );
+        assert_eq!(
+            html("__*[link](https://a.io)*__"),
+            "<u><b><a href=\"https://a.io\">link</a></b></u>"
+        );
+        assert_eq!(
+            html(r"[*G\.* `x`](https://a.io)"),
+            "<a href=\"https://a.io\"><b>G.</b> <code>x</code></a>"
+        );
+    }
+
+    #[test]
+    fn link_urls() {
+        assert_eq!(
+            html(r"[GMGN](https://gmgn\.ai/sol/token/a\_b\-c?x=\(1\))"),
+            "<a href=\"https://gmgn.ai/sol/token/a_b-c?x=(1)\">GMGN</a>"
+        );
+        assert_eq!(
+            html(r"[DT](https://x.io/a_b-c.d(e\))"),
+            "<a href=\"https://x.io/a_b-c.d(e)\">DT</a>"
+        );
+        assert_eq!(
+            html(r#"[X](https://x.io/a\\b?q=1&r="2")"#),
+            "<a href=\"https://x.io/a\\b?q=1&amp;r=&quot;2&quot;\">X</a>"
+        );
+        assert_eq!(
+            html("[A](https://a.io)    [B](https://b.io)"),
+            "<a href=\"https://a.io\">A</a>    <a href=\"https://b.io\">B</a>"
+        );
+    }
+
+    #[test]
+    fn brackets_without_url() {
+        assert_eq!(html("[2x] done"), "[2x] done");
+        assert_eq!(html("[*a*]"), "[a]");
+    }
+
+    #[test]
+    fn unclosed_entities_run_to_the_end() {
+        assert_eq!(html("*bold"), "<b>bold</b>");
+        assert_eq!(html("`code"), "<code>code</code>");
+    }
+
+    #[test]
+    fn markdown_v2_round_trip() {
+        for markdown in [
+            r"*A\_B\.C* \- Dog\-wif \(hat\)",
+            r"`0xAb_c\\1\`2`",
+            r"[GMGN](https://gmgn.ai/a_b-c.d(e\))    [DF](https://defined.fi/x)",
+            r"*bold `code` _italic_* ~s~ ||sp|| __u__",
+        ] {
+            assert_eq!(OutputFormat::TelegramMarkdownV2.convert(markdown), markdown);
+        }
+    }
+}
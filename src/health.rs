@@ -0,0 +1,136 @@
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::time::Duration;
+
+use chrono::Utc;
+use log::{debug, info, warn};
+use serde::Serialize;
+use teloxide::Bot;
+use teloxide::prelude::Requester;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::outbox::{OutboxStats, outbox_stats};
+
+const GET_ME_INTERVAL: Duration = Duration::from_secs(60);
+
+static DISPATCHER_RUNNING: AtomicBool = AtomicBool::new(false);
+/// Unix timestamp, 0 means no update has been received yet
+static LAST_UPDATE_AT: AtomicI64 = AtomicI64::new(0);
+static LAST_GET_ME_OK: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Serialize)]
+struct HealthReport {
+    healthy: bool,
+    dispatcher_running: bool,
+    /// Unix timestamp of the last update received from Telegram
+    last_update_at: Option<i64>,
+    last_get_me_ok: bool,
+    outbox: OutboxStats,
+}
+
+impl HealthReport {
+    fn current() -> Self {
+        let dispatcher_running = DISPATCHER_RUNNING.load(Ordering::Relaxed);
+        let last_get_me_ok = LAST_GET_ME_OK.load(Ordering::Relaxed);
+        let last_update_at = match LAST_UPDATE_AT.load(Ordering::Relaxed) {
+            0 => None,
+            timestamp => Some(timestamp),
+        };
+
+        Self {
+            healthy: is_healthy(),
+            dispatcher_running,
+            last_update_at,
+            last_get_me_ok,
+            outbox: outbox_stats(),
+        }
+    }
+}
+
+/// The dispatcher is running and the last getMe succeeded
+pub fn is_healthy() -> bool {
+    DISPATCHER_RUNNING.load(Ordering::Relaxed) && LAST_GET_ME_OK.load(Ordering::Relaxed)
+}
+
+pub fn set_dispatcher_running(running: bool) {
+    DISPATCHER_RUNNING.store(running, Ordering::Relaxed);
+}
+
+pub fn record_update_received() {
+    LAST_UPDATE_AT.store(Utc::now().timestamp(), Ordering::Relaxed);
+}
+
+pub fn record_get_me(ok: bool) {
+    LAST_GET_ME_OK.store(ok, Ordering::Relaxed);
+}
+
+/// Keeps checking that the bot token still works and Telegram is reachable
+pub fn spawn_get_me_checker(bot: Bot) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(GET_ME_INTERVAL);
+        // the first tick completes immediately, getMe was just done at startup
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            match bot.get_me().await {
+                Ok(_) => record_get_me(true),
+                Err(e) => {
+                    warn!("Failed to perform getMe on bot - {e:?}");
+                    record_get_me(false);
+                }
+            }
+        }
+    });
+}
+
+/// Serves `GET /healthz` - 200 with a JSON report when healthy, 503 otherwise
+pub async fn spawn_health_server(addr: &str) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("Serving health checks on {addr}");
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(async move {
+                        if let Err(e) = serve_connection(stream).await {
+                            debug!("Failed to serve health check - {e:?}");
+                        }
+                    });
+                }
+                Err(e) => warn!("Failed to accept health check connection - {e:?}"),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+async fn serve_connection(mut stream: TcpStream) -> anyhow::Result<()> {
+    let mut buf = [0u8; 1024];
+    let read = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..read]);
+    let request_line = request.lines().next().unwrap_or_default();
+
+    let (status, body) = if request_line.starts_with("GET /healthz ") {
+        let report = HealthReport::current();
+        let status = if report.healthy {
+            "200 OK"
+        } else {
+            "503 Service Unavailable"
+        };
+        (status, serde_json::to_string(&report)?)
+    } else {
+        ("404 Not Found", "{}".to_owned())
+    };
+
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+
+    Ok(())
+}
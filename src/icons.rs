@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use teloxide::types::ChatId;
+
+use crate::APP_CONFIG;
+
+/// Icons used in token cards. Empty icon means the item is rendered without one.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IconSet {
+    pub name: String,
+    pub ca: String,
+    pub mcap: String,
+    pub price: String,
+    pub liquidity: String,
+    pub exit: String,
+    pub unlock: String,
+    pub security: String,
+    pub gmgn: String,
+    pub defined: String,
+    pub dextools: String,
+    pub pancake: String,
+    pub uniswap: String,
+    pub meteora: String,
+    pub rugcheck: String,
+    pub trenchradar: String,
+    pub jupiter: String,
+    pub dexscreener: String,
+    pub tonviewer: String,
+    pub sunpump: String,
+    pub tronscan: String,
+}
+
+impl Default for IconSet {
+    fn default() -> Self {
+        Self {
+            name: "🏷️".to_owned(),
+            ca: "📜".to_owned(),
+            mcap: "💵".to_owned(),
+            price: "💲".to_owned(),
+            liquidity: "💧".to_owned(),
+            exit: "🛟".to_owned(),
+            unlock: "🔓".to_owned(),
+            security: "🛡️".to_owned(),
+            gmgn: "🦎".to_owned(),
+            defined: "🅳".to_owned(),
+            dextools: "🔄".to_owned(),
+            pancake: "🥞".to_owned(),
+            uniswap: "🦄".to_owned(),
+            meteora: "☄️".to_owned(),
+            rugcheck: "🦝".to_owned(),
+            trenchradar: "📡".to_owned(),
+            jupiter: "🪐".to_owned(),
+            dexscreener: "🦅".to_owned(),
+            tonviewer: "💎".to_owned(),
+            sunpump: "☀️".to_owned(),
+            tronscan: "🔍".to_owned(),
+        }
+    }
+}
+
+impl IconSet {
+    pub fn plain() -> Self {
+        Self {
+            name: String::new(),
+            ca: String::new(),
+            mcap: String::new(),
+            price: String::new(),
+            liquidity: String::new(),
+            exit: String::new(),
+            unlock: String::new(),
+            security: String::new(),
+            gmgn: String::new(),
+            defined: String::new(),
+            dextools: String::new(),
+            pancake: String::new(),
+            uniswap: String::new(),
+            meteora: String::new(),
+            rugcheck: String::new(),
+            trenchradar: String::new(),
+            jupiter: String::new(),
+            dexscreener: String::new(),
+            tonviewer: String::new(),
+            sunpump: String::new(),
+            tronscan: String::new(),
+        }
+    }
+}
+
+/// Icon set as written in the config, e.g. `{"preset": "plain"}` or
+/// `{"preset": "custom", "gmgn": "G"}` - icons missing in a custom set fall back to the default ones
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(tag = "preset", rename_all = "lowercase")]
+pub enum IconSetConfig {
+    #[default]
+    Default,
+    Plain,
+    Custom(Box<IconSet>),
+}
+
+impl IconSetConfig {
+    pub fn icon_set(&self) -> IconSet {
+        match self {
+            IconSetConfig::Default => IconSet::default(),
+            IconSetConfig::Plain => IconSet::plain(),
+            IconSetConfig::Custom(icons) => icons.as_ref().clone(),
+        }
+    }
+}
+
+/// Icon followed by a space, or nothing for an empty icon
+pub fn icon_prefix(icon: &str) -> String {
+    if icon.is_empty() {
+        String::new()
+    } else {
+        format!("{icon} ")
+    }
+}
+
+pub fn icon_set_for_chat(chat_id: ChatId) -> IconSet {
+    let cfg = APP_CONFIG.get().unwrap().app_config();
+    let ChatId(id) = chat_id;
+
+    cfg.chats
+        .get(&id)
+        .and_then(|chat| chat.icons.as_ref())
+        .unwrap_or(&cfg.icons)
+        .icon_set()
+}
@@ -0,0 +1,139 @@
+use log::{debug, warn};
+use teloxide::Bot;
+use teloxide::payloads::AnswerInlineQuerySetters;
+use teloxide::prelude::{Requester, ResponseResult};
+use teloxide::types::{
+    ChatId, InlineQuery, InlineQueryResult, InlineQueryResultArticle, InputMessageContent,
+    InputMessageContentText, LinkPreviewOptions,
+};
+
+use crate::APP_CONFIG;
+use crate::formatter::telegram_text;
+use crate::icons::icon_set_for_chat;
+use crate::render::{CardContext, CardKind, render_card};
+use crate::token_info::{
+    extract_evm_cas, extract_solana_cas, extract_ton_cas, extract_tron_cas, probe_evm_chains,
+    retrieve_solana_token_info, retrieve_ton_token_info, retrieve_tron_token_info,
+};
+use crate::validation::{is_valid_evm_address, is_valid_solana_address};
+
+/// Inline results are cached by Telegram for this long
+const INLINE_CACHE_TIME_SECS: u32 = 60;
+
+/// Card rendered for an inline query, without the extra lookups done for
+/// regular cards so the answer stays fast
+pub struct InlineCard {
+    pub kind: CardKind,
+    pub context: CardContext,
+}
+
+/// Card of the first CA in `query`, `chat_id` picks the icons and the EVM chains
+pub async fn lookup_inline_card(
+    query: &str,
+    chat_id: ChatId,
+    client: reqwest::Client,
+) -> Option<InlineCard> {
+    let icons = icon_set_for_chat(chat_id);
+
+    if let Some(token_ca) = extract_ton_cas(query).first() {
+        return match retrieve_ton_token_info(token_ca, client).await {
+            Ok(data) => Some(InlineCard {
+                kind: CardKind::Ton,
+                context: CardContext::ton(&data, icons),
+            }),
+            Err(err) => {
+                warn!("Failed to retrieve token info {token_ca} - {err:?}");
+                None
+            }
+        };
+    }
+
+    if let Some(token_ca) = extract_tron_cas(query).first() {
+        return match retrieve_tron_token_info(token_ca, client).await {
+            Ok(data) => Some(InlineCard {
+                kind: CardKind::Tron,
+                context: CardContext::tron(&data, icons),
+            }),
+            Err(err) => {
+                warn!("Failed to retrieve token info {token_ca} - {err:?}");
+                None
+            }
+        };
+    }
+
+    if let Some(token_ca) = extract_solana_cas(query)
+        .iter()
+        .find(|ca| is_valid_solana_address(ca))
+    {
+        return match retrieve_solana_token_info(token_ca, client).await {
+            Ok(data) => Some(InlineCard {
+                kind: CardKind::Solana,
+                context: CardContext::solana(&data, icons),
+            }),
+            Err(err) => {
+                warn!("Failed to retrieve token info {token_ca} - {err:?}");
+                None
+            }
+        };
+    }
+
+    let token_ca = extract_evm_cas(query)
+        .into_iter()
+        .find(|ca| is_valid_evm_address(ca))?;
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+    match probe_evm_chains(&token_ca, app_config.evm_chains(chat_id), client).await {
+        Ok(data) => Some(InlineCard {
+            kind: CardKind::Evm,
+            context: CardContext::evm(&data, icons),
+        }),
+        Err(errors) => {
+            for (chain, err) in errors {
+                warn!("Failed to retrieve token info {token_ca} on {chain:?} - {err:?}");
+            }
+            None
+        }
+    }
+}
+
+/// Answers `@bot <ca>` with the token card, works in any chat since the bot
+/// doesn't need to be a member. Inline mode must be enabled via @BotFather.
+pub async fn inline_query_handler(
+    bot: Bot,
+    query: InlineQuery,
+    client: reqwest::Client,
+) -> ResponseResult<()> {
+    debug!("Got {query:?}");
+
+    let chat_id = ChatId::from(query.from.id);
+    let results = match lookup_inline_card(query.query.trim(), chat_id, client).await {
+        Some(InlineCard { kind, context }) => {
+            let (text, parse_mode) = telegram_text(&render_card(chat_id, kind, &context));
+            let content = InputMessageContentText {
+                link_preview_options: Some(LinkPreviewOptions {
+                    is_disabled: true,
+                    url: None,
+                    prefer_small_media: false,
+                    prefer_large_media: false,
+                    show_above_text: false,
+                }),
+                ..InputMessageContentText::new(text).parse_mode(parse_mode)
+            };
+            // ids are limited to 64 bytes, too short for some addresses, and there's a single result anyway
+            let article = InlineQueryResultArticle::new(
+                context.chain_id,
+                format!("{} - {}", context.symbol, context.name),
+                InputMessageContent::Text(content),
+            )
+            .description(format!("{} - {}", context.mcap, context.chain));
+
+            vec![InlineQueryResult::Article(article)]
+        }
+        None => Vec::new(),
+    };
+
+    bot.answer_inline_query(query.id, results)
+        .cache_time(INLINE_CACHE_TIME_SECS)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::sync::OnceLock;
+
+use log::{debug, info, warn};
+use regex::Regex;
+use teloxide::types::{Message, MessageEntityKind};
+
+use crate::dexscreener::retrieve_pair;
+
+// TON goes first, otherwise base58 would match a prefix of a TON address
+const ADDRESS_PATTERN: &str =
+    "(?P<address>(?:EQ|UQ)[A-Za-z0-9_-]{46}|0x[a-fA-F0-9]{40}|[1-9A-HJ-NP-Za-km-z]{32,44})";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum LinkTarget {
+    /// The link always points to a pair (pool) address
+    Pair,
+    /// The link always points to a token address
+    Token,
+    /// The link may point either to a pair or to a token
+    PairOrToken,
+}
+
+#[derive(Debug)]
+struct LinkPattern {
+    regex: Regex,
+    target: LinkTarget,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkedToken {
+    /// DexScreener chain id, e.g. `solana`, `bsc`, `base`, `ton`, `tron`
+    pub chain_id: String,
+    pub token_ca: String,
+}
+
+static LINK_PATTERNS: OnceLock<Vec<LinkPattern>> = OnceLock::new();
+
+pub fn init_link_regexes() {
+    let patterns = [
+        (
+            format!("https?://(?:www\\.)?dexscreener\\.com/(?P<chain>[a-z]+)/{ADDRESS_PATTERN}"),
+            LinkTarget::PairOrToken,
+        ),
+        (
+            format!(
+                "https?://(?:www\\.)?dextools\\.io/app/(?:[a-z]{{2}}/)?(?P<chain>[a-z]+)/pair-explorer/{ADDRESS_PATTERN}"
+            ),
+            // dextools links, ours included, often carry the token CA instead of the pair
+            LinkTarget::PairOrToken,
+        ),
+        (
+            format!(
+                "https?://(?:www\\.)?birdeye\\.so/(?:(?P<chain>[a-z]+)/)?token/{ADDRESS_PATTERN}(?:\\?chain=(?P<query_chain>[a-z]+))?"
+            ),
+            LinkTarget::Token,
+        ),
+        (
+            format!("https?://photon-sol\\.tinyastro\\.io/[a-z]{{2}}/lp/{ADDRESS_PATTERN}"),
+            LinkTarget::Pair,
+        ),
+        (
+            format!(
+                "https?://(?:www\\.)?(?P<chain>bscscan\\.com|basescan\\.org|arbiscan\\.io|polygonscan\\.com|etherscan\\.io)/token/{ADDRESS_PATTERN}"
+            ),
+            LinkTarget::Token,
+        ),
+    ];
+
+    // this is safe as long as the regexes themselves are valid
+    let patterns = patterns
+        .into_iter()
+        .map(|(regex, target)| LinkPattern {
+            regex: Regex::new(&regex).unwrap(),
+            target,
+        })
+        .collect();
+    // This is safe if init_link_regexes is called just once directly in the main fn
+    LINK_PATTERNS.set(patterns).unwrap();
+}
+
+/// Normalizes chain names used by various sites to DexScreener chain ids
+fn normalize_chain(chain: &str) -> &str {
+    match chain {
+        "sol" => "solana",
+        "bnb" => "bsc",
+        "ether" | "eth" | "etherscan.io" => "ethereum",
+        "bscscan.com" => "bsc",
+        "basescan.org" => "base",
+        "arbiscan.io" => "arbitrum",
+        "polygonscan.com" => "polygon",
+        chain => chain,
+    }
+}
+
+/// Used when the link itself doesn't carry a chain
+fn default_chain(address: &str) -> &'static str {
+    if address.starts_with("0x") {
+        "bsc"
+    } else if address.len() == 48 && (address.starts_with("EQ") || address.starts_with("UQ")) {
+        "ton"
+    } else if address.len() == 34 && address.starts_with('T') {
+        "tron"
+    } else {
+        "solana"
+    }
+}
+
+/// URLs of the hyperlinked text of the message (or its caption), one per line.
+/// The plain text doesn't contain them, so CAs behind the links are missed otherwise.
+pub fn hidden_link_urls(message: &Message) -> String {
+    let entities = message
+        .entities()
+        .or_else(|| message.caption_entities())
+        .unwrap_or_default();
+
+    entities
+        .iter()
+        .filter_map(|entity| match &entity.kind {
+            MessageEntityKind::TextLink { url } => Some(url.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Token or pair address found in a link, not resolved yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundLink {
+    /// DexScreener chain id, e.g. `solana`, `bsc`, `base`, `ton`, `tron`
+    pub chain_id: String,
+    pub address: String,
+    target: LinkTarget,
+    /// Byte offset of the link in the text
+    pub position: usize,
+}
+
+/// Finds dexscreener.com, dextools.io, birdeye.so, photon-sol and block explorer links
+/// in the text, ordered by their position. Local only, nothing is resolved yet.
+pub fn find_links(text: &str) -> Vec<FoundLink> {
+    let mut links: Vec<FoundLink> = Vec::new();
+
+    for pattern in LINK_PATTERNS.get().unwrap() {
+        for captures in pattern.regex.captures_iter(text) {
+            let address = &captures["address"];
+            let chain_id = captures
+                .name("chain")
+                .or_else(|| captures.name("query_chain"))
+                .map(|c| normalize_chain(c.as_str()))
+                .unwrap_or_else(|| default_chain(address));
+
+            if links
+                .iter()
+                .any(|link| link.chain_id == chain_id && link.address == address)
+            {
+                continue;
+            }
+            links.push(FoundLink {
+                chain_id: chain_id.to_owned(),
+                address: address.to_owned(),
+                target: pattern.target,
+                position: captures.get(0).map_or(0, |m| m.start()),
+            });
+        }
+    }
+
+    links.sort_by_key(|link| link.position);
+    links
+}
+
+/// Pair addresses are resolved to the base token of the pair via DexScreener
+pub async fn resolve_link(link: FoundLink, client: reqwest::Client) -> Option<LinkedToken> {
+    let FoundLink {
+        chain_id,
+        address,
+        target,
+        ..
+    } = link;
+
+    let token_ca = match target {
+        LinkTarget::Token => address,
+        LinkTarget::Pair | LinkTarget::PairOrToken => {
+            match retrieve_pair(&chain_id, &address, client).await {
+                Ok(Some(pair)) => {
+                    debug!(
+                        "Resolved pair {address} on {chain_id} to token {}",
+                        pair.base_token.address
+                    );
+                    pair.base_token.address
+                }
+                Ok(None) if target == LinkTarget::PairOrToken => address,
+                Ok(None) => {
+                    warn!("Pair {address} not found on {chain_id}");
+                    return None;
+                }
+                Err(err) => {
+                    warn!("Failed to resolve pair {address} on {chain_id} - {err:?}");
+                    return None;
+                }
+            }
+        }
+    };
+
+    info!("FOUND LINKED TOKEN CA {token_ca} on {chain_id}");
+    Some(LinkedToken { chain_id, token_ca })
+}
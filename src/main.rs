@@ -1,39 +1,216 @@
+pub mod abuse;
+pub mod aliases;
+pub mod auto_leave;
+pub mod backfill;
+pub mod birdeye;
+pub mod blacklist;
+pub mod cache;
+pub mod coingecko;
+pub mod commands;
+#[cfg(feature = "mtproto")]
+pub mod companion;
 pub mod config;
+pub mod context;
+pub mod dedupe;
+pub mod deep_links;
+pub mod delete;
+pub mod delivery;
+pub mod dexscreener;
+pub mod digest;
+pub mod email;
+pub mod error_reports;
+pub mod fmt;
+pub mod followups;
+pub mod formatter;
+pub mod health;
+pub mod icons;
+pub mod inline;
+pub mod links;
+pub mod maintenance;
+pub mod market;
+pub mod mentions;
+pub mod moralis_keys;
+pub mod more;
+pub mod notify;
+pub mod outbox;
+pub mod pacing;
+pub mod providers;
+pub mod quota;
+pub mod render;
+pub mod retry;
+pub mod router;
+pub mod sanitize;
+pub mod scoring;
+pub mod security;
+pub mod settings;
+pub mod share;
+pub mod singleflight;
+pub mod solana_rpc;
+pub mod stats;
+pub mod storage;
+pub mod supervisor;
+pub mod symbols;
+pub mod tags;
 pub mod token_info;
+pub mod token_lists;
+pub mod trending;
+pub mod unlocks;
+pub mod user_limits;
+pub mod validation;
+pub mod wallets;
+pub mod watchlist;
+pub mod webhook;
+pub mod whitelist;
 
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, OnceLock};
 
 use chrono::{DateTime, Duration, Utc};
 use flexi_logger::{AdaptiveFormat, Logger};
+use futures::{StreamExt, future, stream};
 use log::{debug, info, warn};
-use teloxide::Bot;
-use teloxide::dispatching::UpdateFilterExt;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use sqlx::SqlitePool;
+use teloxide::dispatching::{HandlerExt, ShutdownToken, UpdateFilterExt};
 use teloxide::payloads::SendMessageSetters;
-use teloxide::prelude::{Dispatcher, Requester, ResponseResult};
+use teloxide::prelude::{Dispatcher, LoggingErrorHandler, Requester, ResponseResult};
 use teloxide::sugar::request::{RequestLinkPreviewExt, RequestReplyExt};
-use teloxide::types::{Chat, ChatId, Message, ParseMode, ThreadId, Update, User};
-use teloxide::utils::markdown::escape;
+use teloxide::types::{
+    CallbackQuery, Chat, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Message, ParseMode,
+    ThreadId, Update, User, UserId,
+};
+use teloxide::{ApiError, Bot, RequestError, dptree};
 use tokio::sync::RwLock;
 
-use crate::config::{RuntimeConfig, load_config_or_default};
-use crate::token_info::{init_evm_token_ca_regex, init_solana_token_ca_regex, retrieve_evm_token_info, retrieve_solana_token_info, Chain, EVM_TOKEN_CA_REGEX, SOLANA_TOKEN_CA_REGEX};
+use crate::abuse::{ABUSE_CALLBACK_PREFIX, handle_abuse_action, track_non_whitelisted_message};
+use crate::aliases::find_aliased_cas;
+use crate::auto_leave::handle_my_chat_member;
+use crate::blacklist::is_blacklisted;
+use crate::cache::{
+    caches, init_caches, restore_token_info_cache, save_token_info_cache,
+    spawn_cache_stats_reporter,
+};
+use crate::coingecko::retrieve_solana_origin;
+use crate::commands::{Command, command_handler};
+#[cfg(feature = "mtproto")]
+use crate::companion::start_companion;
+#[cfg(unix)]
+use crate::config::spawn_sighup_reloader;
+use crate::config::{Config, ReplyOrder, RuntimeConfig, default_config_path, load_config};
+use crate::context::MessageContext;
+use crate::dedupe::is_duplicate_text;
+use crate::deep_links::open_in_dm_url;
+use crate::delete::{DELETE_CALLBACK_DATA, delete_button, handle_delete, schedule_auto_delete};
+use crate::delivery::init_private_chat_users;
+use crate::digest::spawn_digest_task;
+use crate::error_reports::{ErrorClass, init_error_reports, report_error};
+use crate::followups::{CardToken, answer_follow_up, remember_card};
+use crate::formatter::{OutputFormat, telegram_text};
+use crate::health::{
+    record_get_me, record_update_received, set_dispatcher_running, spawn_get_me_checker,
+    spawn_health_server,
+};
+use crate::icons::icon_set_for_chat;
+use crate::inline::inline_query_handler;
+use crate::links::{hidden_link_urls, init_link_regexes};
+use crate::maintenance::spawn_maintenance_task;
+use crate::market::{market_note, spawn_native_price_feed};
+use crate::mentions::record_mention;
+use crate::more::{MORE_CALLBACK_DATA, handle_more, more_button};
+use crate::notify::init_notifier;
+use crate::outbox::{CardReply, enqueue_reply, is_retryable, spawn_outbox_worker};
+use crate::pacing::{record_send_error, wait_for_send_slot};
+use crate::providers::{Provider, guarded};
+use crate::quota::{log_usage, spawn_quota_flusher};
+use crate::render::{CardContext, CardKind, render_card, render_compact_card, render_wallet_card};
+use crate::router::{RoutedAddresses, init_address_router, route_addresses};
+use crate::sanitize::sanitize_text;
+use crate::scoring::{RiskSignals, risk_score, risk_weights_for_chat};
+use crate::security::retrieve_evm_token_security;
+use crate::settings::{
+    SETTINGS_CALLBACK_PREFIX, chat_settings, handle_settings_action, init_chat_settings,
+};
+use crate::share::{SHARE_CALLBACK_DATA, handle_share, share_button};
+use crate::stats::spawn_mention_refresher;
+use crate::storage::{load_throttling_info, open_storage, save_throttling_info};
+use crate::supervisor::{
+    EXIT_CONFIG_ERROR, EXIT_RUNTIME_FAILURE, exit_with, notify_ready, notify_stopping,
+    spawn_watchdog,
+};
+use crate::symbols::{SymbolCollision, record_symbol};
+use crate::tags::record_tags;
+use crate::token_info::{
+    Chain, TokenInfoError, init_evm_token_ca_regex, init_solana_token_ca_regex,
+    init_ton_token_ca_regex, init_tron_token_ca_regex, probe_evm_chains,
+    retrieve_solana_token_info, retrieve_ton_token_info, retrieve_tron_token_info,
+};
+use crate::token_lists::{find_cashtag_cas, init_cashtag_regex, spawn_token_list_refresher};
+use crate::trending::EXPAND_CALLBACK_PREFIX;
+use crate::unlocks::retrieve_next_unlock;
+use crate::user_limits::{record_user_cards, user_card_budget};
+use crate::wallets::{retrieve_evm_wallet, retrieve_solana_wallet};
+use crate::watchlist::spawn_watch_poller;
+use crate::webhook::webhook_listener;
 
 static APP_CONFIG: OnceLock<RuntimeConfig> = OnceLock::new();
 
 const ALLOWED_THROTTLING: Duration = Duration::minutes(5);
 
+/// Longest cooldown chats may pick via /settings, throttle entries and posted mcaps
+/// (`POSTED_MCAPS_TTL`) are kept this long
+const MAX_THROTTLING: Duration = Duration::minutes(60);
+
 const AGE_THRESHOLD: Duration = Duration::minutes(6);
 
+/// How often the throttle data and the token info cache are saved for a warm start
+const WARM_START_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// How many token lookups of a single message may run at the same time
+const MAX_CONCURRENT_LOOKUPS: usize = 4;
+
 type ThrottlingInfo = HashMap<(Cow<'static, str>, ChatId, Option<ThreadId>), DateTime<Utc>>;
 
+/// Rendered reply for a single token
+struct TokenCard {
+    /// DexScreener chain id
+    chain_id: &'static str,
+    token_ca: String,
+    mcap: Option<Decimal>,
+    text: String,
+    /// Full card when `text` is the compact one
+    full_text: Option<String>,
+    buttons: Vec<InlineKeyboardButton>,
+}
+
 type Cache = Arc<RwLock<HashMap<(Cow<'static, str>, ChatId, Option<ThreadId>), DateTime<Utc>>>>;
 
 fn is_whitelisted_chat(chat: &Chat, cfg: &RuntimeConfig) -> bool {
     let ChatId(id) = chat.id;
 
-    cfg.app_config.whitelisted_chats.contains(&id)
+    cfg.app_config().whitelisted_chats.contains(&id)
+}
+
+/// Private chats with the bot are served apart from the whitelist, if enabled
+fn is_allowed_private_chat(message: &Message, cfg: &RuntimeConfig) -> bool {
+    let app_config = cfg.app_config();
+    if !message.chat.is_private() || !app_config.allow_private_chats {
+        return false;
+    }
+
+    let Some(User { id: UserId(id), .. }) = message.from else {
+        return false;
+    };
+    app_config.private_chat_user_ids.is_empty() || app_config.private_chat_user_ids.contains(&id)
+}
+
+/// Paused chats stay whitelisted, so they aren't left or reported, but are ignored
+fn is_paused_chat(chat: &Chat, cfg: &RuntimeConfig) -> bool {
+    let ChatId(id) = chat.id;
+
+    cfg.app_config().paused_chats.contains(&id)
 }
 
 fn is_message_too_old(msg: &Message) -> bool {
@@ -42,11 +219,24 @@ fn is_message_too_old(msg: &Message) -> bool {
     diff > AGE_THRESHOLD
 }
 
+/// Text of the message being replied to unless it is one of our own messages
+fn quoted_text(message: &Message) -> Option<String> {
+    let quoted = message.reply_to_message()?;
+    let bot_id = APP_CONFIG.get().unwrap().bot_info.id;
+    if quoted.from.as_ref().is_some_and(|user| user.id == bot_id) {
+        return None;
+    }
+
+    let text = quoted.text().or_else(|| quoted.caption())?;
+    Some(format!("{text}\n{}", hidden_link_urls(quoted)))
+}
+
 async fn message_handler(
     bot: Bot,
     message: Message,
     client: reqwest::Client,
     cache: Arc<RwLock<ThrottlingInfo>>,
+    storage: SqlitePool,
 ) -> ResponseResult<()> {
     debug!("Got {message:?}");
 
@@ -58,21 +248,34 @@ async fn message_handler(
 
     let app_cfg = APP_CONFIG.get().unwrap();
 
-    if !is_whitelisted_chat(&message.chat, app_cfg) {
+    let private_chat = is_allowed_private_chat(&message, app_cfg);
+    if !private_chat && !is_whitelisted_chat(&message.chat, app_cfg) {
         debug!("Skipping message since it is not coming from whitelisted chat");
+        track_non_whitelisted_message(&bot, &message.chat).await;
         return Ok(());
     }
 
-    // skip our own messages or messages from other bots
-    if let Some(User { is_bot: true, .. }) = message.from {
+    if is_paused_chat(&message.chat, app_cfg) {
+        debug!("Skipping message since the chat is paused");
+        return Ok(());
+    }
+
+    // skip our own messages or messages from other bots, messages posted on behalf
+    // of a channel (e.g. automatic forwards to the discussion group) come from
+    // a service bot account though
+    if let Some(User { is_bot: true, .. }) = message.from
+        && message.sender_chat.is_none()
+    {
         debug!("This message is from a bot - ignoring it!");
         return Ok(());
     }
 
     let bot_id = &app_cfg.bot_info.id;
-    if let Some(User { id, .. }) = message.forward_from_user() && id == bot_id  {
+    if let Some(User { id, .. }) = message.forward_from_user()
+        && id == bot_id
+    {
         debug!("This is our own message - skipping");
-        return Ok(())
+        return Ok(());
     }
 
     let maybe_text = message.text().or_else(|| message.caption());
@@ -81,190 +284,1004 @@ async fn message_handler(
         return Ok(());
     };
 
-    process_solana_cas(&bot, &message, client.clone(), &cache, msg_text).await;
-    process_evm_cas(&bot, &message, client, &cache, msg_text).await;
+    if answer_follow_up(&bot, &message, client.clone()).await {
+        return Ok(());
+    }
+
+    // re-sending a CA in a private chat asks for a fresh card, like the throttle there
+    if !private_chat && is_duplicate_text(message.chat.id, msg_text).await {
+        debug!(
+            "Same text was seen in chat {} recently - skipping it",
+            message.chat.id
+        );
+        return Ok(());
+    }
+
+    let card_budget = user_card_budget(message.chat.id, message.from.as_ref());
+    if card_budget == Some(0) {
+        debug!(
+            "User {:?} triggered too many cards in {} recently - skipping the message",
+            message.from.as_ref().map(|user| user.id),
+            message.chat.id
+        );
+        return Ok(());
+    }
+
+    let mut ctx = MessageContext::new(bot, &message, client, cache, storage);
+    // whoever sends a CA in a private chat wants to see its card
+    ctx.bypass_throttle = private_chat;
+    debug!(
+        "[{}] Processing message {} in {}",
+        ctx.trace_id, message.id, message.chat.id
+    );
+
+    let aliased_cas = find_aliased_cas(&ctx.storage, ctx.chat_id(), msg_text).await;
+    let cashtag_cas = find_cashtag_cas(&ctx.storage, msg_text, ctx.client.clone()).await;
+    let link_urls = hidden_link_urls(&message);
+    let mut msg_text = format!("{msg_text}\n{aliased_cas}\n{cashtag_cas}\n{link_urls}");
+    if chat_settings(ctx.chat_id()).scan_replies
+        && let Some(quoted) = quoted_text(&message)
+    {
+        msg_text = format!("{msg_text}\n{quoted}");
+    }
+    let msg_text = &msg_text;
+
+    let routed = route_addresses(msg_text, ctx.client.clone()).await;
+    let mut cards = process_routed_addresses(&ctx, &routed).await;
+
+    if ctx.app_config.reply_order == ReplyOrder::Text {
+        // lookups finish in any order, the cards are buffered until all of them are done
+        let lower_text = msg_text.to_lowercase();
+        cards.sort_by_key(|card| {
+            lower_text
+                .find(&card.token_ca.to_lowercase())
+                .unwrap_or(usize::MAX)
+        });
+    }
+
+    if let Some(budget) = card_budget
+        && cards.len() > budget
+    {
+        info!(
+            "[{}] User rate limit leaves {budget} of {} cards",
+            ctx.trace_id,
+            cards.len()
+        );
+        cards.truncate(budget);
+    }
+    record_user_cards(ctx.chat_id(), message.from.as_ref(), cards.len());
+
+    send_cards(&ctx, cards).await;
 
     Ok(())
 }
 
-async fn process_evm_cas(
-    bot: &Bot,
-    message: &Message,
+async fn callback_handler(
+    bot: Bot,
+    query: CallbackQuery,
     client: reqwest::Client,
-    cache: &Cache,
-    msg_text: &str,
-) {
-    for (_, [token_ca]) in EVM_TOKEN_CA_REGEX
-        .get()
-        .unwrap()
-        .captures_iter(msg_text)
-        .map(|c| c.extract())
+    cache: Arc<RwLock<ThrottlingInfo>>,
+    storage: SqlitePool,
+) -> ResponseResult<()> {
+    debug!("Got {query:?}");
+
+    if query.data.as_deref() == Some(SHARE_CALLBACK_DATA) {
+        return handle_share(&bot, &query).await;
+    }
+
+    if query
+        .data
+        .as_deref()
+        .is_some_and(|data| data.starts_with(SETTINGS_CALLBACK_PREFIX))
     {
-        info!(
-            "FOUND EVM TOKEN CA in the message {:?} - {token_ca}",
-            message.id
-        );
+        return handle_settings_action(&bot, &query, &storage).await;
+    }
 
-        if should_we_throttle_ca(message, cache, token_ca).await {
-            continue;
-        }
+    if query.data.as_deref() == Some(MORE_CALLBACK_DATA) {
+        return handle_more(&bot, &query).await;
+    }
+
+    if query.data.as_deref() == Some(DELETE_CALLBACK_DATA) {
+        return handle_delete(&bot, &query).await;
+    }
+
+    if query
+        .data
+        .as_deref()
+        .is_some_and(|data| data.starts_with(ABUSE_CALLBACK_PREFIX))
+    {
+        return handle_abuse_action(&bot, &query).await;
+    }
+
+    bot.answer_callback_query(query.id.clone()).await?;
+
+    let Some(message) = query.regular_message() else {
+        debug!("Callback message is inaccessible - skipping it");
+        return Ok(());
+    };
+
+    let app_cfg = APP_CONFIG.get().unwrap();
+    if !is_whitelisted_chat(&message.chat, app_cfg) {
+        debug!("Skipping callback since it is not coming from whitelisted chat");
+        return Ok(());
+    }
+
+    if is_paused_chat(&message.chat, app_cfg) {
+        debug!("Skipping callback since the chat is paused");
+        return Ok(());
+    }
+
+    let Some(token_ca) = query
+        .data
+        .as_deref()
+        .and_then(|data| data.strip_prefix(EXPAND_CALLBACK_PREFIX))
+    else {
+        warn!("Unknown callback data {:?}", query.data);
+        return Ok(());
+    };
+
+    // expanding a trending entry goes through the normal lookup
+    // with the trending list being the message we reply to
+    let ctx = MessageContext::new(bot, message, client, cache, storage);
+    let cards = process_token_ca(&ctx, token_ca).await;
+    send_cards(&ctx, cards).await;
+
+    Ok(())
+}
 
-        let mut result = None;
-
-        for chain in [Chain::Bsc, Chain::Base] {
-            match retrieve_evm_token_info(token_ca, chain, client.clone()).await {
-                Ok(data) => {
-                    result = Some(data);
-                    break;
-                },
-                Err(err) => {
-                    warn!("Failed to retrieve token info {token_ca} on {chain:?} - {err:?}");
-                }
+/// Looks up a single token address of any chain
+async fn process_token_ca(ctx: &MessageContext<'_>, token_ca: &str) -> Vec<TokenCard> {
+    let routed = route_addresses(token_ca, ctx.client.clone()).await;
+    process_routed_addresses(ctx, &routed).await
+}
+
+async fn process_routed_addresses(
+    ctx: &MessageContext<'_>,
+    routed: &RoutedAddresses,
+) -> Vec<TokenCard> {
+    let (mut cards, evm_cards, ton_cards, tron_cards, wallet_cards) = tokio::join!(
+        process_solana_cas(ctx, &routed.solana),
+        process_evm_cas(ctx, routed),
+        process_ton_cas(ctx, &routed.ton),
+        process_tron_cas(ctx, &routed.tron),
+        process_wallets(ctx, routed),
+    );
+    cards.extend(evm_cards);
+    cards.extend(ton_cards);
+    cards.extend(tron_cards);
+    cards.extend(wallet_cards);
+
+    cards
+}
+
+/// Wallets are ignored unless `wallet_cards` is enabled
+async fn process_wallets(ctx: &MessageContext<'_>, routed: &RoutedAddresses) -> Vec<TokenCard> {
+    if !ctx.app_config.wallet_cards {
+        return Vec::new();
+    }
+
+    let lookups: Vec<_> = routed
+        .solana_wallets
+        .iter()
+        .map(|address| process_wallet(ctx, address, "solana"))
+        .chain(
+            routed
+                .evm_wallets
+                .iter()
+                .map(|address| process_wallet(ctx, address, "bsc")),
+        )
+        .collect();
+
+    stream::iter(lookups)
+        .buffered(MAX_CONCURRENT_LOOKUPS)
+        .filter_map(future::ready)
+        .collect()
+        .await
+}
+
+/// Card with the native balances and the number of held tokens of the wallet,
+/// `chain_id` is `solana` for Solana wallets and `bsc` for EVM ones
+async fn process_wallet(
+    ctx: &MessageContext<'_>,
+    address: &str,
+    chain_id: &'static str,
+) -> Option<TokenCard> {
+    info!(
+        "[{}] FOUND WALLET in the message {:?} - {address}",
+        ctx.trace_id, ctx.message.id
+    );
+
+    if let Throttle::Skip = should_we_throttle_ca(ctx, address).await {
+        return None;
+    }
+
+    let wallet = match chain_id {
+        "solana" => retrieve_solana_wallet(address, ctx.client.clone()).await,
+        _ => retrieve_evm_wallet(address, ctx.client.clone()).await,
+    }?;
+    let message_text = render_wallet_card(ctx.chat_id(), &wallet);
+
+    debug!("Prepared message {message_text}");
+
+    Some(TokenCard {
+        chain_id,
+        token_ca: address.to_owned(),
+        mcap: None,
+        text: message_text,
+        full_text: None,
+        buttons: Vec::new(),
+    })
+}
+
+async fn process_evm_cas(ctx: &MessageContext<'_>, routed: &RoutedAddresses) -> Vec<TokenCard> {
+    let lookups: Vec<_> = routed
+        .evm
+        .iter()
+        .filter(|token_ca| !is_blacklisted(ctx.chat_id(), token_ca))
+        .map(|token_ca| {
+            let known_chain = routed.evm_chains.get(&token_ca.to_lowercase()).copied();
+            process_evm_ca(ctx, token_ca, known_chain)
+        })
+        .collect();
+
+    stream::iter(lookups)
+        .buffered(MAX_CONCURRENT_LOOKUPS)
+        .filter_map(future::ready)
+        .collect()
+        .await
+}
+
+/// `known_chain` skips probing the chains when a link in the message tells the chain
+async fn process_evm_ca(
+    ctx: &MessageContext<'_>,
+    token_ca: &str,
+    known_chain: Option<Chain>,
+) -> Option<TokenCard> {
+    info!(
+        "[{}] FOUND EVM TOKEN CA in the message {:?} - {token_ca}",
+        ctx.trace_id, ctx.message.id
+    );
+
+    let throttle = should_we_throttle_ca(ctx, token_ca).await;
+    if let Throttle::Skip = throttle {
+        return None;
+    }
+
+    let known_chain = known_chain.as_slice();
+    let chains = if known_chain.is_empty() {
+        ctx.app_config.evm_chains(ctx.chat_id())
+    } else {
+        known_chain
+    };
+    let token_info = match probe_evm_chains(token_ca, chains, ctx.client.clone()).await {
+        Ok(token_info) => token_info,
+        Err(errors) => {
+            for (chain, err) in &errors {
+                report_lookup_error(&format!("Moralis request for {token_ca} on {chain:?}"), err);
+            }
+
+            // an address which isn't a token on any chain is most likely a wallet
+            let not_found = errors
+                .iter()
+                .all(|(_, err)| matches!(err, TokenInfoError::NotFound));
+            if not_found && ctx.app_config.wallet_cards {
+                return process_wallet(ctx, token_ca, "bsc").await;
             }
+            return None;
         }
+    };
 
-        let Some(token_info) = result else {
-            continue;
-        };
+    if !throttle.allows(ctx, token_ca, Some(token_info.mcap)) {
+        return None;
+    }
 
-        let message_text = format!(
-            "🏷️ *{}* \\- {}\n\
-            📜 `{}`\n\
-            💵 {} \\- {}\n\
-            🦎 [GMGN]({})    🅳 [DF]({})    🔄 [DT]({})\n\
-            🥞 [P\\. USDT]({})     🥞 [P\\. USDC]({})\n\
-            🦄 [U\\. USDT]({})    🦄 [U\\. USDC]({})",
-            escape(&token_info.symbol),
-            escape(&token_info.name),
-            token_info.id,
-            escape(&token_info.human_readable_mcap()),
-            escape(token_info.chain_name()),
-            escape(&token_info.gmgn_url()),
-            escape(&token_info.defined_url()),
-            escape(&token_info.dextools_url()),
-            escape(&token_info.pancake_add_to_usdt_pool()),
-            escape(&token_info.pancake_add_to_usdc_pool()),
-            escape(&token_info.uniswap_add_to_usdt_pool()),
-            escape(&token_info.uniswap_add_to_usdc_pool()),
-        );
+    if is_out_of_mcap_range(ctx, token_ca, Some(token_info.mcap)).await {
+        return None;
+    }
+
+    let (security, solana_origin, next_unlock) = tokio::join!(
+        guarded(
+            Provider::GoPlus,
+            retrieve_evm_token_security(token_ca, token_info.chain, ctx.client.clone()),
+        ),
+        guarded(
+            Provider::CoinGecko,
+            retrieve_solana_origin(token_ca, token_info.chain, ctx.client.clone()),
+        ),
+        guarded(
+            Provider::DefiLlama,
+            retrieve_next_unlock(token_ca, ctx.client.clone()),
+        ),
+    );
+
+    let security = security
+        .inspect_err(|err| warn!("Failed to retrieve token security {token_ca} - {err:?}"))
+        .ok();
+
+    let solana_origin = solana_origin.unwrap_or_else(|err| {
+        warn!("Failed to retrieve bridge origin of {token_ca} - {err:?}");
+        None
+    });
 
-        debug!("Prepared message {message_text}");
+    let next_unlock = next_unlock.unwrap_or_else(|err| {
+        warn!("Failed to retrieve unlock schedule - {err:?}");
+        None
+    });
 
-        send_reply(bot, message, cache, token_ca, message_text).await;
+    let symbol_collision = record_symbol(
+        ctx.chat_id(),
+        &token_info.symbol,
+        token_info.chain_name(),
+        token_ca,
+    )
+    .await;
+
+    let first_mention = record_mention(
+        ctx,
+        token_info.dexscreener_chain_id(),
+        token_ca,
+        &token_info.symbol,
+        Some(token_info.mcap),
+    )
+    .await;
+    record_tags(
+        &ctx.storage,
+        token_info.dexscreener_chain_id(),
+        token_ca,
+        &token_info.tags,
+    )
+    .await;
+
+    let risk = risk_score(
+        &RiskSignals::evm(&token_info, security.as_ref()),
+        &risk_weights_for_chat(ctx.chat_id()),
+    );
+
+    let context = CardContext {
+        risk: risk.map(|risk| risk.describe()),
+        security: security.map(|security| security.risk_line()),
+        unlock: next_unlock.map(|next_unlock| next_unlock.describe()),
+        collision: describe_collision(&symbol_collision, &token_info.symbol),
+        first_call: first_mention.map(|first| first.describe(Some(token_info.mcap))),
+        market_note: market_note(token_info.dexscreener_chain_id()),
+        bridged_from: solana_origin.clone(),
+        ..CardContext::evm(&token_info, icon_set_for_chat(ctx.chat_id()))
+    };
+    let message_text = render_card(ctx.chat_id(), CardKind::Evm, &context);
+    let compact_text = render_compact_card(ctx.chat_id(), CardKind::Evm, &context);
+
+    debug!("Prepared message {message_text}");
+
+    let buttons = solana_origin
+        .map(|origin| {
+            InlineKeyboardButton::callback(
+                format!("🌉 Original {} on Solana", token_info.symbol),
+                format!("{EXPAND_CALLBACK_PREFIX}{origin}"),
+            )
+        })
+        .into_iter()
+        .chain(open_in_dm_button(ctx.message, &token_info.symbol, token_ca))
+        .collect();
+
+    Some(TokenCard {
+        chain_id: token_info.dexscreener_chain_id(),
+        token_ca: token_ca.to_owned(),
+        mcap: Some(token_info.mcap),
+        text: compact_text.clone().unwrap_or_else(|| message_text.clone()),
+        full_text: compact_text.map(|_| message_text),
+        buttons,
+    })
+}
+
+async fn process_solana_cas(ctx: &MessageContext<'_>, token_cas: &[String]) -> Vec<TokenCard> {
+    let lookups: Vec<_> = token_cas
+        .iter()
+        .filter(|token_ca| !is_blacklisted(ctx.chat_id(), token_ca))
+        .map(|token_ca| process_solana_ca(ctx, token_ca))
+        .collect();
+
+    stream::iter(lookups)
+        .buffered(MAX_CONCURRENT_LOOKUPS)
+        .filter_map(future::ready)
+        .collect()
+        .await
+}
+
+async fn process_solana_ca(ctx: &MessageContext<'_>, token_ca: &str) -> Option<TokenCard> {
+    info!(
+        "[{}] FOUND SOLANA TOKEN CA in the message {:?} - {token_ca}",
+        ctx.trace_id, ctx.message.id
+    );
+
+    let throttle = should_we_throttle_ca(ctx, token_ca).await;
+    if let Throttle::Skip = throttle {
+        return None;
+    }
+
+    let data = match retrieve_solana_token_info(token_ca, ctx.client.clone()).await {
+        Ok(data) => data,
+        Err(TokenInfoError::NotFound) if ctx.app_config.wallet_cards => {
+            debug!("{token_ca} is not a token on Jupiter - checking it as a wallet");
+            return process_wallet(ctx, token_ca, "solana").await;
+        }
+        Err(err) => {
+            report_lookup_error(&format!("Jupiter request for {token_ca}"), &err);
+            return None;
+        }
+    };
+
+    if !throttle.allows(ctx, token_ca, data.mcap) {
+        return None;
+    }
+
+    if is_out_of_mcap_range(ctx, token_ca, data.mcap).await {
+        return None;
     }
+
+    let symbol_collision = record_symbol(ctx.chat_id(), &data.symbol, "SOL", token_ca).await;
+
+    let next_unlock = guarded(
+        Provider::DefiLlama,
+        retrieve_next_unlock(token_ca, ctx.client.clone()),
+    )
+    .await
+    .unwrap_or_else(|err| {
+        warn!("Failed to retrieve unlock schedule - {err:?}");
+        None
+    });
+
+    let first_mention = record_mention(ctx, "solana", token_ca, &data.symbol, data.mcap).await;
+    record_tags(&ctx.storage, "solana", token_ca, &data.tags).await;
+
+    let risk = risk_score(
+        &RiskSignals::solana(&data),
+        &risk_weights_for_chat(ctx.chat_id()),
+    );
+
+    let context = CardContext {
+        risk: risk.map(|risk| risk.describe()),
+        unlock: next_unlock.map(|next_unlock| next_unlock.describe()),
+        collision: describe_collision(&symbol_collision, &data.symbol),
+        first_call: first_mention.map(|first| first.describe(data.mcap)),
+        market_note: market_note("solana"),
+        ..CardContext::solana(&data, icon_set_for_chat(ctx.chat_id()))
+    };
+    let message_text = render_card(ctx.chat_id(), CardKind::Solana, &context);
+    let compact_text = render_compact_card(ctx.chat_id(), CardKind::Solana, &context);
+
+    debug!("Prepared message {message_text}");
+
+    Some(TokenCard {
+        chain_id: "solana",
+        token_ca: token_ca.to_owned(),
+        mcap: data.mcap,
+        text: compact_text.clone().unwrap_or_else(|| message_text.clone()),
+        full_text: compact_text.map(|_| message_text),
+        buttons: open_in_dm_button(ctx.message, &data.symbol, token_ca)
+            .into_iter()
+            .collect(),
+    })
+}
+
+async fn process_ton_cas(ctx: &MessageContext<'_>, token_cas: &[String]) -> Vec<TokenCard> {
+    let lookups: Vec<_> = token_cas
+        .iter()
+        .filter(|token_ca| !is_blacklisted(ctx.chat_id(), token_ca))
+        .map(|token_ca| process_ton_ca(ctx, token_ca))
+        .collect();
+
+    stream::iter(lookups)
+        .buffered(MAX_CONCURRENT_LOOKUPS)
+        .filter_map(future::ready)
+        .collect()
+        .await
 }
 
-async fn process_solana_cas(
-    bot: &Bot,
+async fn process_ton_ca(ctx: &MessageContext<'_>, token_ca: &str) -> Option<TokenCard> {
+    info!(
+        "[{}] FOUND TON TOKEN CA in the message {:?} - {token_ca}",
+        ctx.trace_id, ctx.message.id
+    );
+
+    let throttle = should_we_throttle_ca(ctx, token_ca).await;
+    if let Throttle::Skip = throttle {
+        return None;
+    }
+
+    let data = match retrieve_ton_token_info(token_ca, ctx.client.clone()).await {
+        Ok(data) => data,
+        Err(err) => {
+            report_lookup_error(&format!("TonAPI request for {token_ca}"), &err);
+            return None;
+        }
+    };
+
+    if !throttle.allows(ctx, token_ca, data.mcap) {
+        return None;
+    }
+
+    if is_out_of_mcap_range(ctx, token_ca, data.mcap).await {
+        return None;
+    }
+
+    let symbol_collision = record_symbol(ctx.chat_id(), &data.symbol, "TON", token_ca).await;
+
+    let first_mention = record_mention(ctx, "ton", token_ca, &data.symbol, data.mcap).await;
+
+    let context = CardContext {
+        collision: describe_collision(&symbol_collision, &data.symbol),
+        first_call: first_mention.map(|first| first.describe(data.mcap)),
+        market_note: market_note("ton"),
+        ..CardContext::ton(&data, icon_set_for_chat(ctx.chat_id()))
+    };
+    let message_text = render_card(ctx.chat_id(), CardKind::Ton, &context);
+    let compact_text = render_compact_card(ctx.chat_id(), CardKind::Ton, &context);
+
+    debug!("Prepared message {message_text}");
+
+    Some(TokenCard {
+        chain_id: "ton",
+        token_ca: token_ca.to_owned(),
+        mcap: data.mcap,
+        text: compact_text.clone().unwrap_or_else(|| message_text.clone()),
+        full_text: compact_text.map(|_| message_text),
+        buttons: open_in_dm_button(ctx.message, &data.symbol, token_ca)
+            .into_iter()
+            .collect(),
+    })
+}
+
+async fn process_tron_cas(ctx: &MessageContext<'_>, token_cas: &[String]) -> Vec<TokenCard> {
+    let lookups: Vec<_> = token_cas
+        .iter()
+        .filter(|token_ca| !is_blacklisted(ctx.chat_id(), token_ca))
+        .map(|token_ca| process_tron_ca(ctx, token_ca))
+        .collect();
+
+    stream::iter(lookups)
+        .buffered(MAX_CONCURRENT_LOOKUPS)
+        .filter_map(future::ready)
+        .collect()
+        .await
+}
+
+async fn process_tron_ca(ctx: &MessageContext<'_>, token_ca: &str) -> Option<TokenCard> {
+    info!(
+        "[{}] FOUND TRON TOKEN CA in the message {:?} - {token_ca}",
+        ctx.trace_id, ctx.message.id
+    );
+
+    let throttle = should_we_throttle_ca(ctx, token_ca).await;
+    if let Throttle::Skip = throttle {
+        return None;
+    }
+
+    let data = match retrieve_tron_token_info(token_ca, ctx.client.clone()).await {
+        Ok(data) => data,
+        Err(err) => {
+            report_lookup_error(&format!("TronScan request for {token_ca}"), &err);
+            return None;
+        }
+    };
+
+    if !throttle.allows(ctx, token_ca, data.mcap) {
+        return None;
+    }
+
+    if is_out_of_mcap_range(ctx, token_ca, data.mcap).await {
+        return None;
+    }
+
+    let symbol_collision = record_symbol(ctx.chat_id(), &data.symbol, "TRON", token_ca).await;
+
+    let first_mention = record_mention(ctx, "tron", token_ca, &data.symbol, data.mcap).await;
+
+    let context = CardContext {
+        collision: describe_collision(&symbol_collision, &data.symbol),
+        first_call: first_mention.map(|first| first.describe(data.mcap)),
+        market_note: market_note("tron"),
+        ..CardContext::tron(&data, icon_set_for_chat(ctx.chat_id()))
+    };
+    let message_text = render_card(ctx.chat_id(), CardKind::Tron, &context);
+    let compact_text = render_compact_card(ctx.chat_id(), CardKind::Tron, &context);
+
+    debug!("Prepared message {message_text}");
+
+    Some(TokenCard {
+        chain_id: "tron",
+        token_ca: token_ca.to_owned(),
+        mcap: data.mcap,
+        text: compact_text.clone().unwrap_or_else(|| message_text.clone()),
+        full_text: compact_text.map(|_| message_text),
+        buttons: open_in_dm_button(ctx.message, &data.symbol, token_ca)
+            .into_iter()
+            .collect(),
+    })
+}
+
+/// Tokens out of the chat's mcap range are neither recorded nor carded, they are
+/// throttled though so repeated mentions don't hit the providers again
+async fn is_out_of_mcap_range(
+    ctx: &MessageContext<'_>,
+    token_ca: &str,
+    mcap: Option<Decimal>,
+) -> bool {
+    if chat_settings(ctx.chat_id()).allows_mcap(mcap, ctx.chat_config()) {
+        return false;
+    }
+
+    debug!(
+        "[{}] Mcap {mcap:?} of {token_ca} is out of the range of {} - skipping it",
+        ctx.trace_id,
+        ctx.chat_id()
+    );
+    ctx.out_of_mcap_range.fetch_add(1, Ordering::Relaxed);
+    throttle_sent_tokens(ctx, &[token_ca]).await;
+    true
+}
+
+/// Button delivering the card privately, not shown in private chats
+fn open_in_dm_button(
     message: &Message,
-    client: reqwest::Client,
-    cache: &Cache,
-    msg_text: &str,
-) {
-    for (_, [token_ca]) in SOLANA_TOKEN_CA_REGEX
-        .get()
-        .unwrap()
-        .captures_iter(msg_text)
-        .map(|c| c.extract())
-    {
-        info!(
-            "FOUND SOLANA TOKEN CA in the message {:?} - {token_ca}",
-            message.id
-        );
+    symbol: &str,
+    token_ca: &str,
+) -> Option<InlineKeyboardButton> {
+    if message.chat.is_private() {
+        return None;
+    }
 
-        if should_we_throttle_ca(message, cache, token_ca).await {
+    let url = open_in_dm_url(token_ca)?.parse().ok()?;
+    Some(InlineKeyboardButton::url(
+        format!("📩 Open {symbol} in DM"),
+        url,
+    ))
+}
+
+/// Unknown tokens are skipped quietly, the token gets looked up again the next time
+/// it's mentioned. Provider outages are reported to the admins by the provider guard.
+fn report_lookup_error(request: &str, err: &TokenInfoError) {
+    match err {
+        TokenInfoError::NotFound => debug!("{request} found nothing"),
+        TokenInfoError::ProviderUnavailable(_) => warn!("{request} failed - {err}"),
+        _ => warn!("{request} failed - {err:?}"),
+    }
+}
+
+fn describe_collision(collision: &Option<SymbolCollision>, symbol: &str) -> Option<String> {
+    collision
+        .as_ref()
+        .map(|collision| collision.describe(&sanitize_text(symbol)))
+}
+
+/// Sends the cards aggregated into as few replies as allowed by the config
+async fn send_cards(ctx: &MessageContext<'_>, cards: Vec<TokenCard>) {
+    let max_tokens = ctx.app_config.max_tokens_per_reply.max(1);
+
+    let settings = chat_settings(ctx.chat_id());
+
+    for chunk in cards.chunks(max_tokens) {
+        let token_cas: Vec<&str> = chunk.iter().map(|card| card.token_ca.as_str()).collect();
+        let message_text = chunk
+            .iter()
+            .map(|card| card.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let full_text = chunk.iter().any(|card| card.full_text.is_some()).then(|| {
+            chunk
+                .iter()
+                .map(|card| card.full_text.as_deref().unwrap_or(&card.text))
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        });
+
+        let buttons: Vec<InlineKeyboardButton> = full_text
+            .as_ref()
+            .map(|_| more_button())
+            .into_iter()
+            .chain(chunk.iter().flat_map(|card| card.buttons.iter().cloned()))
+            .chain((!ctx.message.chat.is_private()).then(share_button))
+            .chain((!ctx.message.chat.is_private()).then(delete_button))
+            .collect();
+
+        let tokens = chunk
+            .iter()
+            .map(|card| CardToken {
+                chain_id: card.chain_id,
+                token_ca: card.token_ca.clone(),
+            })
+            .collect();
+        let reply = CardReply {
+            text: message_text,
+            buttons,
+            tokens,
+            full_text,
+        };
+
+        let Some(sent) = send_reply(ctx, &token_cas, &reply).await else {
             continue;
+        };
+
+        remember_card(sent.chat.id, sent.id, reply.tokens, reply.full_text).await;
+        if let Some(ttl) = settings.auto_delete_after() {
+            schedule_auto_delete(ctx.bot.clone(), sent.chat.id, sent.id, ttl);
         }
 
-        let data = match retrieve_solana_token_info(token_ca, client.clone()).await {
-            Ok(data) => data,
-            Err(err) => {
-                warn!("Failed to retrieve token info {token_ca} - {err:?}");
-                continue;
+        for card in chunk {
+            if let Some(mcap) = card.mcap {
+                let key = (card.token_ca.clone(), ctx.chat_id(), ctx.thread_id());
+                caches().posted_mcaps.insert(key, mcap).await;
             }
-        };
+        }
+    }
+}
 
-        let message_text = format!(
-            "🏷️ *{}* \\- {}\n\
-            📜 `{}`\n\
-            💵 {} \\- SOL\n\
-            🦎 [GMGN]({})            ☄️ [Meteora pools]({})\n\
-            🦝 [Rugcheck]({})        📡 [TrenchRadar]({})\n\
-            🪐 [JUP]({})",
-            escape(&data.symbol),
-            escape(&data.name),
-            data.id,
-            escape(&data.human_readable_mcap()),
-            escape(&data.gmgn_url()),
-            escape(&data.meteora_pools()),
-            escape(&data.rugcheck_url()),
-            escape(&data.trenchradar_url()),
-            escape(&data.jup_url()),
-        );
+/// Result of the throttle check done before the token lookup
+enum Throttle {
+    Send,
+    Skip,
+    /// The token was carded recently with this mcap, it is sent again only if the mcap
+    /// changed drastically since then
+    UnlessMcapChanged(Decimal),
+}
 
-        debug!("Prepared message {message_text}");
+impl Throttle {
+    fn allows(&self, ctx: &MessageContext<'_>, token_ca: &str, mcap: Option<Decimal>) -> bool {
+        let Throttle::UnlessMcapChanged(posted_mcap) = *self else {
+            return matches!(self, Throttle::Send);
+        };
+
+        let ratio = ctx.app_config.cooldown_bypass_mcap_ratio;
+        let Some(ratio) = Decimal::from_f64(ratio).filter(|ratio| *ratio > Decimal::ONE) else {
+            return false;
+        };
 
-        send_reply(bot, message, cache, token_ca, message_text).await;
+        match mcap {
+            Some(mcap) if mcap >= posted_mcap * ratio || mcap * ratio <= posted_mcap => {
+                info!(
+                    "Mcap of {token_ca} changed from {posted_mcap} to {mcap} - bypassing the cooldown in {}",
+                    ctx.chat_id()
+                );
+                true
+            }
+            _ => {
+                info!(
+                    "We've sent info on this token {token_ca} not so long time ago so skipping this request for now"
+                );
+                false
+            }
+        }
     }
 }
 
-async fn should_we_throttle_ca(message: &Message, cache: &Cache, token_ca: &str) -> bool {
+async fn should_we_throttle_ca(ctx: &MessageContext<'_>, token_ca: &str) -> Throttle {
+    if ctx.bypass_throttle {
+        return Throttle::Send;
+    }
+
     let value = {
-        let cache_guard = cache.read().await;
+        let cache_guard = ctx.throttle.read().await;
 
-        let key = (Cow::Borrowed(token_ca), message.chat.id, message.thread_id);
+        let key = (Cow::Borrowed(token_ca), ctx.chat_id(), ctx.thread_id());
         cache_guard.get(&key).cloned()
     };
 
-    if let Some(latest_mention) = value {
-        let now = Utc::now();
-        if (now - latest_mention) < ALLOWED_THROTTLING {
+    let Some(latest_mention) = value else {
+        return Throttle::Send;
+    };
+
+    if (Utc::now() - latest_mention) >= chat_settings(ctx.chat_id()).throttle_window() {
+        return Throttle::Send;
+    }
+
+    let posted_key = (token_ca.to_owned(), ctx.chat_id(), ctx.thread_id());
+    match caches().posted_mcaps.get(&posted_key).await {
+        Some(posted_mcap) if posted_mcap > Decimal::ZERO => {
+            Throttle::UnlessMcapChanged(posted_mcap)
+        }
+        _ => {
             info!(
                 "We've sent info on this token {token_ca} not so long time ago so skipping this request for now"
             );
-            return true;
+            Throttle::Skip
         }
     }
-
-    false
 }
 
 async fn send_reply(
-    bot: &Bot,
-    message: &Message,
-    cache: &Cache,
-    token_ca: &str,
-    message_text: String,
-) {
-    let reply_result = bot
-        .send_message(message.chat.id, message_text)
-        .parse_mode(ParseMode::MarkdownV2)
-        .disable_link_preview(true)
-        .disable_notification(true)
-        .reply_to(message.id)
-        .await;
+    ctx: &MessageContext<'_>,
+    token_cas: &[&str],
+    reply: &CardReply,
+) -> Option<Message> {
+    if let Some(format) = ctx.app_config.dry_run {
+        info!(
+            "[{}] Dry run - not sending token info {token_cas:?}:\n{}",
+            ctx.trace_id,
+            format.convert(&reply.text)
+        );
+        throttle_sent_tokens(ctx, token_cas).await;
+        return None;
+    }
+
+    if chat_settings(ctx.chat_id()).observe_only {
+        debug!(
+            "[{}] Observing only - not sending token info {token_cas:?}",
+            ctx.trace_id
+        );
+        throttle_sent_tokens(ctx, token_cas).await;
+        return None;
+    }
+
+    let (text, parse_mode) = telegram_text(&reply.text);
+    let mut reply_result = send_reply_message(ctx, text, Some(parse_mode), &reply.buttons).await;
+
+    // exotic token names may still trip up the parser, a plain card is better than none
+    if let Err(RequestError::Api(ApiError::CantParseEntities(err))) = &reply_result {
+        warn!(
+            "[{}] Telegram failed to parse token info {token_cas:?} - {err} - resending it as plain text",
+            ctx.trace_id
+        );
+        let text = OutputFormat::PlainText.convert(&reply.text);
+        reply_result = send_reply_message(ctx, text, None, &reply.buttons).await;
+    }
 
     match reply_result {
         Ok(msg) => {
-            debug!("Sent reply with token info {token_ca} as {}", msg.id);
-            {
-                let mut cache_guard = cache.write().await;
-
-                let now = Utc::now();
-                cache_guard.insert(
-                    (
-                        Cow::Owned(token_ca.to_owned()),
-                        message.chat.id,
-                        message.thread_id,
-                    ),
-                    now,
-                );
-                debug!("Inserted info about sent token {token_ca} into throttle data");
+            debug!(
+                "[{}] Sent reply with token info {token_cas:?} as {}",
+                ctx.trace_id, msg.id
+            );
+            throttle_sent_tokens(ctx, token_cas).await;
+            Some(msg)
+        }
+        Err(e) => {
+            warn!(
+                "[{}] Failed to send token info {token_cas:?} - {e:?}",
+                ctx.trace_id
+            );
+            report_error(
+                ErrorClass::SendReply,
+                format!("Failed to send token info {token_cas:?} - {e}"),
+            );
+
+            if is_retryable(&e) {
+                enqueue_reply(
+                    &ctx.storage,
+                    ctx.chat_id(),
+                    ctx.thread_id(),
+                    ctx.message.id,
+                    reply.clone(),
+                    &e,
+                )
+                .await;
+                // the queued reply is going to be delivered, the tokens mustn't be carded again meanwhile
+                throttle_sent_tokens(ctx, token_cas).await;
             }
+            None
+        }
+    }
+}
+
+async fn send_reply_message(
+    ctx: &MessageContext<'_>,
+    text: String,
+    parse_mode: Option<ParseMode>,
+    buttons: &[InlineKeyboardButton],
+) -> Result<Message, RequestError> {
+    let mut request = ctx
+        .bot
+        .send_message(ctx.chat_id(), text)
+        .disable_link_preview(true)
+        .disable_notification(chat_settings(ctx.chat_id()).silent)
+        .reply_to(ctx.message.id);
+
+    if let Some(parse_mode) = parse_mode {
+        request = request.parse_mode(parse_mode);
+    }
+
+    if !buttons.is_empty() {
+        let rows = buttons.iter().cloned().map(|button| vec![button]);
+        request = request.reply_markup(InlineKeyboardMarkup::new(rows));
+    }
+
+    wait_for_send_slot(ctx.chat_id()).await;
+    let result = request.await;
+    if let Err(e) = &result {
+        record_send_error(ctx.chat_id(), e).await;
+    }
+    result
+}
+
+async fn throttle_sent_tokens(ctx: &MessageContext<'_>, token_cas: &[&str]) {
+    let mut cache_guard = ctx.throttle.write().await;
+
+    let now = Utc::now();
+    for token_ca in token_cas {
+        cache_guard.insert(
+            (
+                Cow::Owned((*token_ca).to_owned()),
+                ctx.chat_id(),
+                ctx.thread_id(),
+            ),
+            now,
+        );
+    }
+    debug!("Inserted info about sent tokens {token_cas:?} into throttle data");
+}
+
+/// Saves what a restart needs to avoid a burst of lookups and repeated cards
+async fn save_warm_start_data(storage: &SqlitePool, throttle_info: &Cache) {
+    let throttling_info = throttle_info.read().await.clone();
+    if let Err(e) = save_throttling_info(storage, &throttling_info).await {
+        warn!("Failed to save throttle data to storage - {e:?}");
+    }
+    save_token_info_cache(storage).await;
+}
+
+/// Saves the warm start data periodically too, a crash loses at most one interval of it
+fn spawn_warm_start_saver(storage: SqlitePool, throttle_info: Cache) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(WARM_START_SAVE_INTERVAL);
+        // the first tick completes immediately
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            save_warm_start_data(&storage, &throttle_info).await;
         }
+    });
+}
+
+/// Stops the dispatcher on Ctrl-C or SIGTERM (systemd, docker), so the warm start data
+/// gets saved before the exit
+fn spawn_shutdown_handler(shutdown: ShutdownToken) {
+    tokio::spawn(async move {
+        wait_for_shutdown_signal().await;
+        info!("Got shutdown signal - stopping the dispatcher");
+        match shutdown.shutdown() {
+            Ok(stopped) => stopped.await,
+            Err(e) => warn!("Failed to stop the dispatcher - {e:?}"),
+        }
+    });
+}
+
+#[cfg(unix)]
+async fn wait_for_shutdown_signal() {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(terminate) => terminate,
         Err(e) => {
-            warn!("Failed to send token info {token_ca} - {e:?}");
+            warn!("Failed to install SIGTERM handler - {e:?}");
+            let _ = tokio::signal::ctrl_c().await;
+            return;
         }
+    };
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_shutdown_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Reads the secret from the file named by `<name>_FILE` (docker/k8s secrets),
+/// or else from the `<name>` env var
+fn read_secret(name: &str) -> Option<String> {
+    let Ok(path) = std::env::var(format!("{name}_FILE")) else {
+        return std::env::var(name).ok();
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(secret) => Some(secret.trim().to_owned()),
+        Err(e) => exit_with(
+            EXIT_CONFIG_ERROR,
+            &format!("Failed to read {name} from {path} - {e:?}"),
+        ),
     }
 }
 
@@ -279,47 +1296,210 @@ async fn main() {
         .start()
         .unwrap();
 
-    let Ok(bot_token) = std::env::var("BOT_TOKEN") else {
-        panic!("Bot token not found nor in the env variables or in the .env file");
+    let Some(bot_token) = read_secret("BOT_TOKEN") else {
+        exit_with(
+            EXIT_CONFIG_ERROR,
+            "Bot token not found nor in the env variables (BOT_TOKEN or BOT_TOKEN_FILE) or in the .env file",
+        );
     };
 
-    let Ok(moralis_token) = std::env::var("MORALIS_TOKEN") else {
-        panic!("Moralis token not found nor in the env variables or in the .env file");
+    let moralis_tokens: Vec<String> = read_secret("MORALIS_TOKEN")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(str::to_owned)
+        .collect();
+
+    let Some(jup_token) = read_secret("JUP_TOKEN") else {
+        exit_with(
+            EXIT_CONFIG_ERROR,
+            "JUP token not found nor in the env variables (JUP_TOKEN or JUP_TOKEN_FILE) or in the .env file",
+        );
     };
 
-    let Ok(jup_token) = std::env::var("JUP_TOKEN") else {
-        panic!("JUP token not found nor in the env variables or in the .env file");
+    let birdeye_token = read_secret("BIRDEYE_TOKEN");
+
+    // deep links stay valid across restarts only with a stable secret
+    let deep_link_secret = read_secret("DEEP_LINK_SECRET").unwrap_or_else(|| bot_token.clone());
+
+    let config_path = default_config_path();
+    // lenient mode starts with the defaults rather than refusing a broken config
+    let lenient_config = std::env::var("LENIENT_CONFIG")
+        .is_ok_and(|value| value == "1" || value.eq_ignore_ascii_case("true"));
+    let app_config = match load_config(&config_path) {
+        Ok(app_config) => app_config,
+        Err(e) if lenient_config => {
+            warn!("{e:#} - using the default config since LENIENT_CONFIG is set");
+            Config::default()
+        }
+        Err(e) => exit_with(EXIT_CONFIG_ERROR, &format!("{e:#}")),
     };
 
-    let app_config = load_config_or_default("./config.json");
+    if moralis_tokens.is_empty() && app_config.moralis_keys.is_empty() {
+        exit_with(
+            EXIT_CONFIG_ERROR,
+            "Moralis token not found nor in the env variables (MORALIS_TOKEN or MORALIS_TOKEN_FILE), in the .env file or in moralis_keys of the config",
+        );
+    }
+
+    let storage = match open_storage(&app_config.database_path).await {
+        Ok(storage) => storage,
+        Err(e) => exit_with(
+            EXIT_RUNTIME_FAILURE,
+            &format!(
+                "Failed to open storage {} - {e:?}",
+                app_config.database_path
+            ),
+        ),
+    };
 
     let bot = Bot::new(bot_token);
-    let Ok(bot_ino) = bot.get_me().await else {
-        panic!("Failed to perform getMe on bot");
+    let bot_ino = match bot.get_me().await {
+        Ok(bot_ino) => bot_ino,
+        Err(RequestError::Api(ApiError::InvalidToken)) => {
+            exit_with(EXIT_CONFIG_ERROR, "Bot token is invalid")
+        }
+        Err(e) => exit_with(
+            EXIT_RUNTIME_FAILURE,
+            &format!("Failed to perform getMe on bot - {e:?}"),
+        ),
     };
+    record_get_me(true);
+    spawn_get_me_checker(bot.clone());
+
+    if let Some(addr) = &app_config.health_listen_addr
+        && let Err(e) = spawn_health_server(addr).await
+    {
+        exit_with(
+            EXIT_RUNTIME_FAILURE,
+            &format!("Failed to start health check server on {addr} - {e:?}"),
+        );
+    }
 
-    let reqwest_client = reqwest::Client::new();
+    let reqwest_client = match app_config.http.build_client() {
+        Ok(client) => client,
+        Err(e) => exit_with(
+            EXIT_CONFIG_ERROR,
+            &format!("Failed to build HTTP client - {e:?}"),
+        ),
+    };
+    init_notifier(bot.clone(), reqwest_client.clone());
+    init_error_reports(bot.clone());
     init_solana_token_ca_regex();
     init_evm_token_ca_regex();
+    init_ton_token_ca_regex();
+    init_tron_token_ca_regex();
+    init_link_regexes();
+    init_address_router();
+    init_cashtag_regex();
+    init_caches(
+        app_config.cache_memory_limit_mb,
+        std::time::Duration::from_secs(app_config.token_info_cache_ttl_secs),
+        std::time::Duration::from_secs(app_config.duplicate_texts.window_mins * 60),
+    );
+    restore_token_info_cache(
+        &storage,
+        std::time::Duration::from_secs(app_config.token_info_cache_ttl_secs),
+    )
+    .await;
+    spawn_cache_stats_reporter();
 
     let config = RuntimeConfig {
-        moralis_token,
+        moralis_tokens,
         jup_token,
-        app_config,
+        birdeye_token,
+        deep_link_secret,
+        app_config: std::sync::RwLock::new(Arc::new(app_config)),
+        config_path,
         bot_info: bot_ino.user,
     };
     APP_CONFIG.set(config).unwrap();
+    #[cfg(unix)]
+    spawn_sighup_reloader(APP_CONFIG.get().unwrap());
+
+    // warm start so tokens carded right before the restart aren't looked up again
+    init_chat_settings(&storage).await;
+    init_private_chat_users(&storage).await;
+    let throttle_info = load_throttling_info(&storage, Utc::now() - MAX_THROTTLING)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to load throttle data from storage - {e:?}");
+            HashMap::new()
+        });
+    let throttle_info: Arc<RwLock<ThrottlingInfo>> = Arc::new(RwLock::new(throttle_info));
+    let handler_throttle_info = throttle_info.clone();
+    let handler_storage = storage.clone();
+    spawn_warm_start_saver(storage.clone(), throttle_info.clone());
+    spawn_maintenance_task(storage.clone(), throttle_info.clone());
+    spawn_token_list_refresher(storage.clone(), reqwest_client.clone());
+    spawn_mention_refresher(storage.clone(), reqwest_client.clone());
+    spawn_watch_poller(bot.clone(), storage.clone(), reqwest_client.clone());
+    spawn_outbox_worker(bot.clone(), storage.clone());
+    spawn_digest_task(bot.clone(), storage.clone());
+    spawn_native_price_feed(reqwest_client.clone());
+    spawn_quota_flusher(storage.clone());
+    log_usage(&storage).await;
 
-    let throttle_info: Arc<RwLock<ThrottlingInfo>> = Arc::new(RwLock::new(HashMap::new()));
+    let companion = APP_CONFIG.get().unwrap().app_config().companion.clone();
+    #[cfg(feature = "mtproto")]
+    if let Some(companion) = companion
+        && let Err(e) = start_companion(
+            bot.clone(),
+            &companion,
+            reqwest_client.clone(),
+            throttle_info.clone(),
+        )
+        .await
+    {
+        exit_with(
+            EXIT_CONFIG_ERROR,
+            &format!("Failed to start the companion - {e:?}"),
+        );
+    }
+    #[cfg(not(feature = "mtproto"))]
+    if companion.is_some() {
+        warn!("Ignoring the companion config since the bot is built without the mtproto feature");
+    }
 
-    let handler = Update::filter_message()
+    let handler = dptree::entry()
+        .inspect(|_: Update| record_update_received())
         .map(move || reqwest_client.clone())
-        .map(move || throttle_info.clone())
-        .endpoint(message_handler);
-
-    Dispatcher::builder(bot, handler)
-        .enable_ctrlc_handler()
-        .build()
-        .dispatch()
-        .await;
+        .map(move || handler_throttle_info.clone())
+        .map(move || handler_storage.clone())
+        .branch(
+            Update::filter_message()
+                .branch(dptree::entry().filter_command::<Command>().endpoint(command_handler))
+                .branch(dptree::endpoint(message_handler)),
+        )
+        // channels are whitelisted the same way as groups
+        .branch(Update::filter_channel_post().endpoint(message_handler))
+        .branch(Update::filter_callback_query().endpoint(callback_handler))
+        .branch(Update::filter_my_chat_member().endpoint(handle_my_chat_member))
+        .branch(Update::filter_inline_query().endpoint(inline_query_handler));
+
+    set_dispatcher_running(true);
+    spawn_watchdog();
+    notify_ready();
+    let webhook = APP_CONFIG.get().unwrap().app_config().webhook.clone();
+    let mut dispatcher = Dispatcher::builder(bot.clone(), handler).build();
+    spawn_shutdown_handler(dispatcher.shutdown_token());
+    match webhook {
+        Some(webhook) => {
+            let listener = match webhook_listener(bot, &webhook).await {
+                Ok(listener) => listener,
+                Err(e) => exit_with(
+                    EXIT_RUNTIME_FAILURE,
+                    &format!("Failed to set up the webhook {} - {e:?}", webhook.url),
+                ),
+            };
+            let error_handler = LoggingErrorHandler::with_custom_text("Webhook listener failed");
+            dispatcher.dispatch_with_listener(listener, error_handler).await;
+        }
+        None => dispatcher.dispatch().await,
+    }
+    set_dispatcher_running(false);
+    notify_stopping();
+
+    save_warm_start_data(&storage, &throttle_info).await;
 }
@@ -0,0 +1,194 @@
+use chrono::{DateTime, Days, NaiveTime, TimeDelta, Utc};
+use log::{info, warn};
+use sqlx::SqlitePool;
+
+use crate::cache::caches;
+use crate::notify::{Severity, notify};
+use crate::settings::forget_chat_settings;
+use crate::storage::{
+    compact_storage, delete_archived_chat, delete_chat_data, load_archived_chats, prune_mentions,
+    prune_outbox, prune_provider_usage, prune_throttling_info, prune_token_tags, prune_watches,
+    storage_size,
+};
+use crate::{APP_CONFIG, Cache, MAX_THROTTLING};
+
+const BYTES_IN_KB: i64 = 1024;
+
+/// The next time it's `hour` o'clock UTC
+fn next_run_at(now: DateTime<Utc>, hour: u32) -> DateTime<Utc> {
+    let time = NaiveTime::from_hms_opt(hour.min(23), 0, 0).unwrap_or_default();
+    let today = now.date_naive().and_time(time).and_utc();
+
+    if today > now {
+        today
+    } else {
+        today.checked_add_days(Days::new(1)).unwrap_or(today)
+    }
+}
+
+/// Records older than this are deleted
+pub fn retention_cutoff(now: DateTime<Utc>, days: u64) -> DateTime<Utc> {
+    i64::try_from(days)
+        .ok()
+        .and_then(TimeDelta::try_days)
+        .and_then(|retention| now.checked_sub_signed(retention))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
+/// Runs the maintenance every night at the configured hour
+pub fn spawn_maintenance_task(storage: SqlitePool, throttle: Cache) {
+    tokio::spawn(async move {
+        loop {
+            let hour = APP_CONFIG.get().unwrap().app_config().maintenance_hour_utc;
+            let now = Utc::now();
+            let run_at = next_run_at(now, hour);
+            info!("Next maintenance is scheduled at {run_at}");
+
+            let wait = (run_at - now).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+
+            let report = run_maintenance(&storage, &throttle).await;
+            info!("{report}");
+            notify(Severity::Info, report);
+        }
+    });
+}
+
+/// Deletes the data of the chats archived before `cutoff`, forgets the archived chats
+/// which have been whitelisted again. Returns the number of purged chats.
+async fn purge_archived_chats(
+    storage: &SqlitePool,
+    throttle: &Cache,
+    cutoff: DateTime<Utc>,
+) -> usize {
+    let archived = match load_archived_chats(storage).await {
+        Ok(archived) => archived,
+        Err(e) => {
+            warn!("Failed to load archived chats - {e:?}");
+            return 0;
+        }
+    };
+    let whitelisted_chats = APP_CONFIG
+        .get()
+        .unwrap()
+        .app_config()
+        .whitelisted_chats
+        .clone();
+
+    let mut purged = 0;
+    for (chat_id, archived_at) in archived {
+        if whitelisted_chats.contains(&chat_id.0) {
+            info!("Archived chat {chat_id} is whitelisted again - keeping its data");
+            if let Err(e) = delete_archived_chat(storage, chat_id).await {
+                warn!("Failed to unarchive chat {chat_id} - {e:?}");
+            }
+            continue;
+        }
+        if archived_at >= cutoff {
+            continue;
+        }
+
+        match delete_chat_data(storage, chat_id).await {
+            Ok(rows) => {
+                info!("Deleted {rows} rows of chat {chat_id} archived at {archived_at}");
+                throttle
+                    .write()
+                    .await
+                    .retain(|(_, throttled_chat, _), _| *throttled_chat != chat_id);
+                forget_chat_settings(chat_id);
+                purged += 1;
+            }
+            Err(e) => warn!("Failed to delete data of archived chat {chat_id} - {e:?}"),
+        }
+    }
+
+    purged
+}
+
+/// Runs the pruning query of a record kind, returns the number of deleted records
+async fn prune_records(records: &str, prune: impl Future<Output = anyhow::Result<u64>>) -> u64 {
+    prune.await.unwrap_or_else(|e| {
+        warn!("Failed to prune {records} in storage - {e:?}");
+        0
+    })
+}
+
+async fn run_maintenance(storage: &SqlitePool, throttle: &Cache) -> String {
+    let retention = APP_CONFIG.get().unwrap().app_config().retention.clone();
+    let now = Utc::now();
+    let cutoff = now - MAX_THROTTLING;
+
+    let pruned_in_memory = {
+        let mut throttle_guard = throttle.write().await;
+        let before = throttle_guard.len();
+        throttle_guard.retain(|_, sent_at| *sent_at >= cutoff);
+        throttle_guard.shrink_to_fit();
+        before - throttle_guard.len()
+    };
+
+    caches().run_pending_tasks().await;
+
+    let pruned_in_storage =
+        prune_throttling_info(storage, retention_cutoff(now, retention.throttle_days))
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to prune throttle data in storage - {e:?}");
+                0
+            });
+
+    let pruned_mentions = prune_records(
+        "mentions",
+        prune_mentions(storage, retention_cutoff(now, retention.call_history_days)),
+    )
+    .await;
+
+    let pruned_watches = prune_records(
+        "watches",
+        prune_watches(storage, retention_cutoff(now, retention.watch_days)),
+    )
+    .await;
+
+    let pruned_outbox = prune_records(
+        "outbox",
+        prune_outbox(storage, retention_cutoff(now, retention.outbox_days)),
+    )
+    .await;
+
+    let pruned_tags = prune_records(
+        "token tags",
+        prune_token_tags(storage, retention_cutoff(now, retention.token_tag_days)),
+    )
+    .await;
+
+    let usage_cutoff = retention_cutoff(now, retention.provider_usage_days)
+        .format("%Y-%m-%d")
+        .to_string();
+    let pruned_usage = prune_records(
+        "provider usage",
+        prune_provider_usage(storage, &usage_cutoff),
+    )
+    .await;
+
+    let purged_chats = purge_archived_chats(
+        storage,
+        throttle,
+        retention_cutoff(now, retention.archived_chat_days),
+    )
+    .await;
+
+    let size_before = storage_size(storage).await.unwrap_or_default();
+    if let Err(e) = compact_storage(storage).await {
+        warn!("Failed to compact storage - {e:?}");
+    }
+    let size_after = storage_size(storage).await.unwrap_or_default();
+
+    format!(
+        "Nightly maintenance done - pruned {pruned_in_memory} throttle entries in memory \
+        and {pruned_in_storage} in storage, pruned {pruned_mentions} mentions, \
+        {pruned_watches} alerts, {pruned_outbox} queued replies, {pruned_tags} token tags, \
+        {pruned_usage} provider usage rows, purged {purged_chats} archived chats, \
+        database {} KB -> {} KB",
+        size_before / BYTES_IN_KB,
+        size_after / BYTES_IN_KB,
+    )
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+use std::time::Duration;
+
+use log::{debug, warn};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use serde::Deserialize;
+
+use crate::APP_CONFIG;
+use crate::coingecko::{CoinMarket, retrieve_coin_markets};
+use crate::providers::{Provider, guarded};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// DexScreener chain id -> CoinGecko id and ticker of the native coin
+const NATIVE_COINS: [(&str, &str, &str); 7] = [
+    ("solana", "solana", "SOL"),
+    ("bsc", "binancecoin", "BNB"),
+    ("base", "ethereum", "ETH"),
+    ("arbitrum", "ethereum", "ETH"),
+    ("polygon", "polygon-ecosystem-token", "POL"),
+    ("ton", "the-open-network", "TON"),
+    ("tron", "tron", "TRX"),
+];
+
+/// CoinGecko id and ticker of the stablecoins the mcaps are usually quoted in
+const STABLECOINS: [(&str, &str); 2] = [("tether", "USDT"), ("usd-coin", "USDC")];
+
+/// Notes appended to the cards when the market moves under the token
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MarketContextConfig {
+    /// Hourly move of the native coin worth mentioning, in percent, 0 disables it
+    pub native_move_pct: f64,
+    /// Distance of a stablecoin from $1 worth mentioning, in percent, 0 disables it
+    pub depeg_pct: f64,
+}
+
+impl Default for MarketContextConfig {
+    fn default() -> Self {
+        Self {
+            native_move_pct: 5.0,
+            depeg_pct: 1.0,
+        }
+    }
+}
+
+/// Latest CoinGecko markets by coin id
+static MARKETS: LazyLock<RwLock<HashMap<String, CoinMarket>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+async fn refresh_markets(client: reqwest::Client) {
+    let mut ids: Vec<&str> = NATIVE_COINS
+        .iter()
+        .map(|(_, id, _)| *id)
+        .chain(STABLECOINS.iter().map(|(id, _)| *id))
+        .collect();
+    // several chains share the native coin
+    ids.sort_unstable();
+    ids.dedup();
+
+    match guarded(Provider::CoinGecko, retrieve_coin_markets(&ids, client)).await {
+        Ok(markets) => {
+            debug!("Refreshed {} native and stablecoin prices", markets.len());
+            let mut cached = MARKETS.write().unwrap();
+            for market in markets {
+                cached.insert(market.id.clone(), market);
+            }
+        }
+        Err(e) => warn!("Failed to refresh native prices - {e:?}"),
+    }
+}
+
+/// Keeps the native coin and stablecoin prices used by [`market_note`] fresh
+pub fn spawn_native_price_feed(client: reqwest::Client) {
+    tokio::spawn(async move {
+        loop {
+            refresh_markets(client.clone()).await;
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+fn threshold(pct: f64) -> Option<Decimal> {
+    Decimal::from_f64(pct).filter(|pct| pct.is_sign_positive() && !pct.is_zero())
+}
+
+fn native_move(
+    markets: &HashMap<String, CoinMarket>,
+    chain_id: &str,
+    pct: Decimal,
+) -> Option<String> {
+    let (_, id, ticker) = NATIVE_COINS
+        .iter()
+        .find(|(chain, _, _)| *chain == chain_id)?;
+    let change = markets.get(*id)?.price_change_percentage_1h_in_currency?;
+    if change.abs() < pct {
+        return None;
+    }
+
+    let sign = if change.is_sign_negative() {
+        "−"
+    } else {
+        "+"
+    };
+    Some(format!(
+        "{ticker} {sign}{}% last hour — mcaps shifting",
+        change.abs().round_dp(1).normalize()
+    ))
+}
+
+fn depegs(markets: &HashMap<String, CoinMarket>, pct: Decimal) -> Vec<String> {
+    STABLECOINS
+        .iter()
+        .filter_map(|(id, ticker)| {
+            let price = markets.get(*id)?.current_price?;
+            let distance = (price - Decimal::ONE).abs() * Decimal::ONE_HUNDRED;
+            (distance >= pct)
+                .then(|| format!("{ticker} ${} depegged", price.round_dp(3).normalize()))
+        })
+        .collect()
+}
+
+/// Warns about sharp native coin moves on the chain and depegged stablecoins
+/// since they shift the mcaps regardless of the token itself
+pub fn market_note(chain_id: &str) -> Option<String> {
+    let config = APP_CONFIG
+        .get()
+        .unwrap()
+        .app_config()
+        .market_context
+        .clone();
+    let markets = MARKETS.read().unwrap();
+
+    let notes: Vec<String> = threshold(config.native_move_pct)
+        .and_then(|pct| native_move(&markets, chain_id, pct))
+        .into_iter()
+        .chain(
+            threshold(config.depeg_pct)
+                .map(|pct| depegs(&markets, pct))
+                .unwrap_or_default(),
+        )
+        .collect();
+
+    (!notes.is_empty()).then(|| notes.join(", "))
+}
@@ -0,0 +1,138 @@
+use chrono::{DateTime, Utc};
+use log::warn;
+use rust_decimal::Decimal;
+use sqlx::SqlitePool;
+use teloxide::types::ChatId;
+
+use crate::context::MessageContext;
+use crate::storage::{
+    increment_mention_count, load_first_mention, save_first_mention, update_mention_mcap,
+};
+use crate::token_info::format_human_readable;
+
+/// The first card of the token in the chat
+#[derive(Debug, Clone)]
+pub struct FirstMention {
+    pub mcap: Option<Decimal>,
+    /// `@username` or the name of whoever posted the CA
+    pub caller: Option<String>,
+    pub mentioned_at: DateTime<Utc>,
+    /// How many times the token was carded in the chat, including the first time
+    pub count: i64,
+}
+
+/// `1st`, `2nd`, `3rd`, `11th`, `22nd`, ...
+fn ordinal(n: i64) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+impl FirstMention {
+    /// e.g. "3rd mention, first called 2d ago at 150K by @user (now 1.2M, 8x)", not escaped
+    pub fn describe(&self, current_mcap: Option<Decimal>) -> String {
+        let ago = Utc::now() - self.mentioned_at;
+        let ago = if ago.num_days() > 0 {
+            format!("{}d ago", ago.num_days())
+        } else if ago.num_hours() > 0 {
+            format!("{}h ago", ago.num_hours())
+        } else if ago.num_minutes() > 0 {
+            format!("{}m ago", ago.num_minutes())
+        } else {
+            "just now".to_owned()
+        };
+
+        let mut line = format!("{} mention, first called {ago}", ordinal(self.count));
+        if let Some(mcap) = self.mcap {
+            line.push_str(&format!(" at {}", format_human_readable(mcap, 1)));
+        }
+        if let Some(caller) = &self.caller {
+            line.push_str(&format!(" by {caller}"));
+        }
+
+        match (self.mcap, current_mcap) {
+            (Some(called), Some(now)) if called > Decimal::ZERO => {
+                let multiple = (now / called).round_dp(1).normalize();
+                line.push_str(&format!(
+                    " (now {}, {multiple}x)",
+                    format_human_readable(now, 1)
+                ));
+            }
+            (_, Some(now)) => line.push_str(&format!(" (now {})", format_human_readable(now, 1))),
+            _ => {}
+        }
+
+        line
+    }
+}
+
+/// Returns the first mention of the token in the chat, the current message
+/// is recorded as the first one if there is none yet
+pub async fn record_mention(
+    ctx: &MessageContext<'_>,
+    chain_id: &str,
+    token_ca: &str,
+    symbol: &str,
+    mcap: Option<Decimal>,
+) -> Option<FirstMention> {
+    let mention = FirstMention {
+        mcap,
+        caller: ctx.caller.clone(),
+        mentioned_at: Utc::now(),
+        count: 1,
+    };
+    record_mention_in(
+        &ctx.storage,
+        ctx.chat_id(),
+        chain_id,
+        token_ca,
+        symbol,
+        mention,
+    )
+    .await
+}
+
+/// Same as [`record_mention`], but for a mention not coming from a live message,
+/// e.g. one imported from the chat history
+pub async fn record_mention_in(
+    storage: &SqlitePool,
+    chat_id: ChatId,
+    chain_id: &str,
+    token_ca: &str,
+    symbol: &str,
+    mention: FirstMention,
+) -> Option<FirstMention> {
+    let mcap = mention.mcap.filter(|mcap| *mcap > Decimal::ZERO);
+    match load_first_mention(storage, chat_id, token_ca).await {
+        Ok(Some(mut first)) => {
+            match increment_mention_count(storage, chat_id, token_ca).await {
+                Ok(count) => first.count = count,
+                Err(e) => warn!("Failed to count the mention of {token_ca} - {e:?}"),
+            }
+            if let Some(mcap) = mcap
+                && let Err(e) = update_mention_mcap(storage, chain_id, token_ca, mcap).await
+            {
+                warn!("Failed to update mcap of {token_ca} mentions - {e:?}");
+            }
+            return Some(first);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            warn!("Failed to load first mention of {token_ca} - {e:?}");
+            return None;
+        }
+    }
+
+    let first = FirstMention { mcap, ..mention };
+    let saved = save_first_mention(storage, chat_id, chain_id, token_ca, symbol, &first);
+    if let Err(e) = saved.await {
+        warn!("Failed to save first mention of {token_ca} - {e:?}");
+    }
+
+    None
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use log::warn;
+use reqwest::{RequestBuilder, Response, StatusCode};
+
+use crate::APP_CONFIG;
+use crate::providers::Provider;
+use crate::quota::record_usage;
+use crate::retry::send_with_retry;
+
+/// Estimated compute units Moralis charges per endpoint, for the quota accounting
+pub const TOKEN_METADATA_CU: u64 = 10;
+pub const TOKEN_HOLDERS_CU: u64 = 50;
+pub const NATIVE_BALANCE_CU: u64 = 10;
+pub const WALLET_TOKENS_CU: u64 = 100;
+
+/// Keys Moralis refused, skipped until the instant
+static EXHAUSTED: LazyLock<Mutex<HashMap<String, Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// `MORALIS_TOKEN` keys first, then the ones from the config
+fn moralis_keys() -> Vec<String> {
+    let cfg = APP_CONFIG.get().unwrap();
+    let mut keys = cfg.moralis_tokens.clone();
+    for key in &cfg.app_config().moralis_keys {
+        if !keys.contains(key) {
+            keys.push(key.clone());
+        }
+    }
+    keys
+}
+
+pub fn moralis_key_count() -> usize {
+    moralis_keys().len()
+}
+
+/// Enough of the key to tell it in the logs
+fn key_hint(key: &str) -> String {
+    let suffix = key.get(key.len().saturating_sub(4)..).unwrap_or_default();
+    format!("...{suffix}")
+}
+
+/// The first key not tried yet which isn't exhausted, or the one recovering soonest
+fn pick_key(keys: &[String], tried: &[String]) -> Option<String> {
+    let exhausted = EXHAUSTED.lock().unwrap();
+    let now = Instant::now();
+
+    let untried = keys.iter().filter(|key| !tried.contains(key));
+    let usable = untried
+        .clone()
+        .find(|key| exhausted.get(*key).is_none_or(|until| *until <= now));
+
+    usable
+        .or_else(|| untried.min_by_key(|key| exhausted.get(*key).copied()))
+        .cloned()
+}
+
+fn mark_exhausted(key: &str, status: StatusCode) {
+    let cooldown_mins = APP_CONFIG
+        .get()
+        .unwrap()
+        .app_config()
+        .moralis_key_cooldown_mins;
+    warn!(
+        "Moralis key {} got {status} - skipping it for {cooldown_mins} min",
+        key_hint(key)
+    );
+
+    let until = Instant::now() + Duration::from_secs(cooldown_mins * 60);
+    EXHAUSTED.lock().unwrap().insert(key.to_owned(), until);
+}
+
+/// Sends the Moralis request with the first usable key, moving on to the next key
+/// while Moralis answers 401 (key disabled) or 429 (compute units used up).
+/// The last response is returned as is, so callers still have to check its status.
+pub async fn send_moralis(request: RequestBuilder, compute_units: u64) -> anyhow::Result<Response> {
+    let keys = moralis_keys();
+    let mut tried = Vec::new();
+
+    loop {
+        let Some(key) = pick_key(&keys, &tried) else {
+            return Err(anyhow::anyhow!("No Moralis API keys configured"));
+        };
+        let Some(attempt) = request.try_clone() else {
+            return Err(anyhow::anyhow!("Moralis request can't be cloned"));
+        };
+
+        let response = send_with_retry(attempt.header("X-API-Key", key.as_str())).await?;
+        let status = response.status();
+        if status != StatusCode::UNAUTHORIZED && status != StatusCode::TOO_MANY_REQUESTS {
+            record_usage(Provider::Moralis, 0, compute_units);
+            return Ok(response);
+        }
+
+        mark_exhausted(&key, status);
+        tried.push(key);
+        if tried.len() == keys.len() {
+            return Ok(response);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use log::{debug, warn};
+use teloxide::payloads::{AnswerCallbackQuerySetters, EditMessageTextSetters};
+use teloxide::prelude::{Requester, ResponseResult};
+use teloxide::sugar::request::RequestLinkPreviewExt;
+use teloxide::types::{
+    CallbackQuery, InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup,
+};
+use teloxide::{ApiError, Bot, RequestError};
+
+use crate::cache::caches;
+use crate::formatter::{OutputFormat, telegram_text};
+
+pub const MORE_CALLBACK_DATA: &str = "more";
+
+pub fn more_button() -> InlineKeyboardButton {
+    InlineKeyboardButton::callback("More ▼", MORE_CALLBACK_DATA)
+}
+
+fn is_more_button(button: &InlineKeyboardButton) -> bool {
+    matches!(&button.kind, InlineKeyboardButtonKind::CallbackData(data) if data == MORE_CALLBACK_DATA)
+}
+
+/// Expands the compact card to the full one, keeping the other buttons of the card
+pub async fn handle_more(bot: &Bot, query: &CallbackQuery) -> ResponseResult<()> {
+    let Some(card) = query.regular_message() else {
+        debug!("Expanded card is inaccessible - skipping it");
+        bot.answer_callback_query(query.id.clone()).await?;
+        return Ok(());
+    };
+
+    let Some(full_text) = caches().full_cards.get(&(card.chat.id, card.id)).await else {
+        bot.answer_callback_query(query.id.clone())
+            .text("This card is too old to expand, paste the token again")
+            .await?;
+        return Ok(());
+    };
+
+    bot.answer_callback_query(query.id.clone()).await?;
+
+    let rows: Vec<Vec<InlineKeyboardButton>> = card
+        .reply_markup()
+        .map(|markup| {
+            markup
+                .inline_keyboard
+                .iter()
+                .map(|row| row.iter().filter(|b| !is_more_button(b)).cloned().collect())
+                .filter(|row: &Vec<_>| !row.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    let markup = InlineKeyboardMarkup::new(rows);
+
+    let (text, parse_mode) = telegram_text(&full_text);
+    let mut result = bot
+        .edit_message_text(card.chat.id, card.id, text)
+        .parse_mode(parse_mode)
+        .disable_link_preview(true)
+        .reply_markup(markup.clone())
+        .await;
+
+    if let Err(RequestError::Api(ApiError::CantParseEntities(err))) = &result {
+        warn!(
+            "Telegram failed to parse full card {} - {err} - resending it as plain text",
+            card.id
+        );
+        result = bot
+            .edit_message_text(
+                card.chat.id,
+                card.id,
+                OutputFormat::PlainText.convert(&full_text),
+            )
+            .disable_link_preview(true)
+            .reply_markup(markup)
+            .await;
+    }
+
+    if let Err(e) = result {
+        warn!("Failed to expand card {} - {e:?}", card.id);
+    }
+
+    Ok(())
+}
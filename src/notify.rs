@@ -0,0 +1,172 @@
+use std::sync::OnceLock;
+
+use anyhow::anyhow;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use teloxide::Bot;
+use teloxide::prelude::Requester;
+use teloxide::types::ChatId;
+
+use crate::APP_CONFIG;
+use crate::email::send_email;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    fn label(&self) -> &'static str {
+        match self {
+            Severity::Info => "ℹ️ INFO",
+            Severity::Warning => "⚠️ WARNING",
+            Severity::Critical => "🚨 CRITICAL",
+        }
+    }
+}
+
+/// A single notification channel
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Notifier {
+    /// Sends the notification to a Telegram chat (usually the admin chat)
+    Telegram { chat_id: i64 },
+    /// POSTs the notification as JSON to an outbound webhook, signed when a `secret` is set
+    Webhook {
+        url: String,
+        #[serde(default)]
+        secret: Option<String>,
+    },
+    /// Sends the notification by email via the `smtp` server from the config
+    Email { to: Vec<String> },
+}
+
+/// Ordered list of channels for each severity. A notification is escalated
+/// to the next channel of the list only if delivery via the previous one failed.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EscalationConfig {
+    pub info: Vec<Notifier>,
+    pub warning: Vec<Notifier>,
+    pub critical: Vec<Notifier>,
+}
+
+impl EscalationConfig {
+    fn route(&self, severity: Severity) -> &[Notifier] {
+        match severity {
+            Severity::Info => &self.info,
+            Severity::Warning => &self.warning,
+            Severity::Critical => &self.critical,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct WebhookPayload<'a> {
+    severity: Severity,
+    message: &'a str,
+    timestamp: i64,
+}
+
+/// Base64 HMAC-SHA256 of `{timestamp}.{body}`. The receiver recomputes it with the shared
+/// secret and rejects stale timestamps, so captured requests can't be replayed later
+fn sign_webhook(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(format!("{timestamp}.").as_bytes());
+    mac.update(body);
+    STANDARD.encode(mac.finalize().into_bytes())
+}
+
+static NOTIFIER_CONTEXT: OnceLock<(Bot, reqwest::Client)> = OnceLock::new();
+
+pub fn init_notifier(bot: Bot, client: reqwest::Client) {
+    // This is safe if init_notifier is called just once directly in the main fn
+    NOTIFIER_CONTEXT.set((bot, client)).unwrap();
+}
+
+/// Escalates the notification in the background according to the configured route
+pub fn notify(severity: Severity, message: String) {
+    tokio::spawn(async move {
+        let app_config = APP_CONFIG.get().unwrap().app_config();
+        let route = app_config.escalation.route(severity);
+        if route.is_empty() {
+            debug!("No notifiers configured for {severity:?} - dropping notification {message}");
+            return;
+        }
+
+        for notifier in route {
+            match send_notification(notifier, severity, &message).await {
+                Ok(()) => {
+                    debug!("Delivered {severity:?} notification via {notifier:?}");
+                    return;
+                }
+                Err(err) => {
+                    warn!("Failed to deliver notification via {notifier:?} - {err:?} - escalating");
+                }
+            }
+        }
+
+        warn!("All notifiers failed for {severity:?} notification {message}");
+    });
+}
+
+async fn send_notification(
+    notifier: &Notifier,
+    severity: Severity,
+    message: &str,
+) -> anyhow::Result<()> {
+    let (bot, client) = NOTIFIER_CONTEXT.get().unwrap();
+
+    match notifier {
+        Notifier::Telegram { chat_id } => {
+            bot.send_message(ChatId(*chat_id), format!("{}\n{message}", severity.label()))
+                .await?;
+        }
+        Notifier::Webhook { url, secret } => {
+            let timestamp = Utc::now().timestamp();
+            let body = serde_json::to_vec(&WebhookPayload {
+                severity,
+                message,
+                timestamp,
+            })?;
+
+            let mut request = client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json");
+            if let Some(secret) = secret {
+                request = request
+                    .header("X-Timestamp", timestamp)
+                    .header("X-Signature", sign_webhook(secret, timestamp, &body));
+            }
+            request.body(body).send().await?.error_for_status()?;
+        }
+        Notifier::Email { to } => {
+            let app_config = APP_CONFIG.get().unwrap().app_config();
+            let Some(smtp) = &app_config.smtp else {
+                return Err(anyhow!("Email notifier is used but smtp is not configured"));
+            };
+            let first_line = message.lines().next().unwrap_or_default();
+            let subject = format!("[{}] {first_line}", severity.label());
+            send_email(smtp, to, &subject, message.to_owned()).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Provider outages are network errors or error statuses returned by the provider,
+/// as opposed to the token simply not being found
+pub fn is_provider_outage(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<reqwest::Error>().is_some_and(|e| {
+        e.is_connect() || e.is_timeout() || e.status().is_some_and(|s| s.is_server_error())
+    })
+}
@@ -0,0 +1,284 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use teloxide::payloads::SendMessageSetters;
+use teloxide::prelude::Requester;
+use teloxide::sugar::request::RequestLinkPreviewExt;
+use teloxide::types::{
+    ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Message, MessageId, ParseMode,
+    ReplyParameters, ThreadId,
+};
+use teloxide::{ApiError, Bot, RequestError};
+
+use crate::APP_CONFIG;
+use crate::delete::schedule_auto_delete;
+use crate::followups::{CardToken, remember_card};
+use crate::formatter::{OutputFormat, telegram_text};
+use crate::pacing::{record_send_error, wait_for_send_slot};
+use crate::settings::chat_settings;
+use crate::storage::{
+    delete_outbox_entry, enqueue_outbox_entry, load_due_outbox, reschedule_outbox_entry,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Entries retried in a single pass
+const BATCH_SIZE: i64 = 20;
+
+const BASE_BACKOFF_SECS: i64 = 5;
+
+const MAX_BACKOFF_SECS: i64 = 10 * 60;
+
+static QUEUED: AtomicU64 = AtomicU64::new(0);
+static DELIVERED: AtomicU64 = AtomicU64::new(0);
+static EXPIRED: AtomicU64 = AtomicU64::new(0);
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// Replies which failed to be sent are retried until they are `ttl_mins` old
+/// or failed `max_attempts` times
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OutboxConfig {
+    pub ttl_mins: u64,
+    pub max_attempts: u32,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            ttl_mins: 30,
+            max_attempts: 8,
+        }
+    }
+}
+
+/// Reply with token cards, along with what's needed to serve its buttons and follow-ups
+#[derive(Debug, Clone)]
+pub struct CardReply {
+    /// MarkdownV2 text
+    pub text: String,
+    pub buttons: Vec<InlineKeyboardButton>,
+    pub tokens: Vec<CardToken>,
+    /// Full cards when `text` has the compact ones
+    pub full_text: Option<String>,
+}
+
+/// Reply waiting to be resent
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub chat_id: ChatId,
+    pub thread_id: Option<ThreadId>,
+    pub reply_to: MessageId,
+    pub reply: CardReply,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Outbox counters since the start, reported by the health endpoint
+#[derive(Debug, Serialize)]
+pub struct OutboxStats {
+    queued: u64,
+    delivered: u64,
+    expired: u64,
+    dropped: u64,
+}
+
+pub fn outbox_stats() -> OutboxStats {
+    OutboxStats {
+        queued: QUEUED.load(Ordering::Relaxed),
+        delivered: DELIVERED.load(Ordering::Relaxed),
+        expired: EXPIRED.load(Ordering::Relaxed),
+        dropped: DROPPED.load(Ordering::Relaxed),
+    }
+}
+
+/// Errors which may go away on their own, e.g. network issues or flood control
+pub fn is_retryable(error: &RequestError) -> bool {
+    matches!(
+        error,
+        RequestError::RetryAfter(_) | RequestError::Network(_) | RequestError::Io(_)
+    )
+}
+
+fn retry_delay(error: &RequestError, attempts: u32) -> TimeDelta {
+    if let RequestError::RetryAfter(seconds) = error {
+        return seconds.chrono_duration();
+    }
+
+    let backoff = BASE_BACKOFF_SECS
+        .saturating_mul(2i64.saturating_pow(attempts.saturating_sub(1)))
+        .min(MAX_BACKOFF_SECS);
+    TimeDelta::seconds(backoff)
+}
+
+/// Queues the reply which failed to be sent with `error` for a retry
+pub async fn enqueue_reply(
+    storage: &SqlitePool,
+    chat_id: ChatId,
+    thread_id: Option<ThreadId>,
+    reply_to: MessageId,
+    reply: CardReply,
+    error: &RequestError,
+) {
+    let now = Utc::now();
+    let entry = OutboxEntry {
+        id: 0,
+        chat_id,
+        thread_id,
+        reply_to,
+        reply,
+        attempts: 1,
+        next_attempt_at: now + retry_delay(error, 1),
+        created_at: now,
+    };
+
+    match enqueue_outbox_entry(storage, &entry).await {
+        Ok(()) => {
+            QUEUED.fetch_add(1, Ordering::Relaxed);
+            info!(
+                "Queued reply to {} in {chat_id} for a retry at {}",
+                reply_to, entry.next_attempt_at
+            );
+        }
+        Err(e) => {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+            warn!("Failed to queue reply to {reply_to} in {chat_id} - {e:?}");
+        }
+    }
+}
+
+async fn send_queued_message(
+    bot: &Bot,
+    entry: &OutboxEntry,
+    text: String,
+    parse_mode: Option<ParseMode>,
+) -> Result<Message, RequestError> {
+    let mut request = bot
+        .send_message(entry.chat_id, text)
+        .disable_link_preview(true)
+        .disable_notification(chat_settings(entry.chat_id).silent)
+        // the message may have been deleted while we were retrying
+        .reply_parameters(ReplyParameters::new(entry.reply_to).allow_sending_without_reply());
+
+    if let Some(parse_mode) = parse_mode {
+        request = request.parse_mode(parse_mode);
+    }
+
+    if let Some(thread_id) = entry.thread_id {
+        request = request.message_thread_id(thread_id);
+    }
+
+    let buttons = &entry.reply.buttons;
+    if !buttons.is_empty() {
+        let rows = buttons.iter().cloned().map(|button| vec![button]);
+        request = request.reply_markup(InlineKeyboardMarkup::new(rows));
+    }
+
+    wait_for_send_slot(entry.chat_id).await;
+    let result = request.await;
+    if let Err(e) = &result {
+        record_send_error(entry.chat_id, e).await;
+    }
+    result
+}
+
+/// Sends the queued reply the way the cards are sent in the first place
+async fn resend(bot: &Bot, entry: &OutboxEntry) -> Result<(), RequestError> {
+    // the text is stored as MarkdownV2 and converted when it's sent
+    let (text, parse_mode) = telegram_text(&entry.reply.text);
+    let mut result = send_queued_message(bot, entry, text, Some(parse_mode)).await;
+
+    if let Err(RequestError::Api(ApiError::CantParseEntities(err))) = &result {
+        warn!(
+            "Telegram failed to parse queued reply to {} in {} - {err} - resending it as plain text",
+            entry.reply_to, entry.chat_id
+        );
+        let text = OutputFormat::PlainText.convert(&entry.reply.text);
+        result = send_queued_message(bot, entry, text, None).await;
+    }
+
+    let sent = result?;
+    remember_card(
+        sent.chat.id,
+        sent.id,
+        entry.reply.tokens.clone(),
+        entry.reply.full_text.clone(),
+    )
+    .await;
+    if let Some(ttl) = chat_settings(entry.chat_id).auto_delete_after() {
+        schedule_auto_delete(bot.clone(), sent.chat.id, sent.id, ttl);
+    }
+    Ok(())
+}
+
+async fn process_entry(bot: &Bot, storage: &SqlitePool, entry: OutboxEntry) -> anyhow::Result<()> {
+    let config = APP_CONFIG.get().unwrap().app_config().outbox.clone();
+    let ttl = TimeDelta::minutes(i64::try_from(config.ttl_mins).unwrap_or(i64::MAX / 60_000));
+
+    if Utc::now() - entry.created_at > ttl {
+        info!(
+            "Reply to {} in {} expired after {} attempts",
+            entry.reply_to, entry.chat_id, entry.attempts
+        );
+        EXPIRED.fetch_add(1, Ordering::Relaxed);
+        return delete_outbox_entry(storage, entry.id).await;
+    }
+
+    match resend(bot, &entry).await {
+        Ok(()) => {
+            info!(
+                "Delivered queued reply to {} in {} after {} attempts",
+                entry.reply_to, entry.chat_id, entry.attempts
+            );
+            DELIVERED.fetch_add(1, Ordering::Relaxed);
+            delete_outbox_entry(storage, entry.id).await
+        }
+        Err(e) if is_retryable(&e) && entry.attempts < config.max_attempts => {
+            let attempts = entry.attempts + 1;
+            let next_attempt_at = Utc::now() + retry_delay(&e, attempts);
+            debug!(
+                "Failed to resend reply to {} in {} - {e:?} - retrying at {next_attempt_at}",
+                entry.reply_to, entry.chat_id
+            );
+            reschedule_outbox_entry(storage, entry.id, attempts, next_attempt_at).await
+        }
+        Err(e) => {
+            warn!(
+                "Failed to resend reply to {} in {} - {e:?} - dropping it",
+                entry.reply_to, entry.chat_id
+            );
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+            delete_outbox_entry(storage, entry.id).await
+        }
+    }
+}
+
+/// Resends the queued replies once they are due, the queue survives restarts
+pub fn spawn_outbox_worker(bot: Bot, storage: SqlitePool) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let entries = match load_due_outbox(&storage, Utc::now(), BATCH_SIZE).await {
+                Ok(entries) => entries,
+                Err(e) => {
+                    warn!("Failed to load the outbox - {e:?}");
+                    continue;
+                }
+            };
+
+            for entry in entries {
+                let id = entry.id;
+                if let Err(e) = process_entry(&bot, &storage, entry).await {
+                    warn!("Failed to update outbox entry {id} - {e:?}");
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use log::debug;
+use serde::Deserialize;
+use teloxide::RequestError;
+use teloxide::types::ChatId;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::APP_CONFIG;
+
+/// Spacing of the sent messages keeping the bot under the Telegram flood limits
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PacingConfig {
+    /// Min interval between two messages to the same chat
+    pub per_chat_interval_ms: u64,
+    /// Max messages per second across all chats
+    pub global_per_sec: u32,
+}
+
+impl Default for PacingConfig {
+    fn default() -> Self {
+        Self {
+            per_chat_interval_ms: 1000,
+            global_per_sec: 30,
+        }
+    }
+}
+
+#[derive(Default)]
+struct SendSlots {
+    next_global: Option<Instant>,
+    next_per_chat: HashMap<ChatId, Instant>,
+}
+
+static SEND_SLOTS: LazyLock<Mutex<SendSlots>> = LazyLock::new(Default::default);
+
+/// Waits until a message may be sent to the chat without hitting the flood limits.
+/// Slots are handed out in order, so messages queued in a burst are spread out.
+pub async fn wait_for_send_slot(chat_id: ChatId) {
+    let config = APP_CONFIG.get().unwrap().app_config().pacing.clone();
+    let per_chat_interval = Duration::from_millis(config.per_chat_interval_ms);
+    let global_interval = Duration::from_secs(1) / config.global_per_sec.max(1);
+
+    let slot = {
+        let mut slots = SEND_SLOTS.lock().await;
+        let now = Instant::now();
+        // entries in the past don't delay anything anymore
+        slots.next_per_chat.retain(|_, next| *next > now);
+
+        let chat_slot = slots.next_per_chat.get(&chat_id).copied().unwrap_or(now);
+        let slot = chat_slot.max(slots.next_global.unwrap_or(now)).max(now);
+
+        slots.next_global = Some(slot + global_interval);
+        slots
+            .next_per_chat
+            .insert(chat_id, slot + per_chat_interval);
+        slot
+    };
+
+    if slot > Instant::now() {
+        debug!(
+            "Delaying message to {chat_id} by {:?} to stay under the flood limits",
+            slot - Instant::now()
+        );
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+/// Holds the next messages to the chat back for as long as Telegram asked us to
+pub async fn record_send_error(chat_id: ChatId, error: &RequestError) {
+    let RequestError::RetryAfter(seconds) = error else {
+        return;
+    };
+
+    let retry_at = Instant::now() + seconds.duration();
+    let mut slots = SEND_SLOTS.lock().await;
+    let next = slots.next_per_chat.entry(chat_id).or_insert(retry_at);
+    *next = (*next).max(retry_at);
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+
+use crate::error_reports::{ErrorClass, report_error};
+use crate::notify::{Severity, is_provider_outage, notify};
+use crate::quota::record_usage;
+
+/// Consecutive outage-like failures after which the provider is skipped
+const FAILURE_THRESHOLD: u32 = 5;
+
+/// How long the provider is skipped before a trial call is let through
+const COOLDOWN: Duration = Duration::from_secs(30);
+
+/// A flapping provider is reported to be down at most once within this interval
+const OUTAGE_NOTIFY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Provider {
+    Moralis,
+    Jupiter,
+    Birdeye,
+    TonApi,
+    TronScan,
+    GoPlus,
+    CoinGecko,
+    DefiLlama,
+    SolanaRpc,
+}
+
+/// Returned by [`guarded`] instead of calling a provider whose circuit is open
+#[derive(Debug, Copy, Clone)]
+pub struct CircuitOpen(pub Provider);
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} is unavailable - its circuit is open", self.0)
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+#[derive(Debug, Copy, Clone)]
+enum BreakerState {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        since: Instant,
+    },
+    /// A single trial call is in progress, another one is let through
+    /// if it doesn't finish within the cooldown (e.g. it got cancelled)
+    HalfOpen {
+        since: Instant,
+    },
+}
+
+static BREAKERS: LazyLock<Mutex<HashMap<Provider, BreakerState>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// When each provider was last reported to be down
+static OUTAGES_NOTIFIED: LazyLock<Mutex<HashMap<Provider, Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn should_notify_outage(provider: Provider) -> bool {
+    let mut notified = OUTAGES_NOTIFIED.lock().unwrap();
+    if notified
+        .get(&provider)
+        .is_some_and(|at| at.elapsed() < OUTAGE_NOTIFY_INTERVAL)
+    {
+        return false;
+    }
+    notified.insert(provider, Instant::now());
+    true
+}
+
+fn try_acquire(provider: Provider) -> bool {
+    let mut breakers = BREAKERS.lock().unwrap();
+    let state = breakers
+        .entry(provider)
+        .or_insert(BreakerState::Closed { failures: 0 });
+
+    match *state {
+        BreakerState::Closed { .. } => true,
+        BreakerState::Open { since } | BreakerState::HalfOpen { since }
+            if since.elapsed() >= COOLDOWN =>
+        {
+            info!("Letting a trial call through to {provider:?}");
+            *state = BreakerState::HalfOpen {
+                since: Instant::now(),
+            };
+            true
+        }
+        BreakerState::Open { .. } | BreakerState::HalfOpen { .. } => false,
+    }
+}
+
+/// Returns whether the call opened the circuit, i.e. the provider went down
+fn record_result(provider: Provider, failed: bool) -> bool {
+    let mut breakers = BREAKERS.lock().unwrap();
+    let state = breakers
+        .entry(provider)
+        .or_insert(BreakerState::Closed { failures: 0 });
+
+    let mut opened = false;
+    *state = match (*state, failed) {
+        (BreakerState::HalfOpen { .. }, false) => {
+            info!("{provider:?} is back - closing its circuit");
+            BreakerState::Closed { failures: 0 }
+        }
+        (BreakerState::HalfOpen { .. }, true) => {
+            warn!("Trial call to {provider:?} failed - skipping it for {COOLDOWN:?} more");
+            BreakerState::Open {
+                since: Instant::now(),
+            }
+        }
+        (BreakerState::Closed { failures }, true) if failures + 1 >= FAILURE_THRESHOLD => {
+            warn!(
+                "{provider:?} failed {} times in a row - skipping it for {COOLDOWN:?}",
+                failures + 1
+            );
+            // the failed trial calls keep it open, it doesn't go down again
+            opened = true;
+            BreakerState::Open {
+                since: Instant::now(),
+            }
+        }
+        (BreakerState::Closed { failures }, true) => BreakerState::Closed {
+            failures: failures + 1,
+        },
+        (BreakerState::Closed { .. }, false) => BreakerState::Closed { failures: 0 },
+        // a call started before the circuit got opened
+        (state @ BreakerState::Open { .. }, _) => state,
+    };
+    opened
+}
+
+/// Runs the provider call unless its circuit is open. Only outage-like errors
+/// (timeouts, connection errors, 5xx) count as failures, "not found" doesn't.
+pub async fn guarded<T>(
+    provider: Provider,
+    call: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    if !try_acquire(provider) {
+        return Err(CircuitOpen(provider).into());
+    }
+    record_usage(provider, 1, 0);
+
+    let result = call.await;
+    let outage = result.as_ref().err().filter(|e| is_provider_outage(e));
+    if let Some(e) = outage {
+        report_error(ErrorClass::Provider(provider), format!("{e:#}"));
+    }
+    if record_result(provider, outage.is_some()) && should_notify_outage(provider) {
+        notify(
+            Severity::Critical,
+            format!("{provider:?} is down - lookups relying on it are skipped until it's back"),
+        );
+    }
+    result
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use chrono::{NaiveDate, Utc};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use teloxide::Bot;
+use teloxide::prelude::Requester;
+use teloxide::types::Message;
+
+use crate::APP_CONFIG;
+use crate::moralis_keys::moralis_key_count;
+use crate::providers::Provider;
+use crate::storage::{add_provider_usage, load_provider_usage};
+use crate::token_info::format_human_readable;
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Daily plan limits the remaining budget is estimated against
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct QuotaConfig {
+    /// Compute units each Moralis key may use per day
+    pub moralis_daily_cu: Option<u64>,
+    /// Jupiter requests allowed per day
+    pub jupiter_daily_requests: Option<u64>,
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        Self {
+            moralis_daily_cu: Some(40_000),
+            jupiter_daily_requests: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct Usage {
+    calls: u64,
+    units: u64,
+}
+
+/// Usage not written to the storage yet, by the day of the calls
+static PENDING: LazyLock<Mutex<HashMap<(NaiveDate, Provider), Usage>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Counts provider calls and units, e.g. Moralis compute units
+pub fn record_usage(provider: Provider, calls: u64, units: u64) {
+    add_pending_usage(Utc::now().date_naive(), provider, calls, units);
+}
+
+fn add_pending_usage(day: NaiveDate, provider: Provider, calls: u64, units: u64) {
+    let mut pending = PENDING.lock().unwrap();
+    let usage = pending.entry((day, provider)).or_default();
+    usage.calls += calls;
+    usage.units += units;
+}
+
+/// Usage is counted per UTC day, that's when Moralis resets its quota
+fn today() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+async fn flush_usage(storage: &SqlitePool) {
+    let pending = std::mem::take(&mut *PENDING.lock().unwrap());
+
+    for ((day, provider), usage) in pending {
+        let name = format!("{provider:?}");
+        let calls = i64::try_from(usage.calls).unwrap_or(i64::MAX);
+        let units = i64::try_from(usage.units).unwrap_or(i64::MAX);
+        let day_str = day.format("%Y-%m-%d").to_string();
+        if let Err(e) = add_provider_usage(storage, &day_str, &name, calls, units).await {
+            warn!("Failed to save {name} usage of {day} - {e:?}");
+            add_pending_usage(day, provider, usage.calls, usage.units);
+        }
+    }
+}
+
+/// Writes the counted usage to the storage every minute
+pub fn spawn_quota_flusher(storage: SqlitePool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        // the first tick completes immediately
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            flush_usage(&storage).await;
+        }
+    });
+}
+
+/// `950`, `4.5K`, `1.2M`
+fn human(value: u64) -> String {
+    let decimal_places = if value < 1000 { 0 } else { 1 };
+    format_human_readable(Decimal::from(value), decimal_places)
+}
+
+/// Line like `Moralis - 120 calls, 4.5K CU, ~35.5K of 40K CU left`
+fn usage_line(provider: &str, calls: u64, units: u64, config: &QuotaConfig) -> String {
+    match provider {
+        "Moralis" => {
+            let mut line = format!("Moralis - {calls} calls, {} CU", human(units));
+            if let Some(daily_cu) = config.moralis_daily_cu {
+                let budget = daily_cu * moralis_key_count() as u64;
+                line.push_str(&format!(
+                    ", ~{} of {} CU left",
+                    human(budget.saturating_sub(units)),
+                    human(budget)
+                ));
+            }
+            line
+        }
+        "Jupiter" => {
+            let mut line = format!("Jupiter - {calls} calls");
+            if let Some(daily_requests) = config.jupiter_daily_requests {
+                line.push_str(&format!(
+                    ", ~{} of {} left",
+                    human(daily_requests.saturating_sub(calls)),
+                    human(daily_requests)
+                ));
+            }
+            line
+        }
+        _ => format!("{provider} - {calls} calls"),
+    }
+}
+
+async fn usage_report(storage: &SqlitePool) -> anyhow::Result<String> {
+    flush_usage(storage).await;
+    let usage = load_provider_usage(storage, &today()).await?;
+    if usage.is_empty() {
+        return Ok("No provider calls today".to_owned());
+    }
+
+    let config = &APP_CONFIG.get().unwrap().app_config().quota;
+    let lines: Vec<String> = usage
+        .into_iter()
+        .map(|(provider, calls, units)| {
+            let calls = u64::try_from(calls).unwrap_or_default();
+            let units = u64::try_from(units).unwrap_or_default();
+            usage_line(&provider, calls, units, config)
+        })
+        .collect();
+
+    Ok(lines.join("\n"))
+}
+
+/// Logs today's usage, so a restart close to the limits is noticed
+pub async fn log_usage(storage: &SqlitePool) {
+    match usage_report(storage).await {
+        Ok(report) => info!("Provider usage today (UTC):\n{report}"),
+        Err(e) => warn!("Failed to load provider usage - {e:?}"),
+    }
+}
+
+/// Handles `/quota`
+pub async fn handle_quota(bot: &Bot, message: &Message, storage: &SqlitePool) {
+    let text = match usage_report(storage).await {
+        Ok(report) => format!("Provider usage today (UTC):\n{report}"),
+        Err(e) => {
+            warn!("Failed to load provider usage - {e:?}");
+            "Failed to load provider usage, try again later".to_owned()
+        }
+    };
+
+    if let Err(e) = bot.send_message(message.chat.id, text).await {
+        warn!("Failed to reply to /quota - {e:?}");
+    }
+}
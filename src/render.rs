@@ -0,0 +1,819 @@
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use handlebars::{Handlebars, handlebars_helper};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use teloxide::types::ChatId;
+use teloxide::utils::markdown::escape;
+
+use crate::APP_CONFIG;
+use crate::icons::{IconSet, icon_prefix};
+use crate::sanitize::sanitize_text;
+use crate::settings::chat_settings;
+use crate::tags::hashtags;
+use crate::token_info::{EvmTokenInfo, SolanaTokenInfo, TonTokenInfo, TronTokenInfo};
+
+/// Default card layouts. Literal text must be valid MarkdownV2, all
+/// `{{values}}` are escaped when rendered.
+const EVM_TEMPLATE: &str = "{{icon icons.name}}*{{symbol}}* \\- {{name}}\n\
+    {{icon icons.ca}}`{{ca}}`\n\
+    {{icon icons.mcap}}{{mcap}} \\- {{chain}}\
+    {{#if price}}\n{{icon icons.price}}{{price}}{{/if}}\
+    {{#if liquidity}}\n{{icon icons.liquidity}}{{liquidity}}{{/if}}\
+    {{#if exit_capacity}}\n{{icon icons.exit}}{{exit_capacity}}{{/if}}\
+    {{#if holders}}\n👥 {{holders}}{{/if}}\
+    {{#if security}}\n{{icon icons.security}}{{security}}{{/if}}\
+    {{#if risk}}\n🎯 {{risk}}{{/if}}\
+    {{#if collision}}\nℹ️ {{collision}}{{/if}}\
+    {{#if first_call}}\n📣 {{first_call}}{{/if}}\
+    {{#if market_note}}\n⚠️ {{market_note}}{{/if}}\
+    {{#if unlock}}\n{{icon icons.unlock}}{{unlock}}{{/if}}\
+    {{#if bridged_from}}\n🌉 bridged from Solana: `{{bridged_from}}`{{/if}}\n\
+    {{#if custom_links}}{{> custom_links}}{{else}}\
+    {{icon icons.gmgn}}[GMGN]({{links.gmgn}})    {{icon icons.defined}}[DF]({{links.defined}})    {{icon icons.dextools}}[DT]({{links.dextools}})\n\
+    {{icon icons.pancake}}[P\\. USDT]({{links.pancake_usdt}})     {{icon icons.pancake}}[P\\. USDC]({{links.pancake_usdc}})\n\
+    {{icon icons.uniswap}}[U\\. USDT]({{links.uniswap_usdt}})    {{icon icons.uniswap}}[U\\. USDC]({{links.uniswap_usdc}}){{/if}}\
+    {{#if tags}}\n{{tags}}{{/if}}";
+
+const SOLANA_TEMPLATE: &str = "{{icon icons.name}}*{{symbol}}* \\- {{name}}\n\
+    {{icon icons.ca}}`{{ca}}`\n\
+    {{icon icons.mcap}}{{mcap}} \\- {{chain}}{{#if launchpad}} · {{launchpad}}{{/if}}\
+    {{#if price}}\n{{icon icons.price}}{{price}}{{/if}}\
+    {{#if liquidity}}\n{{icon icons.liquidity}}{{liquidity}}{{/if}}\
+    {{#if exit_capacity}}\n{{icon icons.exit}}{{exit_capacity}}{{/if}}\
+    {{#if holders}}\n👥 {{holders}}{{/if}}\
+    {{#if risk}}\n🎯 {{risk}}{{/if}}\
+    {{#if unlock}}\n{{icon icons.unlock}}{{unlock}}{{/if}}\
+    {{#if collision}}\nℹ️ {{collision}}{{/if}}\
+    {{#if first_call}}\n📣 {{first_call}}{{/if}}\
+    {{#if market_note}}\n⚠️ {{market_note}}{{/if}}\n\
+    {{#if custom_links}}{{> custom_links}}{{else}}\
+    {{icon icons.gmgn}}[GMGN]({{links.gmgn}})            {{icon icons.meteora}}[Meteora pools]({{links.meteora}})\n\
+    {{icon icons.rugcheck}}[Rugcheck]({{links.rugcheck}})        {{icon icons.trenchradar}}[TrenchRadar]({{links.trenchradar}})\n\
+    {{icon icons.jupiter}}[JUP]({{links.jupiter}}){{/if}}\
+    {{#if tags}}\n{{tags}}{{/if}}";
+
+const TON_TEMPLATE: &str = "{{icon icons.name}}*{{symbol}}* \\- {{name}}\n\
+    {{icon icons.ca}}`{{ca}}`\n\
+    {{icon icons.mcap}}{{mcap}} \\- {{chain}}\
+    {{#if price}}\n{{icon icons.price}}{{price}}{{/if}}\
+    {{#if liquidity}}\n{{icon icons.liquidity}}{{liquidity}}{{/if}}\
+    {{#if exit_capacity}}\n{{icon icons.exit}}{{exit_capacity}}{{/if}}\
+    {{#if collision}}\nℹ️ {{collision}}{{/if}}\
+    {{#if first_call}}\n📣 {{first_call}}{{/if}}\
+    {{#if market_note}}\n⚠️ {{market_note}}{{/if}}\n\
+    {{#if custom_links}}{{> custom_links}}{{else}}\
+    {{icon icons.gmgn}}[GMGN]({{links.gmgn}})    {{icon icons.dexscreener}}[DexScreener]({{links.dexscreener}})    {{icon icons.tonviewer}}[Tonviewer]({{links.tonviewer}}){{/if}}\
+    {{#if tags}}\n{{tags}}{{/if}}";
+
+const TRON_TEMPLATE: &str = "{{icon icons.name}}*{{symbol}}* \\- {{name}}\n\
+    {{icon icons.ca}}`{{ca}}`\n\
+    {{icon icons.mcap}}{{mcap}} \\- {{chain}}\
+    {{#if price}}\n{{icon icons.price}}{{price}}{{/if}}\
+    {{#if liquidity}}\n{{icon icons.liquidity}}{{liquidity}}{{/if}}\
+    {{#if exit_capacity}}\n{{icon icons.exit}}{{exit_capacity}}{{/if}}\
+    {{#if collision}}\nℹ️ {{collision}}{{/if}}\
+    {{#if first_call}}\n📣 {{first_call}}{{/if}}\
+    {{#if market_note}}\n⚠️ {{market_note}}{{/if}}\n\
+    {{#if custom_links}}{{> custom_links}}{{else}}\
+    {{icon icons.sunpump}}[SunPump]({{links.sunpump}})    {{icon icons.dexscreener}}[DexScreener]({{links.dexscreener}})    {{icon icons.tronscan}}[TronScan]({{links.tronscan}}){{/if}}\
+    {{#if tags}}\n{{tags}}{{/if}}";
+
+const WALLET_TEMPLATE: &str = "👛 *Wallet* \\- {{chain}}\n\
+    `{{address}}`\
+    {{#each balances}}\n💰 {{this}}{{/each}}\
+    {{#if token_count}}\n🪙 {{token_count}} tokens held{{/if}}\n\
+    {{#each links}}[{{name}}]({{url}}){{#unless @last}}    {{/unless}}{{/each}}";
+
+/// Short cards sent when `compact_cards` is on, the full one is shown by the "More" button
+const EVM_COMPACT_TEMPLATE: &str = "{{icon icons.name}}*{{symbol}}* \\- {{name}}\n\
+    {{icon icons.ca}}`{{ca}}`\n\
+    {{icon icons.mcap}}{{mcap}} \\- {{chain}}\n\
+    {{#if custom_links}}{{> custom_links}}{{else}}\
+    {{icon icons.gmgn}}[GMGN]({{links.gmgn}})    {{icon icons.dextools}}[DT]({{links.dextools}}){{/if}}";
+
+const SOLANA_COMPACT_TEMPLATE: &str = "{{icon icons.name}}*{{symbol}}* \\- {{name}}\n\
+    {{icon icons.ca}}`{{ca}}`\n\
+    {{icon icons.mcap}}{{mcap}} \\- {{chain}}{{#if launchpad}} · {{launchpad}}{{/if}}\n\
+    {{#if custom_links}}{{> custom_links}}{{else}}\
+    {{icon icons.gmgn}}[GMGN]({{links.gmgn}})    {{icon icons.jupiter}}[JUP]({{links.jupiter}}){{/if}}";
+
+const TON_COMPACT_TEMPLATE: &str = "{{icon icons.name}}*{{symbol}}* \\- {{name}}\n\
+    {{icon icons.ca}}`{{ca}}`\n\
+    {{icon icons.mcap}}{{mcap}} \\- {{chain}}\n\
+    {{#if custom_links}}{{> custom_links}}{{else}}\
+    {{icon icons.gmgn}}[GMGN]({{links.gmgn}})    {{icon icons.dexscreener}}[DexScreener]({{links.dexscreener}}){{/if}}";
+
+const TRON_COMPACT_TEMPLATE: &str = "{{icon icons.name}}*{{symbol}}* \\- {{name}}\n\
+    {{icon icons.ca}}`{{ca}}`\n\
+    {{icon icons.mcap}}{{mcap}} \\- {{chain}}\n\
+    {{#if custom_links}}{{> custom_links}}{{else}}\
+    {{icon icons.sunpump}}[SunPump]({{links.sunpump}})    {{icon icons.dexscreener}}[DexScreener]({{links.dexscreener}}){{/if}}";
+
+/// Number of the configured links shown on compact cards
+const COMPACT_LINKS: usize = 2;
+
+/// Links configured for the chain, replacing the built-in ones, available as `{{> custom_links}}`
+const CUSTOM_LINKS_PARTIAL: &str = "{{#each custom_links}}{{icon icon}}[{{name}}]({{url}}){{#unless @last}}    {{/unless}}{{/each}}";
+
+handlebars_helper!(icon_helper: |icon: str| icon_prefix(icon));
+
+const WALLET_TEMPLATE_NAME: &str = "wallet";
+
+static RENDERER: LazyLock<Handlebars<'static>> = LazyLock::new(|| {
+    let mut renderer = Handlebars::new();
+    renderer.register_escape_fn(escape);
+    renderer.register_helper("icon", Box::new(icon_helper));
+    // The partial is static so this can only fail on a typo in it
+    renderer
+        .register_partial("custom_links", CUSTOM_LINKS_PARTIAL)
+        .unwrap();
+
+    for kind in [
+        CardKind::Evm,
+        CardKind::Solana,
+        CardKind::Ton,
+        CardKind::Tron,
+    ] {
+        // The default templates are static so this can only fail on a typo in them
+        renderer
+            .register_template_string(kind.name(), kind.default_template())
+            .unwrap();
+        renderer
+            .register_template_string(kind.compact_name(), kind.compact_template())
+            .unwrap();
+    }
+    renderer
+        .register_template_string(WALLET_TEMPLATE_NAME, WALLET_TEMPLATE)
+        .unwrap();
+
+    renderer
+});
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CardKind {
+    Evm,
+    Solana,
+    Ton,
+    Tron,
+}
+
+impl CardKind {
+    fn name(&self) -> &'static str {
+        match self {
+            CardKind::Evm => "evm",
+            CardKind::Solana => "solana",
+            CardKind::Ton => "ton",
+            CardKind::Tron => "tron",
+        }
+    }
+
+    fn compact_name(&self) -> &'static str {
+        match self {
+            CardKind::Evm => "evm_compact",
+            CardKind::Solana => "solana_compact",
+            CardKind::Ton => "ton_compact",
+            CardKind::Tron => "tron_compact",
+        }
+    }
+
+    fn compact_template(&self) -> &'static str {
+        match self {
+            CardKind::Evm => EVM_COMPACT_TEMPLATE,
+            CardKind::Solana => SOLANA_COMPACT_TEMPLATE,
+            CardKind::Ton => TON_COMPACT_TEMPLATE,
+            CardKind::Tron => TRON_COMPACT_TEMPLATE,
+        }
+    }
+
+    fn default_template(&self) -> &'static str {
+        match self {
+            CardKind::Evm => EVM_TEMPLATE,
+            CardKind::Solana => SOLANA_TEMPLATE,
+            CardKind::Ton => TON_TEMPLATE,
+            CardKind::Tron => TRON_TEMPLATE,
+        }
+    }
+}
+
+/// Handlebars templates of token cards, missing ones fall back to the default layout.
+/// Literal text must be valid MarkdownV2, values are escaped when rendered.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CardTemplates {
+    pub evm: Option<String>,
+    pub solana: Option<String>,
+    pub ton: Option<String>,
+    pub tron: Option<String>,
+    /// Cards of the pasted wallet addresses
+    pub wallet: Option<String>,
+}
+
+impl CardTemplates {
+    fn template(&self, kind: CardKind) -> Option<&str> {
+        match kind {
+            CardKind::Evm => self.evm.as_deref(),
+            CardKind::Solana => self.solana.as_deref(),
+            CardKind::Ton => self.ton.as_deref(),
+            CardKind::Tron => self.tron.as_deref(),
+        }
+    }
+}
+
+/// A link rendered on cards, `{ca}` and `{chain}` in the url are replaced with the token
+/// address and the DexScreener chain id (`solana`, `bsc`, `base`, `ton`, `tron`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LinkTemplate {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub icon: String,
+}
+
+impl LinkTemplate {
+    fn resolve(&self, ca: &str, chain_id: &str) -> LinkTemplate {
+        LinkTemplate {
+            name: self.name.clone(),
+            url: self.url.replace("{ca}", ca).replace("{chain}", chain_id),
+            icon: self.icon.clone(),
+        }
+    }
+}
+
+/// Links shown on cards of each chain instead of the built-in ones
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LinkSets {
+    pub evm: Option<Vec<LinkTemplate>>,
+    pub solana: Option<Vec<LinkTemplate>>,
+    pub ton: Option<Vec<LinkTemplate>>,
+    pub tron: Option<Vec<LinkTemplate>>,
+}
+
+impl LinkSets {
+    fn links(&self, kind: CardKind) -> Option<&[LinkTemplate]> {
+        match kind {
+            CardKind::Evm => self.evm.as_deref(),
+            CardKind::Solana => self.solana.as_deref(),
+            CardKind::Ton => self.ton.as_deref(),
+            CardKind::Tron => self.tron.as_deref(),
+        }
+    }
+}
+
+/// Values available to card templates, optional ones are missing when unknown
+#[derive(Debug, Serialize)]
+pub struct CardContext {
+    pub icons: IconSet,
+    pub symbol: String,
+    pub name: String,
+    pub ca: String,
+    pub mcap: String,
+    pub chain: String,
+    /// DexScreener chain id, e.g. `bsc`
+    pub chain_id: &'static str,
+    /// Badge of the launchpad the token was created on, e.g. `💊 pump.fun`
+    pub launchpad: Option<String>,
+    pub price: Option<String>,
+    pub liquidity: Option<String>,
+    pub exit_capacity: Option<String>,
+    /// Holders count and the share of the top 10 holders
+    pub holders: Option<String>,
+    pub security: Option<String>,
+    /// Composite risk score with its breakdown
+    pub risk: Option<String>,
+    pub unlock: Option<String>,
+    pub collision: Option<String>,
+    /// Who called the token first in the chat and at what mcap
+    pub first_call: Option<String>,
+    /// Native coin moves and stablecoin depegs shifting the mcap
+    pub market_note: Option<String>,
+    /// Provider categories as hashtags, e.g. `#meme #dogthemed`
+    pub tags: Option<String>,
+    pub bridged_from: Option<String>,
+    pub links: BTreeMap<&'static str, String>,
+}
+
+impl CardContext {
+    pub fn evm(token_info: &EvmTokenInfo, icons: IconSet) -> Self {
+        Self {
+            icons,
+            symbol: sanitize_text(&token_info.symbol),
+            name: sanitize_text(&token_info.name),
+            ca: token_info.id.clone(),
+            mcap: token_info.human_readable_mcap(),
+            chain: token_info.chain_name().to_owned(),
+            chain_id: token_info.dexscreener_chain_id(),
+            launchpad: None,
+            price: token_info.human_readable_price(),
+            liquidity: token_info.human_readable_liquidity(),
+            exit_capacity: token_info.human_readable_exit_capacity(),
+            holders: token_info.human_readable_holders(),
+            security: None,
+            risk: None,
+            unlock: None,
+            collision: None,
+            first_call: None,
+            market_note: None,
+            tags: hashtags(&token_info.tags),
+            bridged_from: None,
+            links: BTreeMap::from([
+                ("gmgn", token_info.gmgn_url()),
+                ("defined", token_info.defined_url()),
+                ("dextools", token_info.dextools_url()),
+                ("pancake_usdt", token_info.pancake_add_to_usdt_pool()),
+                ("pancake_usdc", token_info.pancake_add_to_usdc_pool()),
+                ("uniswap_usdt", token_info.uniswap_add_to_usdt_pool()),
+                ("uniswap_usdc", token_info.uniswap_add_to_usdc_pool()),
+            ]),
+        }
+    }
+
+    pub fn solana(data: &SolanaTokenInfo, icons: IconSet) -> Self {
+        Self {
+            icons,
+            symbol: sanitize_text(&data.symbol),
+            name: sanitize_text(&data.name),
+            ca: data.id.clone(),
+            mcap: data.human_readable_mcap(),
+            chain: "SOL".to_owned(),
+            chain_id: "solana",
+            launchpad: data.launchpad_badge(),
+            price: data.human_readable_price(),
+            liquidity: data.human_readable_liquidity(),
+            exit_capacity: data.human_readable_exit_capacity(),
+            holders: data.human_readable_holders(),
+            security: None,
+            risk: None,
+            unlock: None,
+            collision: None,
+            first_call: None,
+            market_note: None,
+            tags: hashtags(&data.tags),
+            bridged_from: None,
+            links: BTreeMap::from([
+                ("gmgn", data.gmgn_url()),
+                ("meteora", data.meteora_pools()),
+                ("rugcheck", data.rugcheck_url()),
+                ("trenchradar", data.trenchradar_url()),
+                ("jupiter", data.jup_url()),
+            ]),
+        }
+    }
+
+    pub fn ton(data: &TonTokenInfo, icons: IconSet) -> Self {
+        Self {
+            icons,
+            symbol: sanitize_text(&data.symbol),
+            name: sanitize_text(&data.name),
+            ca: data.id.clone(),
+            mcap: data.human_readable_mcap(),
+            chain: "TON".to_owned(),
+            chain_id: "ton",
+            launchpad: None,
+            price: data.human_readable_price(),
+            liquidity: data.human_readable_liquidity(),
+            exit_capacity: data.human_readable_exit_capacity(),
+            holders: None,
+            security: None,
+            risk: None,
+            unlock: None,
+            collision: None,
+            first_call: None,
+            market_note: None,
+            tags: None,
+            bridged_from: None,
+            links: BTreeMap::from([
+                ("gmgn", data.gmgn_url()),
+                ("dexscreener", data.dexscreener_url()),
+                ("tonviewer", data.tonviewer_url()),
+            ]),
+        }
+    }
+
+    pub fn tron(data: &TronTokenInfo, icons: IconSet) -> Self {
+        Self {
+            icons,
+            symbol: sanitize_text(&data.symbol),
+            name: sanitize_text(&data.name),
+            ca: data.id.clone(),
+            mcap: data.human_readable_mcap(),
+            chain: "TRON".to_owned(),
+            chain_id: "tron",
+            launchpad: None,
+            price: data.human_readable_price(),
+            liquidity: data.human_readable_liquidity(),
+            exit_capacity: data.human_readable_exit_capacity(),
+            holders: None,
+            security: None,
+            risk: None,
+            unlock: None,
+            collision: None,
+            first_call: None,
+            market_note: None,
+            tags: None,
+            bridged_from: None,
+            links: BTreeMap::from([
+                ("sunpump", data.sunpump_url()),
+                ("dexscreener", data.dexscreener_url()),
+                ("tronscan", data.tronscan_url()),
+            ]),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RenderContext<'a> {
+    #[serde(flatten)]
+    card: &'a CardContext,
+    custom_links: Vec<LinkTemplate>,
+}
+
+/// Renders the card with the chat template, falling back to the global one and then to the default.
+/// Link sets are picked the same way.
+pub fn render_card(chat_id: ChatId, kind: CardKind, card: &CardContext) -> String {
+    let cfg = APP_CONFIG.get().unwrap().app_config();
+    let ChatId(id) = chat_id;
+
+    let template = cfg
+        .chats
+        .get(&id)
+        .and_then(|chat| chat.templates.template(kind))
+        .or_else(|| cfg.templates.template(kind));
+
+    let context = RenderContext {
+        card,
+        custom_links: custom_links(chat_id, kind, card),
+    };
+
+    if let Some(template) = template {
+        match RENDERER.render_template(template, &context) {
+            Ok(text) => return text,
+            Err(e) => {
+                warn!(
+                    "Failed to render custom {kind:?} card template - {e:?} - using the default one"
+                )
+            }
+        }
+    }
+
+    RENDERER.render(kind.name(), &context).unwrap_or_else(|e| {
+        warn!("Failed to render default {kind:?} card template - {e:?}");
+        String::new()
+    })
+}
+
+/// Renders the short variant of the card, `None` unless `compact_cards` is on
+pub fn render_compact_card(chat_id: ChatId, kind: CardKind, card: &CardContext) -> Option<String> {
+    if !APP_CONFIG.get().unwrap().app_config().compact_cards {
+        return None;
+    }
+
+    let mut custom_links = custom_links(chat_id, kind, card);
+    custom_links.truncate(COMPACT_LINKS);
+    let context = RenderContext { card, custom_links };
+
+    RENDERER
+        .render(kind.compact_name(), &context)
+        .inspect_err(|e| warn!("Failed to render compact {kind:?} card template - {e:?}"))
+        .ok()
+}
+
+/// Links of the set picked via /settings, falling back to the chat ones and then to the global ones,
+/// resolved for the token
+fn custom_links(chat_id: ChatId, kind: CardKind, card: &CardContext) -> Vec<LinkTemplate> {
+    let cfg = APP_CONFIG.get().unwrap().app_config();
+    let ChatId(id) = chat_id;
+
+    let picked_set = chat_settings(chat_id).link_set;
+
+    picked_set
+        .and_then(|name| cfg.link_sets.get(&name))
+        .and_then(|links| links.links(kind))
+        .or_else(|| cfg.chats.get(&id).and_then(|chat| chat.links.links(kind)))
+        .or_else(|| cfg.links.links(kind))
+        .unwrap_or_default()
+        .iter()
+        .map(|link| link.resolve(&card.ca, card.chain_id))
+        .collect()
+}
+
+/// Values available to the wallet card template
+#[derive(Debug, Clone, Serialize)]
+pub struct WalletCardContext {
+    pub address: String,
+    pub chain: String,
+    /// Native coin balances, e.g. `1.25 SOL`
+    pub balances: Vec<String>,
+    pub token_count: Option<usize>,
+    pub links: Vec<LinkTemplate>,
+}
+
+/// Renders the wallet card with the chat template, falling back to the global one and then to the default
+pub fn render_wallet_card(chat_id: ChatId, card: &WalletCardContext) -> String {
+    let cfg = APP_CONFIG.get().unwrap().app_config();
+    let ChatId(id) = chat_id;
+
+    let template = cfg
+        .chats
+        .get(&id)
+        .and_then(|chat| chat.templates.wallet.as_deref())
+        .or(cfg.templates.wallet.as_deref());
+
+    if let Some(template) = template {
+        match RENDERER.render_template(template, card) {
+            Ok(text) => return text,
+            Err(e) => {
+                warn!(
+                    "Failed to render custom wallet card template - {e:?} - using the default one"
+                )
+            }
+        }
+    }
+
+    RENDERER
+        .render(WALLET_TEMPLATE_NAME, card)
+        .unwrap_or_else(|e| {
+            warn!("Failed to render default wallet card template - {e:?}");
+            String::new()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::OutputFormat;
+
+    /// Card with all the optional values set and special characters everywhere
+    fn card(chain: &str, links: &[(&'static str, &str)]) -> CardContext {
+        CardContext {
+            icons: IconSet::plain(),
+            symbol: "A_B.C".to_owned(),
+            name: "Dog-wif (hat) & <co>!".to_owned(),
+            ca: "0xAb_c\\1`2".to_owned(),
+            mcap: "$1.5M".to_owned(),
+            chain: chain.to_owned(),
+            chain_id: "bsc",
+            launchpad: Some("💊 pump.fun".to_owned()),
+            price: Some("$0.0₄12".to_owned()),
+            liquidity: Some("$12.3K".to_owned()),
+            exit_capacity: Some("$4K at -50%".to_owned()),
+            holders: Some("1,234 · top 10 own 12.5%".to_owned()),
+            security: Some("tax 1%/2% · LP 100% locked".to_owned()),
+            risk: Some("Risk 42/100 (LP 20 · auth 0)".to_owned()),
+            unlock: Some("10% in 3d".to_owned()),
+            collision: Some("also *BONK* on SOL".to_owned()),
+            first_call: Some("@user_1 at $100K [2x]".to_owned()),
+            market_note: Some("BNB -5.2% in 1h".to_owned()),
+            tags: Some("#meme #dog_themed".to_owned()),
+            bridged_from: Some("So1_1".to_owned()),
+            links: links
+                .iter()
+                .map(|(name, url)| (*name, (*url).to_owned()))
+                .collect(),
+        }
+    }
+
+    fn render_html(template_name: &str, card: &CardContext) -> String {
+        let context = RenderContext {
+            card,
+            custom_links: Vec::new(),
+        };
+        let markdown = RENDERER.render(template_name, &context).unwrap();
+        OutputFormat::TelegramHtml.convert(&markdown)
+    }
+
+    const HEADER: &str = "<b>A_B.C</b> - Dog-wif (hat) &amp; &lt;co&gt;!\n\
+        <code>0xAb_c\\1`2</code>\n";
+
+    const GMGN_LINK: &str = "<a href=\"https://gmgn.ai/token/0xab?ref=a_b-c.d(e)\">GMGN</a>";
+
+    #[test]
+    fn evm_card_to_html() {
+        let card = card(
+            "BSC",
+            &[
+                ("gmgn", "https://gmgn.ai/token/0xab?ref=a_b-c.d(e)"),
+                ("defined", "https://defined.fi/a\\b"),
+                ("dextools", "https://dextools.io/x"),
+                ("pancake_usdt", "https://pancake.finance/add/0xab/USDT"),
+                ("pancake_usdc", "https://pancake.finance/add/0xab/USDC"),
+                ("uniswap_usdt", "https://app.uniswap.org/add/0xab/USDT"),
+                ("uniswap_usdc", "https://app.uniswap.org/add/0xab/USDC"),
+            ],
+        );
+
+        assert_eq!(
+            render_html(CardKind::Evm.name(), &card),
+            format!(
+                "{HEADER}$1.5M - BSC\n\
+                $0.0₄12\n\
+                $12.3K\n\
+                $4K at -50%\n\
+                👥 1,234 · top 10 own 12.5%\n\
+                tax 1%/2% · LP 100% locked\n\
+                🎯 Risk 42/100 (LP 20 · auth 0)\n\
+                ℹ️ also *BONK* on SOL\n\
+                📣 @user_1 at $100K [2x]\n\
+                ⚠️ BNB -5.2% in 1h\n\
+                10% in 3d\n\
+                🌉 bridged from Solana: <code>So1_1</code>\n\
+                {GMGN_LINK}    <a href=\"https://defined.fi/a\\b\">DF</a>    \
+                <a href=\"https://dextools.io/x\">DT</a>\n\
+                <a href=\"https://pancake.finance/add/0xab/USDT\">P. USDT</a>     \
+                <a href=\"https://pancake.finance/add/0xab/USDC\">P. USDC</a>\n\
+                <a href=\"https://app.uniswap.org/add/0xab/USDT\">U. USDT</a>    \
+                <a href=\"https://app.uniswap.org/add/0xab/USDC\">U. USDC</a>\n\
+                #meme #dog_themed"
+            )
+        );
+        assert_eq!(
+            render_html(CardKind::Evm.compact_name(), &card),
+            format!(
+                "{HEADER}$1.5M - BSC\n\
+                {GMGN_LINK}    <a href=\"https://dextools.io/x\">DT</a>"
+            )
+        );
+    }
+
+    #[test]
+    fn solana_card_to_html() {
+        let card = card(
+            "SOL",
+            &[
+                ("gmgn", "https://gmgn.ai/token/0xab?ref=a_b-c.d(e)"),
+                ("meteora", "https://meteora.ag/pools?token=1"),
+                ("rugcheck", "https://rugcheck.xyz/tokens/1"),
+                ("trenchradar", "https://trench.bot/1"),
+                ("jupiter", "https://jup.ag/1"),
+            ],
+        );
+
+        assert_eq!(
+            render_html(CardKind::Solana.name(), &card),
+            format!(
+                "{HEADER}$1.5M - SOL · 💊 pump.fun\n\
+                $0.0₄12\n\
+                $12.3K\n\
+                $4K at -50%\n\
+                👥 1,234 · top 10 own 12.5%\n\
+                🎯 Risk 42/100 (LP 20 · auth 0)\n\
+                10% in 3d\n\
+                ℹ️ also *BONK* on SOL\n\
+                📣 @user_1 at $100K [2x]\n\
+                ⚠️ BNB -5.2% in 1h\n\
+                {GMGN_LINK}            <a href=\"https://meteora.ag/pools?token=1\">Meteora pools</a>\n\
+                <a href=\"https://rugcheck.xyz/tokens/1\">Rugcheck</a>        \
+                <a href=\"https://trench.bot/1\">TrenchRadar</a>\n\
+                <a href=\"https://jup.ag/1\">JUP</a>\n\
+                #meme #dog_themed"
+            )
+        );
+        assert_eq!(
+            render_html(CardKind::Solana.compact_name(), &card),
+            format!(
+                "{HEADER}$1.5M - SOL · 💊 pump.fun\n\
+                {GMGN_LINK}    <a href=\"https://jup.ag/1\">JUP</a>"
+            )
+        );
+    }
+
+    #[test]
+    fn ton_card_to_html() {
+        let card = card(
+            "TON",
+            &[
+                ("gmgn", "https://gmgn.ai/token/0xab?ref=a_b-c.d(e)"),
+                ("dexscreener", "https://dexscreener.com/ton/1"),
+                ("tonviewer", "https://tonviewer.com/1"),
+            ],
+        );
+
+        assert_eq!(
+            render_html(CardKind::Ton.name(), &card),
+            format!(
+                "{HEADER}$1.5M - TON\n\
+                $0.0₄12\n\
+                $12.3K\n\
+                $4K at -50%\n\
+                ℹ️ also *BONK* on SOL\n\
+                📣 @user_1 at $100K [2x]\n\
+                ⚠️ BNB -5.2% in 1h\n\
+                {GMGN_LINK}    <a href=\"https://dexscreener.com/ton/1\">DexScreener</a>    \
+                <a href=\"https://tonviewer.com/1\">Tonviewer</a>\n\
+                #meme #dog_themed"
+            )
+        );
+        assert_eq!(
+            render_html(CardKind::Ton.compact_name(), &card),
+            format!(
+                "{HEADER}$1.5M - TON\n\
+                {GMGN_LINK}    <a href=\"https://dexscreener.com/ton/1\">DexScreener</a>"
+            )
+        );
+    }
+
+    #[test]
+    fn tron_card_to_html() {
+        let card = card(
+            "TRON",
+            &[
+                ("sunpump", "https://sunpump.meme/token/T1(2)"),
+                ("dexscreener", "https://dexscreener.com/tron/1"),
+                ("tronscan", "https://tronscan.org/#/token20/1"),
+            ],
+        );
+
+        assert_eq!(
+            render_html(CardKind::Tron.name(), &card),
+            format!(
+                "{HEADER}$1.5M - TRON\n\
+                $0.0₄12\n\
+                $12.3K\n\
+                $4K at -50%\n\
+                ℹ️ also *BONK* on SOL\n\
+                📣 @user_1 at $100K [2x]\n\
+                ⚠️ BNB -5.2% in 1h\n\
+                <a href=\"https://sunpump.meme/token/T1(2)\">SunPump</a>    \
+                <a href=\"https://dexscreener.com/tron/1\">DexScreener</a>    \
+                <a href=\"https://tronscan.org/#/token20/1\">TronScan</a>\n\
+                #meme #dog_themed"
+            )
+        );
+        assert_eq!(
+            render_html(CardKind::Tron.compact_name(), &card),
+            format!(
+                "{HEADER}$1.5M - TRON\n\
+                <a href=\"https://sunpump.meme/token/T1(2)\">SunPump</a>    \
+                <a href=\"https://dexscreener.com/tron/1\">DexScreener</a>"
+            )
+        );
+    }
+
+    #[test]
+    fn unknown_values_are_left_out() {
+        let card = CardContext {
+            icons: IconSet::default(),
+            price: None,
+            liquidity: None,
+            exit_capacity: None,
+            collision: None,
+            first_call: None,
+            market_note: None,
+            tags: None,
+            ..card(
+                "TON",
+                &[
+                    ("gmgn", "https://gmgn.ai/1"),
+                    ("dexscreener", "https://dexscreener.com/ton/1"),
+                    ("tonviewer", "https://tonviewer.com/1"),
+                ],
+            )
+        };
+
+        assert_eq!(
+            render_html(CardKind::Ton.name(), &card),
+            "🏷️ <b>A_B.C</b> - Dog-wif (hat) &amp; &lt;co&gt;!\n\
+            📜 <code>0xAb_c\\1`2</code>\n\
+            💵 $1.5M - TON\n\
+            🦎 <a href=\"https://gmgn.ai/1\">GMGN</a>    \
+            🦅 <a href=\"https://dexscreener.com/ton/1\">DexScreener</a>    \
+            💎 <a href=\"https://tonviewer.com/1\">Tonviewer</a>"
+        );
+    }
+
+    #[test]
+    fn wallet_card_to_html() {
+        let card = WalletCardContext {
+            address: "0xAb_c".to_owned(),
+            chain: "BSC".to_owned(),
+            balances: vec!["1.25 BNB".to_owned(), "0.5 ETH".to_owned()],
+            token_count: Some(3),
+            links: vec![
+                LinkTemplate {
+                    name: "Debank".to_owned(),
+                    url: "https://debank.com/profile/0xab_c".to_owned(),
+                    icon: String::new(),
+                },
+                LinkTemplate {
+                    name: "BscScan (tokens)".to_owned(),
+                    url: "https://bscscan.com/address/0xab#tokentxns".to_owned(),
+                    icon: String::new(),
+                },
+            ],
+        };
+        let markdown = RENDERER.render(WALLET_TEMPLATE_NAME, &card).unwrap();
+
+        assert_eq!(
+            OutputFormat::TelegramHtml.convert(&markdown),
+            "👛 <b>Wallet</b> - BSC\n\
+            <code>0xAb_c</code>\n\
+            💰 1.25 BNB\n\
+            💰 0.5 ETH\n\
+            🪙 3 tokens held\n\
+            <a href=\"https://debank.com/profile/0xab_c\">Debank</a>    \
+            <a href=\"https://bscscan.com/address/0xab#tokentxns\">BscScan (tokens)</a>"
+        );
+    }
+}
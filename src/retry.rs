@@ -0,0 +1,56 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use reqwest::{RequestBuilder, Response};
+
+const MAX_ATTEMPTS: u32 = 3;
+
+const BASE_DELAY: Duration = Duration::from_millis(250);
+
+/// Up to half of the delay is added at random so retries from many lookups don't line up
+fn jitter(delay: Duration) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.subsec_nanos())
+        .unwrap_or_default();
+    delay.mul_f64(f64::from(nanos % 1000) / 2000.0)
+}
+
+fn is_transient(result: &reqwest::Result<Response>) -> bool {
+    match result {
+        Ok(response) => response.status().is_server_error(),
+        Err(e) => e.is_timeout() || e.is_connect(),
+    }
+}
+
+/// Sends the request retrying with exponential backoff on 5xx responses, timeouts
+/// and connection errors. The last response is returned as is, so callers still
+/// have to check its status.
+pub async fn send_with_retry(request: RequestBuilder) -> reqwest::Result<Response> {
+    let mut attempt = 1;
+    loop {
+        // requests with streaming bodies can't be cloned, so they get a single attempt
+        let Some(retry_request) = request.try_clone().filter(|_| attempt < MAX_ATTEMPTS) else {
+            return request.send().await;
+        };
+
+        let result = retry_request.send().await;
+        if !is_transient(&result) {
+            return result;
+        }
+
+        let delay = BASE_DELAY * 2u32.pow(attempt - 1);
+        let delay = delay + jitter(delay);
+        match &result {
+            Ok(response) => warn!(
+                "Got {} from {} - retrying in {delay:?}",
+                response.status(),
+                response.url()
+            ),
+            Err(e) => warn!("Request failed - {e:?} - retrying in {delay:?}"),
+        }
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
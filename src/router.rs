@@ -0,0 +1,576 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use futures::future;
+use log::{debug, info, warn};
+use regex::Regex;
+
+use crate::APP_CONFIG;
+use crate::links::{FoundLink, find_links, resolve_link};
+use crate::providers::{Provider, guarded};
+use crate::solana_rpc::is_token_mint;
+use crate::token_info::{
+    Chain, extract_evm_cas, extract_solana_cas, extract_ton_cas, extract_tron_cas,
+};
+use crate::validation::is_plausible_address;
+
+/// What a raw address found in a message is
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AddressKind {
+    SolanaMint,
+    SolanaWallet,
+    EvmToken,
+    EvmWallet,
+    TonJetton,
+    TronToken,
+    /// Pool address which couldn't be resolved to its base token
+    Pair,
+    TxHash,
+    /// Name service domain, e.g. `toly.sol` or `vitalik.eth`
+    Domain,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub kind: AddressKind,
+    pub address: String,
+}
+
+/// Recognizes candidates of a single kind, checked in the order of the table
+#[derive(Debug)]
+struct AddressPattern {
+    regex: Regex,
+    kind: AddressKind,
+}
+
+/// Candidates grouped by the pipeline handling them
+#[derive(Debug, Default)]
+pub struct RoutedAddresses {
+    pub solana: Vec<String>,
+    pub evm: Vec<String>,
+    pub ton: Vec<String>,
+    pub tron: Vec<String>,
+    pub solana_wallets: Vec<String>,
+    pub evm_wallets: Vec<String>,
+    /// Lowercased EVM token CA -> its chain, when a link in the message tells it
+    pub evm_chains: HashMap<String, Chain>,
+}
+
+/// Kinds with a pipeline, the rest is only recognized to keep it from being taken for CAs
+fn is_routable(kind: AddressKind) -> bool {
+    !matches!(
+        kind,
+        AddressKind::Pair | AddressKind::TxHash | AddressKind::Domain
+    )
+}
+
+/// Kind of the tokens linked on the chain, None if the chain has no pipeline
+fn linked_kind(chain_id: &str) -> Option<AddressKind> {
+    match chain_id {
+        "solana" => Some(AddressKind::SolanaMint),
+        "bsc" | "base" | "arbitrum" | "polygon" => Some(AddressKind::EvmToken),
+        "ton" => Some(AddressKind::TonJetton),
+        "tron" => Some(AddressKind::TronToken),
+        _ => None,
+    }
+}
+
+impl RoutedAddresses {
+    fn dispatch(&mut self, candidate: Candidate) {
+        let addresses = match candidate.kind {
+            AddressKind::SolanaMint => &mut self.solana,
+            AddressKind::EvmToken => &mut self.evm,
+            AddressKind::TonJetton => &mut self.ton,
+            AddressKind::TronToken => &mut self.tron,
+            AddressKind::SolanaWallet => &mut self.solana_wallets,
+            AddressKind::EvmWallet => &mut self.evm_wallets,
+            AddressKind::Pair | AddressKind::TxHash | AddressKind::Domain => {
+                debug!(
+                    "No pipeline for {:?} {} - skipping it",
+                    candidate.kind, candidate.address
+                );
+                return;
+            }
+        };
+
+        if !addresses.contains(&candidate.address) {
+            addresses.push(candidate.address);
+        }
+    }
+}
+
+static ADDRESS_PATTERNS: OnceLock<Vec<AddressPattern>> = OnceLock::new();
+
+pub fn init_address_router() {
+    let patterns = [
+        // explorer links tell what the address is
+        (
+            "https?://solscan\\.io/token/(?P<address>[1-9A-HJ-NP-Za-km-z]{32,44})",
+            AddressKind::SolanaMint,
+        ),
+        (
+            "https?://(?:solscan\\.io/account|gmgn\\.ai/sol/address)/(?P<address>[1-9A-HJ-NP-Za-km-z]{32,44})",
+            AddressKind::SolanaWallet,
+        ),
+        (
+            "https?://(?:bscscan\\.com|basescan\\.org)/token/(?P<address>0x[a-fA-F0-9]{40})",
+            AddressKind::EvmToken,
+        ),
+        (
+            "https?://(?:(?:bscscan\\.com|basescan\\.org)/address|gmgn\\.ai/(?:bsc|base)/address)/(?P<address>0x[a-fA-F0-9]{40})",
+            AddressKind::EvmWallet,
+        ),
+        (
+            "https?://(?:solscan\\.io|bscscan\\.com|basescan\\.org)/tx/(?P<address>0x[a-fA-F0-9]{64}|[1-9A-HJ-NP-Za-km-z]{64,88})",
+            AddressKind::TxHash,
+        ),
+        // bare tx hashes, otherwise their prefixes would be taken for CAs
+        (
+            "(?:^|\\s)(?P<address>0x[a-fA-F0-9]{64}|[1-9A-HJ-NP-Za-km-z]{86,88})\\b",
+            AddressKind::TxHash,
+        ),
+        (
+            "(?:^|\\s)(?P<address>[a-z0-9-]+\\.(?:sol|eth|ton|bnb))\\b",
+            AddressKind::Domain,
+        ),
+    ];
+
+    // this is safe as long as the regexes themselves are valid
+    let patterns = patterns
+        .into_iter()
+        .map(|(regex, kind)| AddressPattern {
+            regex: Regex::new(&format!("(?m){regex}")).unwrap(),
+            kind,
+        })
+        .collect();
+    // This is safe if init_address_router is called just once directly in the main fn
+    ADDRESS_PATTERNS.set(patterns).unwrap();
+}
+
+/// Classifies the addresses in the text using local checks only
+fn classify(text: &str) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = Vec::new();
+
+    for pattern in ADDRESS_PATTERNS.get().unwrap() {
+        for captures in pattern.regex.captures_iter(text) {
+            candidates.push(Candidate {
+                kind: pattern.kind,
+                address: captures["address"].to_owned(),
+            });
+        }
+    }
+
+    // an address already classified (e.g. a prefix of a tx hash) isn't a token CA
+    let is_known = |candidates: &[Candidate], address: &str| {
+        candidates.iter().any(|c| c.address.starts_with(address))
+    };
+
+    let ton_cas = extract_ton_cas(text);
+    let tron_cas = extract_tron_cas(text);
+    let token_cas = [
+        (ton_cas.clone(), AddressKind::TonJetton),
+        (tron_cas.clone(), AddressKind::TronToken),
+        (extract_evm_cas(text), AddressKind::EvmToken),
+    ];
+    for (addresses, kind) in token_cas {
+        for address in addresses {
+            if !is_known(&candidates, &address) {
+                candidates.push(Candidate { kind, address });
+            }
+        }
+    }
+
+    // TON and Tron addresses may look like Solana CAs (or start with one) as well
+    for address in extract_solana_cas(text) {
+        if ton_cas
+            .iter()
+            .any(|ton_ca| ton_ca.contains(address.as_str()))
+            || tron_cas.contains(&address)
+            || is_known(&candidates, &address)
+        {
+            continue;
+        }
+        candidates.push(Candidate {
+            kind: AddressKind::SolanaMint,
+            address,
+        });
+    }
+
+    candidates.retain(|candidate| {
+        let plausible = is_plausible_address(candidate.kind, &candidate.address);
+        if !plausible {
+            debug!(
+                "{:?} {} fails validation - skipping it",
+                candidate.kind, candidate.address
+            );
+        }
+        plausible
+    });
+
+    candidates
+}
+
+/// Reclassifies the Solana candidates which aren't token mints on chain as wallets,
+/// the ones which couldn't be checked are kept as mints
+async fn verify_solana_mints(candidates: &mut [Candidate], client: reqwest::Client) {
+    let checks = candidates
+        .iter()
+        .filter(|candidate| candidate.kind == AddressKind::SolanaMint)
+        .map(|candidate| {
+            let address = candidate.address.clone();
+            let client = client.clone();
+            async move {
+                let check = guarded(Provider::SolanaRpc, is_token_mint(&address, client)).await;
+                (address, check)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    for (address, check) in future::join_all(checks).await {
+        match check {
+            Ok(true) => {}
+            Ok(false) => {
+                debug!("{address} is not a token mint - treating it as a wallet");
+                candidates
+                    .iter_mut()
+                    .filter(|candidate| candidate.address == address)
+                    .for_each(|candidate| candidate.kind = AddressKind::SolanaWallet);
+            }
+            Err(e) => warn!("Failed to check whether {address} is a token mint - {e:?}"),
+        }
+    }
+}
+
+/// Address found in the text by the local checks
+#[derive(Debug)]
+enum Found {
+    Candidate(Candidate),
+    Link(FoundLink),
+}
+
+/// Finds the addresses in the text without any network calls, keeping the first
+/// `max_addresses` of them, so a message full of CAs doesn't burn provider quota
+fn find_addresses(text: &str, max_addresses: usize) -> (Vec<Candidate>, Vec<FoundLink>) {
+    let mut found: Vec<(usize, Found)> = Vec::new();
+    let links = find_links(text);
+
+    // the link tells the chain of its address, it mustn't be probed on the supported ones
+    let unsupported: Vec<&FoundLink> = links
+        .iter()
+        .filter(|link| linked_kind(&link.chain_id).is_none())
+        .collect();
+
+    for candidate in classify(text) {
+        if let Some(link) = unsupported
+            .iter()
+            .find(|link| link.address.eq_ignore_ascii_case(&candidate.address))
+        {
+            debug!(
+                "{} is linked on unsupported chain {} - skipping it",
+                candidate.address, link.chain_id
+            );
+            continue;
+        }
+        if !is_routable(candidate.kind) {
+            debug!(
+                "No pipeline for {:?} {} - skipping it",
+                candidate.kind, candidate.address
+            );
+            continue;
+        }
+        let position = text.find(&candidate.address).unwrap_or(usize::MAX);
+        found.push((position, Found::Candidate(candidate)));
+    }
+
+    for link in links {
+        if linked_kind(&link.chain_id).is_none() {
+            debug!(
+                "Skipping linked token {} on unsupported chain {}",
+                link.address, link.chain_id
+            );
+            continue;
+        }
+        found.push((link.position, Found::Link(link)));
+    }
+
+    // the cap keeps the addresses coming first in the message
+    found.sort_by_key(|(position, _)| *position);
+
+    let mut addresses: Vec<String> = Vec::new();
+    let mut ignored = Vec::new();
+    let mut candidates = Vec::new();
+    let mut links = Vec::new();
+    for (_, found) in found {
+        let address = match &found {
+            Found::Candidate(candidate) => &candidate.address,
+            Found::Link(link) => &link.address,
+        };
+        if !addresses.contains(address) {
+            if addresses.len() >= max_addresses {
+                ignored.push(address.clone());
+                continue;
+            }
+            addresses.push(address.clone());
+        }
+
+        match found {
+            Found::Candidate(candidate) => candidates.push(candidate),
+            Found::Link(link) => links.push(link),
+        }
+    }
+    if !ignored.is_empty() {
+        info!(
+            "Message has more than {max_addresses} addresses - ignoring {}",
+            ignored.join(", ")
+        );
+    }
+
+    (candidates, links)
+}
+
+/// Finds the addresses in the text and groups them by pipeline. Only the first
+/// `max_cas_per_message` of them are verified, DEX links among them are probed
+/// via DexScreener to resolve pair addresses to their tokens.
+pub async fn route_addresses(text: &str, client: reqwest::Client) -> RoutedAddresses {
+    let config = APP_CONFIG.get().unwrap().app_config();
+    let (mut candidates, links) = find_addresses(text, config.max_cas_per_message.max(1));
+    if config.verify_solana_mints {
+        verify_solana_mints(&mut candidates, client.clone()).await;
+    }
+
+    let mut found: Vec<(usize, Candidate)> = candidates
+        .into_iter()
+        .map(|candidate| {
+            let position = text.find(&candidate.address).unwrap_or(usize::MAX);
+            (position, candidate)
+        })
+        .collect();
+
+    let mut evm_chains = HashMap::new();
+    for link in links {
+        // resolved pairs keep the position of their links
+        let position = link.position;
+        let Some(linked) = resolve_link(link, client.clone()).await else {
+            continue;
+        };
+        if let Some(chain) = Chain::from_dexscreener_id(&linked.chain_id) {
+            evm_chains.insert(linked.token_ca.to_lowercase(), chain);
+        }
+        // the chains without a pipeline have been skipped already
+        let Some(kind) = linked_kind(&linked.chain_id) else {
+            continue;
+        };
+        found.push((
+            position,
+            Candidate {
+                kind,
+                address: linked.token_ca,
+            },
+        ));
+    }
+    found.sort_by_key(|(position, _)| *position);
+
+    let mut routed = RoutedAddresses {
+        evm_chains,
+        ..Default::default()
+    };
+    for (_, candidate) in found {
+        routed.dispatch(candidate);
+    }
+
+    routed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Once;
+
+    use super::*;
+    use crate::links::init_link_regexes;
+    use crate::token_info::{
+        init_evm_token_ca_regex, init_solana_token_ca_regex, init_ton_token_ca_regex,
+        init_tron_token_ca_regex,
+    };
+
+    const WSOL: &str = "So11111111111111111111111111111111111111112";
+    const USDC_SOL: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const BONK: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+    const USDT_BSC: &str = "0x55d398326f99059ff775485246999027b3197955";
+    const USDC_BSC: &str = "0x8ac76a51cc950d9822d68b83fe1ad97b32cd580d";
+    const USDT_TRON: &str = "TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t";
+    const USDT_TON: &str = "EQCxE6mUtQJKFnGfaROTKOt1lZbDiiX1kCixRv7Nw2Id_sDs";
+
+    fn init() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            init_address_router();
+            init_link_regexes();
+            init_solana_token_ca_regex();
+            init_evm_token_ca_regex();
+            init_ton_token_ca_regex();
+            init_tron_token_ca_regex();
+        });
+    }
+
+    /// Addresses kept by the cap, in the order they come in the text
+    fn kept(text: &str, max_addresses: usize) -> Vec<String> {
+        let (candidates, links) = find_addresses(text, max_addresses);
+        let mut kept: Vec<(usize, String)> = candidates
+            .into_iter()
+            .map(|candidate| (text.find(&candidate.address).unwrap(), candidate.address))
+            .chain(links.into_iter().map(|link| (link.position, link.address)))
+            .collect();
+        kept.sort();
+        kept.into_iter().map(|(_, address)| address).collect()
+    }
+
+    fn links(text: &str) -> Vec<(String, String)> {
+        find_links(text)
+            .into_iter()
+            .map(|link| (link.chain_id, link.address))
+            .collect()
+    }
+
+    #[test]
+    fn cap_keeps_the_first_addresses() {
+        init();
+        let text = format!(
+            "{BONK}\nhttps://dexscreener.com/bsc/{USDC_BSC}\n{WSOL} {USDT_BSC}\n{USDT_TRON}"
+        );
+
+        assert_eq!(kept(&text, 2), [BONK, USDC_BSC]);
+        assert_eq!(kept(&text, 3), [BONK, USDC_BSC, WSOL]);
+        assert_eq!(kept(&text, 10), [BONK, USDC_BSC, WSOL, USDT_BSC, USDT_TRON]);
+    }
+
+    #[test]
+    fn cap_counts_repeated_addresses_once() {
+        init();
+        let text = format!("{WSOL} {WSOL}\nhttps://birdeye.so/token/{WSOL}?chain=solana\n{BONK}");
+
+        let (candidates, links) = find_addresses(&text, 2);
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[1].address, BONK);
+        assert_eq!(links.len(), 1);
+    }
+
+    #[test]
+    fn cap_skips_what_has_no_pipeline() {
+        init();
+        let tx_hash = format!("0x{}", "ab".repeat(32));
+        let text = format!("toly.sol {tx_hash}\n{USDT_TON} {USDC_SOL}");
+
+        assert_eq!(kept(&text, 2), [USDT_TON, USDC_SOL]);
+    }
+
+    #[test]
+    fn unsupported_chains_are_skipped() {
+        init();
+        let text = format!(
+            "https://dexscreener.com/ethereum/{USDT_BSC}\n\
+            https://etherscan.io/token/{USDC_BSC}\n\
+            {USDC_BSC}"
+        );
+
+        let (candidates, links) = find_addresses(&text, 10);
+        assert!(candidates.is_empty(), "{candidates:?}");
+        assert!(links.is_empty(), "{links:?}");
+    }
+
+    #[test]
+    fn link_patterns() {
+        init();
+        let cases = [
+            (
+                format!("https://dexscreener.com/solana/{BONK}"),
+                "solana",
+                BONK,
+            ),
+            (
+                format!("https://www.dextools.io/app/en/bnb/pair-explorer/{USDT_BSC}"),
+                "bsc",
+                USDT_BSC,
+            ),
+            (
+                format!("https://www.dextools.io/app/ether/pair-explorer/{USDT_BSC}"),
+                "ethereum",
+                USDT_BSC,
+            ),
+            (format!("https://birdeye.so/token/{WSOL}"), "solana", WSOL),
+            (
+                format!("https://birdeye.so/token/{USDC_BSC}?chain=bsc"),
+                "bsc",
+                USDC_BSC,
+            ),
+            (
+                format!("https://birdeye.so/base/token/{USDC_BSC}"),
+                "base",
+                USDC_BSC,
+            ),
+            (
+                format!("https://photon-sol.tinyastro.io/en/lp/{USDC_SOL}"),
+                "solana",
+                USDC_SOL,
+            ),
+            (
+                format!("https://bscscan.com/token/{USDT_BSC}"),
+                "bsc",
+                USDT_BSC,
+            ),
+            (
+                format!("https://basescan.org/token/{USDC_BSC}"),
+                "base",
+                USDC_BSC,
+            ),
+            (
+                format!("https://arbiscan.io/token/{USDC_BSC}"),
+                "arbitrum",
+                USDC_BSC,
+            ),
+            (
+                format!("https://polygonscan.com/token/{USDC_BSC}"),
+                "polygon",
+                USDC_BSC,
+            ),
+            (
+                format!("https://etherscan.io/token/{USDC_BSC}"),
+                "ethereum",
+                USDC_BSC,
+            ),
+            (
+                format!("https://birdeye.so/token/{USDT_TRON}"),
+                "tron",
+                USDT_TRON,
+            ),
+            (
+                format!("https://birdeye.so/token/{USDT_TON}"),
+                "ton",
+                USDT_TON,
+            ),
+        ];
+
+        for (url, chain_id, address) in cases {
+            assert_eq!(
+                links(&url),
+                [(chain_id.to_owned(), address.to_owned())],
+                "{url}"
+            );
+        }
+        assert!(links(&format!("https://example.com/token/{WSOL}")).is_empty());
+    }
+
+    #[test]
+    fn links_are_ordered_by_position() {
+        init();
+        let text =
+            format!("https://bscscan.com/token/{USDT_BSC} https://dexscreener.com/solana/{BONK}");
+
+        assert_eq!(
+            links(&text),
+            [
+                ("bsc".to_owned(), USDT_BSC.to_owned()),
+                ("solana".to_owned(), BONK.to_owned())
+            ]
+        );
+    }
+}
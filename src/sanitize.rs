@@ -0,0 +1,132 @@
+/// Left-to-right isolate, keeps RTL text from reordering the text around it
+const LRI: char = '\u{2066}';
+/// Pop directional isolate, closes [`LRI`]
+const PDI: char = '\u{2069}';
+
+/// Longer texts are cut, some tokens put whole sentences (or ads) into their names
+const MAX_CHARS: usize = 64;
+
+/// Appended to Latin names with lookalike letters of other scripts mixed in
+const HOMOGLYPH_WARNING: &str = " ⚠️";
+
+/// Explicit bidi embeddings, overrides, isolates and marks - they are used to
+/// visually reorder text and spoof things like link captions
+fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+/// Invisible characters which make distinct names look the same
+fn is_zero_width(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}' | '\u{180E}' | '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}'
+    )
+}
+
+/// Cyrillic and Greek letters which look like Latin ones
+const LATIN_LOOKALIKES: &str = "АВЕКМНОРСТХаеорсухіјѕΑΒΕΖΗΙΚΜΝΟΡΤΥΧο";
+
+/// Latin text with lookalike letters mixed in, e.g. `USDТ` with the Cyrillic `Т`
+fn has_homoglyphs(text: &str) -> bool {
+    text.chars().any(|c| c.is_ascii_alphabetic())
+        && text.chars().any(|c| LATIN_LOOKALIKES.contains(c))
+}
+
+/// Makes untrusted text (token names, symbols) safe to embed into a card:
+/// strips bidi control and zero-width characters, clamps the length and isolates
+/// the rest, so Arabic/Hebrew names are rendered as is without scrambling the
+/// surrounding layout. Latin names with lookalike letters get a warning sign.
+/// Not escaped.
+pub fn sanitize_text(text: &str) -> String {
+    let visible: Vec<char> = text
+        .chars()
+        .filter(|c| !is_bidi_control(*c) && !is_zero_width(*c))
+        .collect();
+
+    let mut sanitized = String::with_capacity(text.len() + 2 * LRI.len_utf8());
+    sanitized.push(LRI);
+    if visible.len() > MAX_CHARS {
+        sanitized.extend(&visible[..MAX_CHARS - 1]);
+        sanitized.push('…');
+    } else {
+        sanitized.extend(&visible);
+    }
+    sanitized.push(PDI);
+
+    if has_homoglyphs(&sanitized) {
+        sanitized.push_str(HOMOGLYPH_WARNING);
+    }
+    sanitized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn isolated(text: &str) -> String {
+        format!("{LRI}{text}{PDI}")
+    }
+
+    #[test]
+    fn strips_bidi_controls() {
+        assert_eq!(sanitize_text("abc\u{202E}def"), isolated("abcdef"));
+        assert_eq!(sanitize_text("\u{2067}USDT\u{2069}"), isolated("USDT"));
+        assert_eq!(sanitize_text("a\u{200F}b\u{061C}c"), isolated("abc"));
+    }
+
+    #[test]
+    fn isolates_rtl_text() {
+        assert_eq!(sanitize_text("שלום"), isolated("שלום"));
+        assert_eq!(sanitize_text("مرحبا"), isolated("مرحبا"));
+    }
+
+    #[test]
+    fn strips_zero_width_characters() {
+        assert_eq!(sanitize_text("PE\u{200B}PE"), isolated("PEPE"));
+        assert_eq!(
+            sanitize_text("\u{FEFF}DOGE\u{200D}\u{2060}"),
+            isolated("DOGE")
+        );
+        assert_eq!(sanitize_text("so\u{00AD}lana"), isolated("solana"));
+    }
+
+    #[test]
+    fn flags_latin_names_with_homoglyphs() {
+        // Cyrillic Т
+        assert_eq!(
+            sanitize_text("USDТ"),
+            format!("{}{HOMOGLYPH_WARNING}", isolated("USDТ"))
+        );
+        // Greek Ο
+        assert!(sanitize_text("BΟNK").ends_with(HOMOGLYPH_WARNING));
+
+        assert_eq!(sanitize_text("USDT"), isolated("USDT"));
+        assert_eq!(sanitize_text("Привет"), isolated("Привет"));
+        assert_eq!(sanitize_text("Ελλάδα"), isolated("Ελλάδα"));
+    }
+
+    #[test]
+    fn clamps_long_texts() {
+        let clamped = sanitize_text(&"a".repeat(100));
+        assert_eq!(
+            clamped,
+            isolated(&format!("{}…", "a".repeat(MAX_CHARS - 1)))
+        );
+
+        let exact = "b".repeat(MAX_CHARS);
+        assert_eq!(sanitize_text(&exact), isolated(&exact));
+    }
+
+    #[test]
+    fn clamps_after_stripping() {
+        let padded = format!("{}{}", "\u{200B}".repeat(100), "c".repeat(MAX_CHARS));
+        assert_eq!(sanitize_text(&padded), isolated(&"c".repeat(MAX_CHARS)));
+
+        // multibyte characters are counted as characters
+        let clamped = sanitize_text(&"日".repeat(MAX_CHARS + 1));
+        assert_eq!(clamped.chars().count(), MAX_CHARS + 2);
+    }
+}
@@ -0,0 +1,423 @@
+use chrono::{TimeDelta, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, dec};
+use serde::Deserialize;
+use teloxide::types::ChatId;
+
+use crate::APP_CONFIG;
+use crate::security::TokenSecurity;
+use crate::token_info::{EvmTokenInfo, SolanaTokenInfo};
+
+/// Top holders share at or below which the holders are considered spread out, in percents
+const HOLDERS_SAFE_PCT: Decimal = dec!(15);
+/// Top holders share at or above which the token is fully controlled by them, in percents
+const HOLDERS_RISKY_PCT: Decimal = dec!(60);
+/// Tax at or above which the token is as risky as it gets, in percents
+const TAX_RISKY_PCT: Decimal = dec!(20);
+/// Tokens younger than this are the riskier the younger they are
+const AGE_RISKY_HOURS: i64 = 7 * 24;
+
+/// Relative weights of the signals in the composite risk score
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RiskWeights {
+    pub lp: u32,
+    pub authorities: u32,
+    pub holders: u32,
+    pub taxes: u32,
+    pub socials: u32,
+    pub age: u32,
+}
+
+impl Default for RiskWeights {
+    fn default() -> Self {
+        Self {
+            lp: 25,
+            authorities: 20,
+            holders: 20,
+            taxes: 15,
+            socials: 10,
+            age: 10,
+        }
+    }
+}
+
+/// Risk signals of a token, the unknown ones don't count towards the score
+#[derive(Debug, Clone, Default)]
+pub struct RiskSignals {
+    pub is_honeypot: Option<bool>,
+    /// Share of the LP tokens that are locked or burnt, in percents
+    pub lp_locked: Option<Decimal>,
+    /// Ownership renounced on EVM, mint and freeze authorities revoked on Solana
+    pub authorities_renounced: Option<bool>,
+    /// Share of the supply held by the top holders, in percents
+    pub top_holders: Option<Decimal>,
+    /// The higher of the buy and sell taxes, in percents
+    pub max_tax: Option<Decimal>,
+    pub has_socials: Option<bool>,
+    pub age: Option<TimeDelta>,
+}
+
+impl RiskSignals {
+    pub fn evm(token_info: &EvmTokenInfo, security: Option<&TokenSecurity>) -> Self {
+        Self {
+            is_honeypot: security.and_then(|security| security.is_honeypot),
+            lp_locked: security.and_then(|security| security.lp_locked),
+            authorities_renounced: security.and_then(|security| security.ownership_renounced),
+            top_holders: token_info.top10_holders_share,
+            max_tax: security.and_then(|security| security.buy_tax.max(security.sell_tax)),
+            has_socials: None,
+            age: token_info
+                .created_at
+                .map(|created_at| Utc::now() - created_at),
+        }
+    }
+
+    pub fn solana(token_info: &SolanaTokenInfo) -> Self {
+        let audit = token_info.audit.as_ref();
+        let authorities_renounced = audit.and_then(|audit| {
+            Some(audit.mint_authority_disabled? && audit.freeze_authority_disabled?)
+        });
+
+        Self {
+            is_honeypot: None,
+            lp_locked: None,
+            authorities_renounced,
+            top_holders: audit.and_then(|audit| audit.top_holders_percentage),
+            max_tax: None,
+            has_socials: Some(token_info.has_socials()),
+            age: token_info
+                .created_at()
+                .map(|created_at| Utc::now() - created_at),
+        }
+    }
+}
+
+/// Linear interpolation of the risk between the safe and the risky value, clamped to 0..=1
+fn ramp(value: Decimal, safe: Decimal, risky: Decimal) -> Decimal {
+    ((value - safe) / (risky - safe)).clamp(Decimal::ZERO, Decimal::ONE)
+}
+
+fn flag_risk(safe: bool) -> Decimal {
+    if safe { Decimal::ZERO } else { Decimal::ONE }
+}
+
+#[derive(Debug, Clone)]
+pub struct RiskScore {
+    /// 0 is the safest, 100 the riskiest
+    pub score: u32,
+    /// Points each known signal contributed to the score
+    pub breakdown: Vec<(&'static str, u32)>,
+    pub is_honeypot: bool,
+}
+
+impl RiskScore {
+    /// Line like `Risk 42/100 (LP 20 · auth 0 · holders 12 · age 10)`, not escaped
+    pub fn describe(&self) -> String {
+        if self.is_honeypot {
+            return "Risk 100/100 (honeypot)".to_owned();
+        }
+
+        let breakdown = self
+            .breakdown
+            .iter()
+            .map(|(signal, points)| format!("{signal} {points}"))
+            .collect::<Vec<_>>()
+            .join(" · ");
+        format!("Risk {}/100 ({breakdown})", self.score)
+    }
+}
+
+/// Weighted average of the known signals scaled to 0..=100, None if no signal is known
+pub fn risk_score(signals: &RiskSignals, weights: &RiskWeights) -> Option<RiskScore> {
+    if signals.is_honeypot == Some(true) {
+        return Some(RiskScore {
+            score: 100,
+            breakdown: Vec::new(),
+            is_honeypot: true,
+        });
+    }
+
+    let risks = [
+        (
+            "LP",
+            weights.lp,
+            signals
+                .lp_locked
+                .map(|locked| ramp(locked, Decimal::ONE_HUNDRED, Decimal::ZERO)),
+        ),
+        (
+            "auth",
+            weights.authorities,
+            signals.authorities_renounced.map(flag_risk),
+        ),
+        (
+            "holders",
+            weights.holders,
+            signals
+                .top_holders
+                .map(|share| ramp(share, HOLDERS_SAFE_PCT, HOLDERS_RISKY_PCT)),
+        ),
+        (
+            "tax",
+            weights.taxes,
+            signals
+                .max_tax
+                .map(|tax| ramp(tax, Decimal::ZERO, TAX_RISKY_PCT)),
+        ),
+        (
+            "socials",
+            weights.socials,
+            signals.has_socials.map(flag_risk),
+        ),
+        (
+            "age",
+            weights.age,
+            signals.age.map(|age| {
+                let hours = Decimal::from(age.num_hours());
+                ramp(hours, Decimal::from(AGE_RISKY_HOURS), Decimal::ZERO)
+            }),
+        ),
+    ];
+
+    let known_weight: u32 = risks
+        .iter()
+        .filter(|(_, _, risk)| risk.is_some())
+        .map(|(_, weight, _)| weight)
+        .sum();
+    if known_weight == 0 {
+        return None;
+    }
+
+    let breakdown: Vec<(&'static str, u32)> = risks
+        .iter()
+        .filter_map(|(signal, weight, risk)| {
+            let points = (*risk)? * Decimal::from(*weight) * Decimal::ONE_HUNDRED
+                / Decimal::from(known_weight);
+            Some((*signal, points.round().to_u32().unwrap_or_default()))
+        })
+        .collect();
+
+    Some(RiskScore {
+        score: breakdown
+            .iter()
+            .map(|(_, points)| points)
+            .sum::<u32>()
+            .min(100),
+        breakdown,
+        is_honeypot: false,
+    })
+}
+
+/// Weights configured for the chat, falling back to the global ones
+pub fn risk_weights_for_chat(chat_id: ChatId) -> RiskWeights {
+    let cfg = APP_CONFIG.get().unwrap().app_config();
+    let ChatId(id) = chat_id;
+
+    cfg.chats
+        .get(&id)
+        .and_then(|chat| chat.risk_weights.clone())
+        .unwrap_or_else(|| cfg.risk_weights.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(signals: &RiskSignals) -> u32 {
+        risk_score(signals, &RiskWeights::default()).unwrap().score
+    }
+
+    #[test]
+    fn no_known_signals() {
+        assert!(risk_score(&RiskSignals::default(), &RiskWeights::default()).is_none());
+
+        let weights = RiskWeights {
+            taxes: 0,
+            ..RiskWeights::default()
+        };
+        let signals = RiskSignals {
+            max_tax: Some(dec!(10)),
+            ..RiskSignals::default()
+        };
+        assert!(risk_score(&signals, &weights).is_none());
+    }
+
+    #[test]
+    fn honeypot_overrides_the_signals() {
+        let signals = RiskSignals {
+            is_honeypot: Some(true),
+            lp_locked: Some(dec!(100)),
+            authorities_renounced: Some(true),
+            ..RiskSignals::default()
+        };
+        let risk = risk_score(&signals, &RiskWeights::default()).unwrap();
+        assert_eq!(risk.score, 100);
+        assert!(risk.is_honeypot);
+        assert_eq!(risk.describe(), "Risk 100/100 (honeypot)");
+
+        let signals = RiskSignals {
+            is_honeypot: Some(false),
+            lp_locked: Some(dec!(100)),
+            ..RiskSignals::default()
+        };
+        let risk = risk_score(&signals, &RiskWeights::default()).unwrap();
+        assert!(!risk.is_honeypot);
+        assert_eq!(risk.describe(), "Risk 0/100 (LP 0)");
+    }
+
+    #[test]
+    fn lp_locked() {
+        let lp = |locked| RiskSignals {
+            lp_locked: Some(locked),
+            ..RiskSignals::default()
+        };
+        assert_eq!(score(&lp(dec!(100))), 0);
+        assert_eq!(score(&lp(dec!(50))), 50);
+        assert_eq!(score(&lp(dec!(0))), 100);
+    }
+
+    #[test]
+    fn authorities() {
+        let authorities = |renounced| RiskSignals {
+            authorities_renounced: Some(renounced),
+            ..RiskSignals::default()
+        };
+        assert_eq!(score(&authorities(true)), 0);
+        assert_eq!(score(&authorities(false)), 100);
+    }
+
+    #[test]
+    fn top_holders() {
+        let holders = |share| RiskSignals {
+            top_holders: Some(share),
+            ..RiskSignals::default()
+        };
+        assert_eq!(score(&holders(dec!(5))), 0);
+        assert_eq!(score(&holders(HOLDERS_SAFE_PCT)), 0);
+        assert_eq!(score(&holders(dec!(37.5))), 50);
+        assert_eq!(score(&holders(HOLDERS_RISKY_PCT)), 100);
+        assert_eq!(score(&holders(dec!(95))), 100);
+    }
+
+    #[test]
+    fn taxes() {
+        let tax = |tax| RiskSignals {
+            max_tax: Some(tax),
+            ..RiskSignals::default()
+        };
+        assert_eq!(score(&tax(dec!(0))), 0);
+        assert_eq!(score(&tax(dec!(5))), 25);
+        assert_eq!(score(&tax(TAX_RISKY_PCT)), 100);
+        assert_eq!(score(&tax(dec!(99))), 100);
+    }
+
+    #[test]
+    fn socials() {
+        let socials = |has_socials| RiskSignals {
+            has_socials: Some(has_socials),
+            ..RiskSignals::default()
+        };
+        assert_eq!(score(&socials(true)), 0);
+        assert_eq!(score(&socials(false)), 100);
+    }
+
+    #[test]
+    fn age() {
+        let age = |hours| RiskSignals {
+            age: Some(TimeDelta::hours(hours)),
+            ..RiskSignals::default()
+        };
+        assert_eq!(score(&age(0)), 100);
+        assert_eq!(score(&age(AGE_RISKY_HOURS / 2)), 50);
+        assert_eq!(score(&age(AGE_RISKY_HOURS)), 0);
+        assert_eq!(score(&age(365 * 24)), 0);
+    }
+
+    #[test]
+    fn weighted_breakdown() {
+        let signals = RiskSignals {
+            is_honeypot: Some(false),
+            lp_locked: Some(dec!(0)),
+            authorities_renounced: Some(true),
+            top_holders: Some(HOLDERS_RISKY_PCT),
+            max_tax: Some(dec!(10)),
+            has_socials: Some(true),
+            age: Some(TimeDelta::hours(AGE_RISKY_HOURS)),
+        };
+        let risk = risk_score(&signals, &RiskWeights::default()).unwrap();
+        assert_eq!(
+            risk.breakdown,
+            [
+                ("LP", 25),
+                ("auth", 0),
+                ("holders", 20),
+                ("tax", 8),
+                ("socials", 0),
+                ("age", 0)
+            ]
+        );
+        assert_eq!(risk.score, 53);
+        assert_eq!(
+            risk.describe(),
+            "Risk 53/100 (LP 25 · auth 0 · holders 20 · tax 8 · socials 0 · age 0)"
+        );
+    }
+
+    #[test]
+    fn unknown_signals_are_left_out() {
+        let signals = RiskSignals {
+            lp_locked: Some(dec!(0)),
+            authorities_renounced: Some(true),
+            ..RiskSignals::default()
+        };
+        let risk = risk_score(&signals, &RiskWeights::default()).unwrap();
+        // LP weighs 25 of the known 45
+        assert_eq!(risk.breakdown, [("LP", 56), ("auth", 0)]);
+        assert_eq!(risk.score, 56);
+    }
+
+    #[test]
+    fn custom_weights() {
+        let weights = RiskWeights {
+            lp: 0,
+            authorities: 1,
+            holders: 3,
+            ..RiskWeights::default()
+        };
+        let signals = RiskSignals {
+            lp_locked: Some(dec!(0)),
+            authorities_renounced: Some(false),
+            top_holders: Some(HOLDERS_SAFE_PCT),
+            ..RiskSignals::default()
+        };
+        let risk = risk_score(&signals, &weights).unwrap();
+        assert_eq!(risk.breakdown, [("LP", 0), ("auth", 25), ("holders", 0)]);
+        assert_eq!(risk.score, 25);
+    }
+
+    #[test]
+    fn score_is_clamped_to_100() {
+        let weights = RiskWeights {
+            lp: 1,
+            authorities: 1,
+            holders: 1,
+            taxes: 1,
+            socials: 1,
+            age: 1,
+        };
+        let signals = RiskSignals {
+            is_honeypot: Some(false),
+            lp_locked: Some(dec!(0)),
+            authorities_renounced: Some(false),
+            top_holders: Some(dec!(100)),
+            max_tax: Some(dec!(100)),
+            has_socials: Some(false),
+            age: Some(TimeDelta::zero()),
+        };
+        let risk = risk_score(&signals, &weights).unwrap();
+        // every signal rounds 16.67 up to 17
+        assert!(risk.breakdown.iter().all(|(_, points)| *points == 17));
+        assert_eq!(risk.score, 100);
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use log::debug;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::APP_CONFIG;
+use crate::token_info::Chain;
+
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+const DEAD_ADDRESS: &str = "0x000000000000000000000000000000000000dead";
+
+#[derive(Debug, Deserialize)]
+struct GoPlusResponse {
+    code: i64,
+    message: String,
+    #[serde(default)]
+    result: HashMap<String, GoPlusTokenSecurity>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoPlusLpHolder {
+    #[serde(default)]
+    is_locked: i64,
+    #[serde(default)]
+    percent: Option<Decimal>,
+}
+
+// GoPlus returns most of the flags as "0"/"1" strings and
+// omits them completely when they couldn't be determined
+#[derive(Debug, Deserialize)]
+struct GoPlusTokenSecurity {
+    is_honeypot: Option<String>,
+    buy_tax: Option<String>,
+    sell_tax: Option<String>,
+    owner_address: Option<String>,
+    #[serde(default)]
+    lp_holders: Vec<GoPlusLpHolder>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenSecurity {
+    pub is_honeypot: Option<bool>,
+    /// Buy tax in percents
+    pub buy_tax: Option<Decimal>,
+    /// Sell tax in percents
+    pub sell_tax: Option<Decimal>,
+    pub ownership_renounced: Option<bool>,
+    /// Share of the LP tokens that are locked, in percents
+    pub lp_locked: Option<Decimal>,
+}
+
+fn parse_flag(value: &Option<String>) -> Option<bool> {
+    match value.as_deref() {
+        Some("1") => Some(true),
+        Some("0") => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_percent(value: &Option<String>) -> Option<Decimal> {
+    value
+        .as_deref()
+        .filter(|v| !v.is_empty())
+        .and_then(|v| v.parse::<Decimal>().ok())
+        .map(|v| v * Decimal::ONE_HUNDRED)
+}
+
+fn format_percent(value: Option<Decimal>) -> String {
+    match value {
+        Some(value) => format!("{}%", value.round_dp(1).normalize()),
+        None => "?".to_owned(),
+    }
+}
+
+impl From<GoPlusTokenSecurity> for TokenSecurity {
+    fn from(info: GoPlusTokenSecurity) -> Self {
+        let ownership_renounced = info.owner_address.as_deref().map(|owner| {
+            let owner = owner.to_lowercase();
+            owner.is_empty() || owner == ZERO_ADDRESS || owner == DEAD_ADDRESS
+        });
+
+        let lp_locked = if info.lp_holders.is_empty() {
+            None
+        } else {
+            let locked: Decimal = info
+                .lp_holders
+                .iter()
+                .filter(|holder| holder.is_locked == 1)
+                .filter_map(|holder| holder.percent)
+                .sum();
+            Some(locked * Decimal::ONE_HUNDRED)
+        };
+
+        Self {
+            is_honeypot: parse_flag(&info.is_honeypot),
+            buy_tax: parse_percent(&info.buy_tax),
+            sell_tax: parse_percent(&info.sell_tax),
+            ownership_renounced,
+            lp_locked,
+        }
+    }
+}
+
+impl TokenSecurity {
+    /// Short single line summary of the token risks, not escaped
+    pub fn risk_line(&self) -> String {
+        let honeypot = match self.is_honeypot {
+            Some(true) => "🚫 HONEYPOT",
+            Some(false) => "✅ not honeypot",
+            None => "❔ honeypot unknown",
+        };
+        let ownership = match self.ownership_renounced {
+            Some(true) => "renounced",
+            Some(false) => "not renounced",
+            None => "?",
+        };
+
+        format!(
+            "{honeypot} · Tax {}/{} · Owner {ownership} · LP locked {}",
+            format_percent(self.buy_tax),
+            format_percent(self.sell_tax),
+            format_percent(self.lp_locked),
+        )
+    }
+}
+
+pub async fn retrieve_evm_token_security(
+    token_ca: &str,
+    chain: Chain,
+    client: reqwest::Client,
+) -> anyhow::Result<TokenSecurity> {
+    let chain_id = match chain {
+        Chain::Bsc => "56",
+        Chain::Base => "8453",
+        Chain::Arbitrum => "42161",
+        Chain::Polygon => "137",
+    };
+
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+    let url = format!(
+        "{}/api/v1/token_security/{chain_id}",
+        app_config.provider_urls.goplus
+    );
+    debug!("Going to hit url - {url}");
+
+    let response = client
+        .get(url)
+        .query(&[("contract_addresses", token_ca)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<GoPlusResponse>()
+        .await?;
+
+    if response.code != 1 {
+        return Err(anyhow!(
+            "GoPlus returned error code {} for {token_ca} - {}",
+            response.code,
+            response.message
+        ));
+    }
+
+    response
+        .result
+        .into_iter()
+        .find(|(address, _)| address.eq_ignore_ascii_case(token_ca))
+        .map(|(_, info)| info.into())
+        .ok_or(anyhow!("Token CA {token_ca} not found on GoPlus"))
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+
+use chrono::Duration;
+use log::{debug, info, warn};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use sqlx::SqlitePool;
+use teloxide::Bot;
+use teloxide::payloads::{
+    AnswerCallbackQuerySetters, EditMessageReplyMarkupSetters, SendMessageSetters,
+};
+use teloxide::prelude::{Requester, ResponseResult};
+use teloxide::types::{CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Message};
+
+use crate::aliases::{is_chat_admin, is_chat_admin_user};
+use crate::config::ChatConfig;
+use crate::storage::{load_chat_settings, save_chat_settings};
+use crate::token_info::format_human_readable;
+use crate::{ALLOWED_THROTTLING, APP_CONFIG};
+
+pub const SETTINGS_CALLBACK_PREFIX: &str = "settings:";
+
+/// Cooldown choices in minutes, the longest one must not exceed `MAX_THROTTLING`
+const THROTTLE_OPTIONS: [i64; 5] = [1, 5, 15, 30, 60];
+
+const MIN_MCAP_OPTIONS: [Option<f64>; 5] = [
+    None,
+    Some(10_000.0),
+    Some(50_000.0),
+    Some(100_000.0),
+    Some(1_000_000.0),
+];
+
+/// Auto-delete choices in minutes
+const AUTO_DELETE_OPTIONS: [Option<i64>; 4] = [None, Some(1), Some(10), Some(60)];
+
+/// Options chat admins pick via /settings, the chat config is used for the missing ones
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatSettings {
+    /// Cooldown before the same token is carded again
+    pub throttle_mins: Option<i64>,
+    /// Name of one of the `link_sets` of the config
+    pub link_set: Option<String>,
+    /// Replaces `min_mcap` of the chat config
+    pub min_mcap: Option<f64>,
+    /// Replies are deleted this long after they are sent
+    pub auto_delete_mins: Option<i64>,
+    /// Replies are sent without a notification
+    pub silent: bool,
+    /// Mentions are recorded for the stats, but the bot never replies
+    pub observe_only: bool,
+    /// CAs of the message being replied to are carded as well
+    pub scan_replies: bool,
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        Self {
+            throttle_mins: None,
+            link_set: None,
+            min_mcap: None,
+            auto_delete_mins: None,
+            silent: true,
+            observe_only: false,
+            scan_replies: false,
+        }
+    }
+}
+
+impl ChatSettings {
+    pub fn throttle_window(&self) -> Duration {
+        self.throttle_mins
+            .map(Duration::minutes)
+            .unwrap_or(ALLOWED_THROTTLING)
+    }
+
+    pub fn auto_delete_after(&self) -> Option<std::time::Duration> {
+        self.auto_delete_mins
+            .and_then(|mins| u64::try_from(mins).ok())
+            .map(|mins| std::time::Duration::from_secs(mins * 60))
+    }
+
+    /// Tokens with unknown mcap are always carded
+    pub fn allows_mcap(&self, mcap: Option<Decimal>, chat: Option<&ChatConfig>) -> bool {
+        let Some(mcap) = mcap else {
+            return true;
+        };
+
+        let above_min = self
+            .min_mcap
+            .or(chat.and_then(|chat| chat.min_mcap))
+            .and_then(Decimal::from_f64)
+            .is_none_or(|min_mcap| mcap >= min_mcap);
+        let below_max = chat
+            .and_then(|chat| chat.max_mcap)
+            .and_then(Decimal::from_f64)
+            .is_none_or(|max_mcap| mcap <= max_mcap);
+
+        above_min && below_max
+    }
+}
+
+static SETTINGS: LazyLock<RwLock<HashMap<ChatId, ChatSettings>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Loads the settings of all chats, they are served from memory afterwards
+pub async fn init_chat_settings(storage: &SqlitePool) {
+    match load_chat_settings(storage).await {
+        Ok(settings) => {
+            info!("Loaded settings of {} chats", settings.len());
+            SETTINGS.write().unwrap().extend(settings);
+        }
+        Err(e) => warn!("Failed to load chat settings - {e:?}"),
+    }
+}
+
+pub fn chat_settings(chat_id: ChatId) -> ChatSettings {
+    SETTINGS
+        .read()
+        .unwrap()
+        .get(&chat_id)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Drops the settings of a chat whose data was deleted
+pub fn forget_chat_settings(chat_id: ChatId) {
+    SETTINGS.write().unwrap().remove(&chat_id);
+}
+
+fn next_option<T: PartialEq + Clone>(options: &[T], current: &T) -> T {
+    let next = options
+        .iter()
+        .position(|option| option == current)
+        .map_or(0, |i| (i + 1) % options.len());
+    options[next].clone()
+}
+
+fn link_set_options() -> Vec<Option<String>> {
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+    let mut names: Vec<&String> = app_config.link_sets.keys().collect();
+    names.sort();
+
+    std::iter::once(None)
+        .chain(names.into_iter().cloned().map(Some))
+        .collect()
+}
+
+fn button(text: String, option: &str) -> Vec<InlineKeyboardButton> {
+    vec![InlineKeyboardButton::callback(
+        text,
+        format!("{SETTINGS_CALLBACK_PREFIX}{option}"),
+    )]
+}
+
+fn settings_keyboard(settings: &ChatSettings) -> InlineKeyboardMarkup {
+    let min_mcap = settings
+        .min_mcap
+        .and_then(Decimal::from_f64)
+        .map_or("off".to_owned(), |mcap| format_human_readable(mcap, 0));
+    let auto_delete = settings
+        .auto_delete_mins
+        .map_or("off".to_owned(), |mins| format!("{mins} min"));
+
+    let mut rows = vec![
+        button(
+            format!(
+                "⏱ Cooldown: {} min",
+                settings.throttle_window().num_minutes()
+            ),
+            "throttle",
+        ),
+        button(format!("💵 Min mcap: {min_mcap}"), "min_mcap"),
+        button(format!("🗑 Auto-delete: {auto_delete}"), "auto_delete"),
+        button(
+            if settings.silent {
+                "🔕 Silent replies: on".to_owned()
+            } else {
+                "🔔 Silent replies: off".to_owned()
+            },
+            "silent",
+        ),
+        button(
+            if settings.observe_only {
+                "👀 Observe only: on".to_owned()
+            } else {
+                "👀 Observe only: off".to_owned()
+            },
+            "observe_only",
+        ),
+        button(
+            if settings.scan_replies {
+                "↩️ Scan replied messages: on".to_owned()
+            } else {
+                "↩️ Scan replied messages: off".to_owned()
+            },
+            "scan_replies",
+        ),
+    ];
+
+    // there is nothing to choose from unless link sets are configured
+    if link_set_options().len() > 1 {
+        let link_set = settings.link_set.as_deref().unwrap_or("default");
+        rows.insert(1, button(format!("🔗 Links: {link_set}"), "link_set"));
+    }
+
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// Handles `/settings`, only chat admins may open the menu
+pub async fn handle_settings(bot: &Bot, message: &Message) {
+    if !is_chat_admin(bot, message).await {
+        debug!("Skipping /settings from a non-admin user");
+        return;
+    }
+
+    let settings = chat_settings(message.chat.id);
+    if let Err(e) = bot
+        .send_message(message.chat.id, "Settings of this chat, tap to change:")
+        .reply_markup(settings_keyboard(&settings))
+        .await
+    {
+        warn!("Failed to reply to /settings - {e:?}");
+    }
+}
+
+/// Cycles the tapped option to its next value and saves it
+pub async fn handle_settings_action(
+    bot: &Bot,
+    query: &CallbackQuery,
+    storage: &SqlitePool,
+) -> ResponseResult<()> {
+    let Some(menu) = query.regular_message() else {
+        debug!("Settings menu is inaccessible - skipping it");
+        bot.answer_callback_query(query.id.clone()).await?;
+        return Ok(());
+    };
+
+    if !is_chat_admin_user(bot, &menu.chat, &query.from).await {
+        bot.answer_callback_query(query.id.clone())
+            .text("Only chat admins can change the settings")
+            .await?;
+        return Ok(());
+    }
+
+    let chat_id = menu.chat.id;
+    let mut settings = chat_settings(chat_id);
+    let option = query
+        .data
+        .as_deref()
+        .and_then(|data| data.strip_prefix(SETTINGS_CALLBACK_PREFIX));
+    match option {
+        Some("throttle") => {
+            let current = settings.throttle_window().num_minutes();
+            settings.throttle_mins = Some(next_option(&THROTTLE_OPTIONS, &current));
+        }
+        Some("link_set") => {
+            settings.link_set = next_option(&link_set_options(), &settings.link_set);
+        }
+        Some("min_mcap") => settings.min_mcap = next_option(&MIN_MCAP_OPTIONS, &settings.min_mcap),
+        Some("auto_delete") => {
+            settings.auto_delete_mins =
+                next_option(&AUTO_DELETE_OPTIONS, &settings.auto_delete_mins);
+        }
+        Some("silent") => settings.silent = !settings.silent,
+        Some("observe_only") => settings.observe_only = !settings.observe_only,
+        Some("scan_replies") => settings.scan_replies = !settings.scan_replies,
+        _ => {
+            warn!("Unknown settings option {:?}", query.data);
+            bot.answer_callback_query(query.id.clone()).await?;
+            return Ok(());
+        }
+    }
+
+    if let Err(e) = save_chat_settings(storage, chat_id, &settings).await {
+        warn!("Failed to save settings of {chat_id} - {e:?}");
+        bot.answer_callback_query(query.id.clone())
+            .text("Failed to save the settings, try again later")
+            .await?;
+        return Ok(());
+    }
+
+    info!(
+        "Settings of {chat_id} changed by {} - {settings:?}",
+        query.from.id
+    );
+    SETTINGS.write().unwrap().insert(chat_id, settings.clone());
+
+    bot.answer_callback_query(query.id.clone()).await?;
+    if let Err(e) = bot
+        .edit_message_reply_markup(chat_id, menu.id)
+        .reply_markup(settings_keyboard(&settings))
+        .await
+    {
+        warn!("Failed to update settings menu in {chat_id} - {e:?}");
+    }
+
+    Ok(())
+}
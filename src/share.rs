@@ -0,0 +1,73 @@
+use log::{debug, warn};
+use teloxide::Bot;
+use teloxide::payloads::{AnswerCallbackQuerySetters, SendMessageSetters};
+use teloxide::prelude::{Requester, ResponseResult};
+use teloxide::types::{CallbackQuery, ChatId, InlineKeyboardButton, LinkPreviewOptions};
+
+use crate::cache::caches;
+use crate::delivery::has_private_chat;
+
+pub const SHARE_CALLBACK_DATA: &str = "share";
+
+pub fn share_button() -> InlineKeyboardButton {
+    InlineKeyboardButton::callback("📤 Share", SHARE_CALLBACK_DATA)
+}
+
+/// Sends the card to the DM of the user who clicked "Share" - without the reply
+/// context and buttons, but with the chart preview, so it can be forwarded as is
+pub async fn handle_share(bot: &Bot, query: &CallbackQuery) -> ResponseResult<()> {
+    let Some(card) = query.regular_message() else {
+        debug!("Shared card is inaccessible - skipping it");
+        bot.answer_callback_query(query.id.clone()).await?;
+        return Ok(());
+    };
+
+    if !has_private_chat(query.from.id).await {
+        bot.answer_callback_query(query.id.clone())
+            .text("Start a private chat with me first, then tap Share again")
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    }
+
+    bot.answer_callback_query(query.id.clone()).await?;
+
+    let Some(text) = card.text() else {
+        debug!("Shared card {} has no text - skipping it", card.id);
+        return Ok(());
+    };
+
+    let chart_url = caches()
+        .cards
+        .get(&(card.chat.id, card.id))
+        .await
+        .and_then(|tokens| tokens.first().map(|token| token.chart_url()));
+
+    let mut text = text.to_owned();
+    if let Some(chart_url) = &chart_url {
+        // appending keeps the offsets of the existing entities valid
+        text.push_str(&format!("\n\n📈 {chart_url}"));
+    }
+
+    let mut request = bot
+        .send_message(ChatId::from(query.from.id), text)
+        .link_preview_options(LinkPreviewOptions {
+            is_disabled: chart_url.is_none(),
+            url: chart_url,
+            prefer_small_media: false,
+            prefer_large_media: true,
+            show_above_text: false,
+        });
+    if let Some(entities) = card.entities() {
+        request = request.entities(entities.to_vec());
+    }
+
+    if let Err(e) = request.await {
+        warn!(
+            "Failed to share card {} with {} - {e:?}",
+            card.id, query.from.id
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::Mutex;
+
+use anyhow::anyhow;
+use log::debug;
+use tokio::sync::broadcast;
+
+/// Coalesces concurrent calls for the same key into a single call,
+/// the callers arriving while it runs get a copy of its result
+pub struct SingleFlight<K, V, E> {
+    in_flight: Mutex<HashMap<K, broadcast::Sender<Result<V, E>>>>,
+}
+
+/// Removes the in-flight entry even if the leading call gets cancelled,
+/// so the waiting callers don't hang
+struct InFlightGuard<'a, K: Hash + Eq, V, E> {
+    flight: &'a SingleFlight<K, V, E>,
+    key: &'a K,
+    /// Set once the entry was removed by the leader, the key may belong
+    /// to the next leader by the time the guard is dropped
+    finished: bool,
+}
+
+impl<K: Hash + Eq, V, E> InFlightGuard<'_, K, V, E> {
+    /// Takes the entry out for the leader to send the result
+    fn finish(mut self) -> Option<broadcast::Sender<Result<V, E>>> {
+        self.finished = true;
+        self.flight.in_flight.lock().unwrap().remove(self.key)
+    }
+}
+
+impl<K: Hash + Eq, V, E> Drop for InFlightGuard<'_, K, V, E> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.flight.in_flight.lock().unwrap().remove(self.key);
+        }
+    }
+}
+
+impl<K, V, E> Default for SingleFlight<K, V, E> {
+    fn default() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K, V, E> SingleFlight<K, V, E>
+where
+    K: Hash + Eq + Clone + std::fmt::Debug,
+    V: Clone,
+    E: Clone + From<anyhow::Error>,
+{
+    pub async fn run<F>(&self, key: K, call: F) -> Result<V, E>
+    where
+        F: Future<Output = Result<V, E>>,
+    {
+        let waiter = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(&key) {
+                Some(sender) => Some(sender.subscribe()),
+                None => {
+                    in_flight.insert(key.clone(), broadcast::channel(1).0);
+                    None
+                }
+            }
+        };
+
+        if let Some(mut waiter) = waiter {
+            debug!("Joining in-flight call for {key:?}");
+            return match waiter.recv().await {
+                Ok(result) => result,
+                Err(_) => Err(anyhow!("In-flight call for {key:?} was cancelled").into()),
+            };
+        }
+
+        let guard = InFlightGuard {
+            flight: self,
+            key: &key,
+            finished: false,
+        };
+        let result = call.await;
+
+        if let Some(sender) = guard.finish() {
+            // nobody waiting is fine
+            let _ = sender.send(result.clone());
+        }
+
+        result
+    }
+}
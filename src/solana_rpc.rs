@@ -0,0 +1,124 @@
+use log::debug;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::APP_CONFIG;
+use crate::retry::send_with_retry;
+
+const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const TOKEN_2022_PROGRAM: &str = "TokenzQdBNbLqP7VEo6kn2LdvLCr7Bu7HzuZcpuZzwv";
+
+const LAMPORTS_PER_SOL: u32 = 1_000_000_000;
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    result: RpcResult<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResult<T> {
+    value: T,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountInfo {
+    owner: String,
+    data: serde_json::Value,
+}
+
+impl AccountInfo {
+    /// Token accounts are owned by the token programs as well, only the parsed type tells them apart
+    fn is_mint(&self) -> bool {
+        [TOKEN_PROGRAM, TOKEN_2022_PROGRAM].contains(&self.owner.as_str())
+            && self.data.pointer("/parsed/type").and_then(|t| t.as_str()) == Some("mint")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenAccount {
+    account: AccountInfo,
+}
+
+impl TokenAccount {
+    fn is_empty(&self) -> bool {
+        self.account
+            .data
+            .pointer("/parsed/info/tokenAmount/amount")
+            .and_then(|amount| amount.as_str())
+            .is_none_or(|amount| amount == "0")
+    }
+}
+
+async fn call<T: DeserializeOwned>(
+    method: &str,
+    params: serde_json::Value,
+    client: reqwest::Client,
+) -> anyhow::Result<T> {
+    let url = APP_CONFIG
+        .get()
+        .unwrap()
+        .app_config()
+        .provider_urls
+        .solana_rpc
+        .clone();
+    debug!("Going to call {method} via {url}");
+
+    let request = client.post(url).json(&json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    }));
+    let response = send_with_retry(request)
+        .await?
+        .error_for_status()?
+        .json::<RpcResponse<T>>()
+        .await?;
+
+    Ok(response.result.value)
+}
+
+/// Asks the Solana RPC whether the address is a mint of the Token or Token-2022 program,
+/// as opposed to a wallet or any other account
+pub async fn is_token_mint(address: &str, client: reqwest::Client) -> anyhow::Result<bool> {
+    // the account is missing for addresses which hold nothing, e.g. fresh wallets
+    let account: Option<AccountInfo> = call(
+        "getAccountInfo",
+        json!([address, { "encoding": "jsonParsed" }]),
+        client,
+    )
+    .await?;
+
+    Ok(account.is_some_and(|account| account.is_mint()))
+}
+
+/// SOL balance of the wallet
+pub async fn retrieve_sol_balance(
+    address: &str,
+    client: reqwest::Client,
+) -> anyhow::Result<Decimal> {
+    let lamports: u64 = call("getBalance", json!([address]), client).await?;
+
+    Ok(Decimal::from(lamports) / Decimal::from(LAMPORTS_PER_SOL))
+}
+
+/// Number of the tokens the wallet holds a non-zero amount of, of both token programs
+pub async fn count_held_tokens(address: &str, client: reqwest::Client) -> anyhow::Result<usize> {
+    let mut count = 0;
+    for program in [TOKEN_PROGRAM, TOKEN_2022_PROGRAM] {
+        let accounts: Vec<TokenAccount> = call(
+            "getTokenAccountsByOwner",
+            json!([address, { "programId": program }, { "encoding": "jsonParsed" }]),
+            client.clone(),
+        )
+        .await?;
+        count += accounts
+            .iter()
+            .filter(|account| !account.is_empty())
+            .count();
+    }
+
+    Ok(count)
+}
@@ -0,0 +1,206 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use log::{debug, info, warn};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use teloxide::Bot;
+use teloxide::prelude::Requester;
+use teloxide::types::Message;
+
+use crate::APP_CONFIG;
+use crate::dexscreener::retrieve_token_pairs;
+use crate::storage::{load_chat_calls, load_mentioned_tokens, update_mention_mcap};
+use crate::tags::category_breakdown;
+
+/// DexScreener accepts up to 30 addresses per request
+const MAX_TOKENS_PER_REQUEST: usize = 30;
+
+/// `/stats` leaderboard of the callers of the tokens first mentioned within `window_days`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StatsConfig {
+    pub window_days: u64,
+    pub leaderboard_size: usize,
+    /// How often the mcaps of the mentioned tokens are refreshed
+    pub refresh_mins: u64,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            window_days: 7,
+            leaderboard_size: 10,
+            refresh_mins: 30,
+        }
+    }
+}
+
+fn window_start(window_days: u64) -> DateTime<Utc> {
+    let window = i64::try_from(window_days)
+        .ok()
+        .and_then(TimeDelta::try_days)
+        .unwrap_or(TimeDelta::MAX);
+    Utc::now()
+        .checked_sub_signed(window)
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
+/// Updates the latest mcap of the tokens mentioned within the stats window
+async fn refresh_mention_mcaps(storage: &SqlitePool, client: reqwest::Client) {
+    let window_days = APP_CONFIG.get().unwrap().app_config().stats.window_days;
+    let tokens = match load_mentioned_tokens(storage, window_start(window_days)).await {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            warn!("Failed to load mentioned tokens - {e:?}");
+            return;
+        }
+    };
+
+    let mut by_chain: HashMap<String, Vec<String>> = HashMap::new();
+    for (chain_id, token_ca) in tokens {
+        by_chain.entry(chain_id).or_default().push(token_ca);
+    }
+
+    let mut refreshed = 0;
+    for (chain_id, token_cas) in by_chain {
+        for chunk in token_cas.chunks(MAX_TOKENS_PER_REQUEST) {
+            let token_cas: Vec<&str> = chunk.iter().map(String::as_str).collect();
+            let pairs = match retrieve_token_pairs(&chain_id, &token_cas, client.clone()).await {
+                Ok(pairs) => pairs,
+                Err(e) => {
+                    warn!("Failed to retrieve mcaps of {chain_id} mentions - {e:?}");
+                    continue;
+                }
+            };
+
+            for pair in pairs {
+                let Some(mcap) = pair.market_cap.or(pair.fdv) else {
+                    continue;
+                };
+                // DexScreener may return EVM addresses checksummed differently
+                let Some(token_ca) = chunk
+                    .iter()
+                    .find(|ca| ca.eq_ignore_ascii_case(&pair.base_token.address))
+                else {
+                    continue;
+                };
+
+                match update_mention_mcap(storage, &chain_id, token_ca, mcap).await {
+                    Ok(()) => refreshed += 1,
+                    Err(e) => warn!("Failed to update mcap of {token_ca} mentions - {e:?}"),
+                }
+            }
+        }
+    }
+
+    debug!("Refreshed mcaps of {refreshed} mentioned tokens");
+}
+
+/// Keeps the mcaps used by the `/stats` leaderboard up to date
+pub fn spawn_mention_refresher(storage: SqlitePool, client: reqwest::Client) {
+    tokio::spawn(async move {
+        loop {
+            let refresh_mins = APP_CONFIG.get().unwrap().app_config().stats.refresh_mins;
+            tokio::time::sleep(Duration::from_secs(refresh_mins.max(1) * 60)).await;
+
+            refresh_mention_mcaps(&storage, client.clone()).await;
+        }
+    });
+}
+
+/// Best call of a caller within the window
+struct CallerStats {
+    caller: String,
+    best_symbol: String,
+    best_multiple: Decimal,
+    calls: usize,
+}
+
+/// Handles `/stats`
+pub async fn handle_stats(bot: &Bot, message: &Message, storage: &SqlitePool) {
+    let text = match leaderboard(message, storage).await {
+        Ok(text) => text,
+        Err(e) => {
+            warn!("Failed to build the leaderboard - {e:?}");
+            "Failed to build the leaderboard".to_owned()
+        }
+    };
+
+    if let Err(e) = bot.send_message(message.chat.id, text).await {
+        warn!("Failed to reply to /stats - {e:?}");
+    }
+}
+
+async fn leaderboard(message: &Message, storage: &SqlitePool) -> anyhow::Result<String> {
+    let config = APP_CONFIG.get().unwrap().app_config().stats.clone();
+    let since = window_start(config.window_days);
+    let calls = load_chat_calls(storage, message.chat.id, since).await?;
+
+    let mut callers: HashMap<String, CallerStats> = HashMap::new();
+    for (caller, symbol, mcap, latest_mcap) in calls {
+        let mcap = mcap.and_then(|mcap| mcap.parse::<Decimal>().ok());
+        let latest_mcap = latest_mcap.and_then(|mcap| mcap.parse::<Decimal>().ok());
+        let (Some(mcap), Some(latest_mcap)) = (mcap, latest_mcap) else {
+            continue;
+        };
+        if mcap <= Decimal::ZERO {
+            continue;
+        }
+
+        let multiple = latest_mcap / mcap;
+        let stats = callers
+            .entry(caller.clone())
+            .or_insert_with(|| CallerStats {
+                caller,
+                best_symbol: symbol.clone(),
+                best_multiple: multiple,
+                calls: 0,
+            });
+        stats.calls += 1;
+        if multiple > stats.best_multiple {
+            stats.best_multiple = multiple;
+            stats.best_symbol = symbol;
+        }
+    }
+
+    if callers.is_empty() {
+        return Ok(format!("No calls in the last {}d yet", config.window_days));
+    }
+
+    let mut callers: Vec<CallerStats> = callers.into_values().collect();
+    callers.sort_by_key(|stats| Reverse(stats.best_multiple));
+    callers.truncate(config.leaderboard_size.max(1));
+
+    let rows = callers
+        .iter()
+        .enumerate()
+        .map(|(i, stats)| {
+            format!(
+                "{}. {} - {}x on ${} ({} calls)",
+                i + 1,
+                stats.caller,
+                stats.best_multiple.round_dp(1).normalize(),
+                stats.best_symbol,
+                stats.calls
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    info!(
+        "Built the leaderboard of {} callers for {}",
+        callers.len(),
+        message.chat.id
+    );
+
+    let mut text = format!("Best callers of the last {}d:\n{rows}", config.window_days);
+    if let Some(categories) = category_breakdown(storage, message.chat.id, since).await {
+        text.push_str(&format!("\n\n{categories}"));
+    }
+
+    Ok(text)
+}
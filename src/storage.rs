@@ -0,0 +1,1018 @@
+use std::borrow::Cow;
+
+use chrono::{DateTime, Utc};
+use log::info;
+use rust_decimal::Decimal;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use teloxide::types::{ChatId, MessageId, ThreadId, UserId};
+
+use crate::ThrottlingInfo;
+use crate::cache::{CachedTokenInfo, TokenInfoKey};
+use crate::delivery::AlertDestination;
+use crate::dexscreener::dexscreener_chain_id;
+use crate::followups::CardToken;
+use crate::mentions::FirstMention;
+use crate::outbox::{CardReply, OutboxEntry};
+use crate::settings::ChatSettings;
+use crate::token_lists::ListedToken;
+use crate::watchlist::Watch;
+
+pub async fn open_storage(path: &str) -> anyhow::Result<SqlitePool> {
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true);
+    let pool = SqlitePool::connect_with(options).await?;
+
+    // the initial migration only has IF NOT EXISTS statements,
+    // so databases created before the migrations were introduced are adopted as is
+    sqlx::migrate!().run(&pool).await?;
+
+    Ok(pool)
+}
+
+/// Loads the throttle entries persisted on the previous shutdown which are newer than `since`
+pub async fn load_throttling_info(
+    pool: &SqlitePool,
+    since: DateTime<Utc>,
+) -> anyhow::Result<ThrottlingInfo> {
+    let rows = sqlx::query_as::<_, (String, i64, Option<i32>, i64)>(
+        "SELECT token_ca, chat_id, thread_id, sent_at FROM throttle WHERE sent_at > ?",
+    )
+    .bind(since.timestamp())
+    .fetch_all(pool)
+    .await?;
+
+    let mut throttling_info = ThrottlingInfo::with_capacity(rows.len());
+    for (token_ca, chat_id, thread_id, sent_at) in rows {
+        let Some(sent_at) = DateTime::from_timestamp(sent_at, 0) else {
+            continue;
+        };
+        throttling_info.insert(
+            (
+                Cow::Owned(token_ca),
+                ChatId(chat_id),
+                thread_id.map(|id| ThreadId(MessageId(id))),
+            ),
+            sent_at,
+        );
+    }
+
+    info!(
+        "Loaded {} throttle entries from storage",
+        throttling_info.len()
+    );
+
+    Ok(throttling_info)
+}
+
+/// Replaces the persisted throttle entries with the current ones
+pub async fn save_throttling_info(
+    pool: &SqlitePool,
+    throttling_info: &ThrottlingInfo,
+) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM throttle")
+        .execute(&mut *tx)
+        .await?;
+
+    for ((token_ca, ChatId(chat_id), thread_id), sent_at) in throttling_info {
+        sqlx::query(
+            "INSERT INTO throttle (token_ca, chat_id, thread_id, sent_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(token_ca.as_ref())
+        .bind(chat_id)
+        .bind(thread_id.map(|ThreadId(MessageId(id))| id))
+        .bind(sent_at.timestamp())
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    info!(
+        "Saved {} throttle entries to storage",
+        throttling_info.len()
+    );
+
+    Ok(())
+}
+
+/// Replaces the persisted token infos with the current cache entries
+pub async fn save_token_infos(
+    pool: &SqlitePool,
+    token_infos: &[(TokenInfoKey, CachedTokenInfo)],
+) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM token_info_cache")
+        .execute(&mut *tx)
+        .await?;
+
+    let now = Utc::now().timestamp();
+    for ((chain_id, token_ca), info) in token_infos {
+        sqlx::query(
+            "INSERT INTO token_info_cache (chain_id, token_ca, info, saved_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(*chain_id)
+        .bind(token_ca)
+        .bind(serde_json::to_string(info)?)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    info!("Saved {} token infos to storage", token_infos.len());
+
+    Ok(())
+}
+
+/// Chain id, token CA and info of the token infos saved after `since`
+pub async fn load_token_infos(
+    pool: &SqlitePool,
+    since: DateTime<Utc>,
+) -> anyhow::Result<Vec<(String, String, CachedTokenInfo)>> {
+    let rows = sqlx::query_as::<_, (String, String, String)>(
+        "SELECT chain_id, token_ca, info FROM token_info_cache WHERE saved_at > ?",
+    )
+    .bind(since.timestamp())
+    .fetch_all(pool)
+    .await?;
+
+    let mut token_infos = Vec::with_capacity(rows.len());
+    for (chain_id, token_ca, info) in rows {
+        token_infos.push((chain_id, token_ca, serde_json::from_str(&info)?));
+    }
+
+    Ok(token_infos)
+}
+
+/// Alias phrases of the chat with the CAs they point to
+pub async fn load_aliases(
+    pool: &SqlitePool,
+    chat_id: ChatId,
+) -> anyhow::Result<Vec<(String, String)>> {
+    let ChatId(chat_id) = chat_id;
+    let aliases = sqlx::query_as::<_, (String, String)>(
+        "SELECT phrase, token_ca FROM aliases WHERE chat_id = ? ORDER BY phrase",
+    )
+    .bind(chat_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(aliases)
+}
+
+pub async fn save_alias(
+    pool: &SqlitePool,
+    chat_id: ChatId,
+    phrase: &str,
+    token_ca: &str,
+) -> anyhow::Result<()> {
+    let ChatId(chat_id) = chat_id;
+    sqlx::query("INSERT OR REPLACE INTO aliases (chat_id, phrase, token_ca) VALUES (?, ?, ?)")
+        .bind(chat_id)
+        .bind(phrase)
+        .bind(token_ca)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Returns whether the alias existed
+pub async fn delete_alias(
+    pool: &SqlitePool,
+    chat_id: ChatId,
+    phrase: &str,
+) -> anyhow::Result<bool> {
+    let ChatId(chat_id) = chat_id;
+    let result = sqlx::query("DELETE FROM aliases WHERE chat_id = ? AND phrase = ?")
+        .bind(chat_id)
+        .bind(phrase)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// The first card of the token in the chat, if any
+pub async fn load_first_mention(
+    pool: &SqlitePool,
+    chat_id: ChatId,
+    token_ca: &str,
+) -> anyhow::Result<Option<FirstMention>> {
+    let ChatId(chat_id) = chat_id;
+    let row = sqlx::query_as::<_, (Option<String>, Option<String>, i64, i64)>(
+        "SELECT mcap, caller, mentioned_at, mention_count FROM mentions WHERE chat_id = ? AND token_ca = ?",
+    )
+    .bind(chat_id)
+    .bind(token_ca)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some((mcap, caller, mentioned_at, count)) = row else {
+        return Ok(None);
+    };
+
+    Ok(Some(FirstMention {
+        mcap: mcap.and_then(|mcap| mcap.parse().ok()),
+        caller,
+        mentioned_at: DateTime::from_timestamp(mentioned_at, 0).unwrap_or_default(),
+        count,
+    }))
+}
+
+/// Counts one more mention of the token in the chat, returns the new count
+pub async fn increment_mention_count(
+    pool: &SqlitePool,
+    chat_id: ChatId,
+    token_ca: &str,
+) -> anyhow::Result<i64> {
+    let ChatId(chat_id) = chat_id;
+    let (count,) = sqlx::query_as::<_, (i64,)>(
+        "UPDATE mentions SET mention_count = mention_count + 1
+            WHERE chat_id = ? AND token_ca = ? RETURNING mention_count",
+    )
+    .bind(chat_id)
+    .bind(token_ca)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Keeps the already saved mention if there is one
+pub async fn save_first_mention(
+    pool: &SqlitePool,
+    chat_id: ChatId,
+    chain_id: &str,
+    token_ca: &str,
+    symbol: &str,
+    mention: &FirstMention,
+) -> anyhow::Result<()> {
+    let ChatId(chat_id) = chat_id;
+    let mcap = mention.mcap.map(|mcap| mcap.to_string());
+    sqlx::query(
+        "INSERT OR IGNORE INTO mentions (chat_id, chain_id, token_ca, symbol, mcap, latest_mcap, caller, mentioned_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(chat_id)
+    .bind(chain_id)
+    .bind(token_ca)
+    .bind(symbol)
+    .bind(&mcap)
+    .bind(&mcap)
+    .bind(&mention.caller)
+    .bind(mention.mentioned_at.timestamp())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Sets the current mcap of the token in all chats it was mentioned in
+pub async fn update_mention_mcap(
+    pool: &SqlitePool,
+    chain_id: &str,
+    token_ca: &str,
+    mcap: Decimal,
+) -> anyhow::Result<()> {
+    sqlx::query("UPDATE mentions SET latest_mcap = ? WHERE chain_id = ? AND token_ca = ?")
+        .bind(mcap.to_string())
+        .bind(chain_id)
+        .bind(token_ca)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Chain ids and CAs of the tokens first mentioned after `since` in any chat
+pub async fn load_mentioned_tokens(
+    pool: &SqlitePool,
+    since: DateTime<Utc>,
+) -> anyhow::Result<Vec<(String, String)>> {
+    let tokens = sqlx::query_as::<_, (String, String)>(
+        "SELECT DISTINCT chain_id, token_ca FROM mentions WHERE mentioned_at > ?",
+    )
+    .bind(since.timestamp())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tokens)
+}
+
+/// Symbol, first and latest mcap of the tokens first mentioned in the chat after `since`
+pub async fn load_recent_mentions(
+    pool: &SqlitePool,
+    chat_id: ChatId,
+    since: DateTime<Utc>,
+) -> anyhow::Result<Vec<(String, Option<String>, Option<String>)>> {
+    let ChatId(chat_id) = chat_id;
+    let mentions = sqlx::query_as::<_, (String, Option<String>, Option<String>)>(
+        "SELECT symbol, mcap, latest_mcap FROM mentions WHERE chat_id = ? AND mentioned_at > ?",
+    )
+    .bind(chat_id)
+    .bind(since.timestamp())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(mentions)
+}
+
+/// Caller, symbol, first and latest mcap of the credited mentions in the chat after `since`
+pub async fn load_chat_calls(
+    pool: &SqlitePool,
+    chat_id: ChatId,
+    since: DateTime<Utc>,
+) -> anyhow::Result<Vec<(String, String, Option<String>, Option<String>)>> {
+    let ChatId(chat_id) = chat_id;
+    let calls = sqlx::query_as::<_, (String, String, Option<String>, Option<String>)>(
+        "SELECT caller, symbol, mcap, latest_mcap FROM mentions
+         WHERE chat_id = ? AND mentioned_at > ? AND caller IS NOT NULL",
+    )
+    .bind(chat_id)
+    .bind(since.timestamp())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(calls)
+}
+
+type WatchRow = (
+    i64,
+    i64,
+    String,
+    i64,
+    Option<i32>,
+    String,
+    String,
+    String,
+    String,
+    String,
+    bool,
+);
+
+const WATCH_COLUMNS: &str = "id, user_id, user_name, chat_id, thread_id, chain_id, token_ca, symbol, base_mcap, target_mcap, dm";
+
+fn watch_from_row(row: WatchRow) -> Option<Watch> {
+    let (
+        id,
+        user_id,
+        user_name,
+        chat_id,
+        thread_id,
+        chain_id,
+        token_ca,
+        symbol,
+        base_mcap,
+        target_mcap,
+        dm,
+    ) = row;
+
+    Some(Watch {
+        id,
+        user_id: UserId(u64::try_from(user_id).ok()?),
+        user_name,
+        chat_id: ChatId(chat_id),
+        thread_id: thread_id.map(|id| ThreadId(MessageId(id))),
+        chain_id,
+        token_ca,
+        symbol,
+        base_mcap: base_mcap.parse().ok()?,
+        target_mcap: target_mcap.parse().ok()?,
+        destination: if dm {
+            AlertDestination::DirectMessage
+        } else {
+            AlertDestination::Chat
+        },
+    })
+}
+
+/// Replaces the user's alert on the same token in the chat
+pub async fn save_watch(pool: &SqlitePool, watch: &Watch) -> anyhow::Result<()> {
+    let UserId(user_id) = watch.user_id;
+    let ChatId(chat_id) = watch.chat_id;
+    sqlx::query(
+        "INSERT OR REPLACE INTO watches (user_id, user_name, chat_id, thread_id, chain_id, token_ca, symbol, base_mcap, target_mcap, dm, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(i64::try_from(user_id)?)
+    .bind(&watch.user_name)
+    .bind(chat_id)
+    .bind(watch.thread_id.map(|ThreadId(MessageId(id))| id))
+    .bind(&watch.chain_id)
+    .bind(&watch.token_ca)
+    .bind(&watch.symbol)
+    .bind(watch.base_mcap.to_string())
+    .bind(watch.target_mcap.to_string())
+    .bind(watch.destination == AlertDestination::DirectMessage)
+    .bind(Utc::now().timestamp())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns whether the alert existed, the CA is compared case-insensitively
+pub async fn delete_watch(
+    pool: &SqlitePool,
+    user_id: UserId,
+    chat_id: ChatId,
+    token_ca: &str,
+) -> anyhow::Result<bool> {
+    let UserId(user_id) = user_id;
+    let ChatId(chat_id) = chat_id;
+    let result = sqlx::query(
+        "DELETE FROM watches WHERE user_id = ? AND chat_id = ? AND lower(token_ca) = lower(?)",
+    )
+    .bind(i64::try_from(user_id)?)
+    .bind(chat_id)
+    .bind(token_ca)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn delete_watch_by_id(pool: &SqlitePool, id: i64) -> anyhow::Result<()> {
+    sqlx::query("DELETE FROM watches WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn count_user_watches(pool: &SqlitePool, user_id: UserId) -> anyhow::Result<i64> {
+    let UserId(user_id) = user_id;
+    let (count,) = sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM watches WHERE user_id = ?")
+        .bind(i64::try_from(user_id)?)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(count)
+}
+
+/// Alerts of the user set up in the chat
+pub async fn load_user_watches(
+    pool: &SqlitePool,
+    user_id: UserId,
+    chat_id: ChatId,
+) -> anyhow::Result<Vec<Watch>> {
+    let UserId(user_id) = user_id;
+    let ChatId(chat_id) = chat_id;
+    let rows = sqlx::query_as::<_, WatchRow>(&format!(
+        "SELECT {WATCH_COLUMNS} FROM watches WHERE user_id = ? AND chat_id = ? ORDER BY id"
+    ))
+    .bind(i64::try_from(user_id)?)
+    .bind(chat_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().filter_map(watch_from_row).collect())
+}
+
+pub async fn load_watches(pool: &SqlitePool) -> anyhow::Result<Vec<Watch>> {
+    let rows = sqlx::query_as::<_, WatchRow>(&format!("SELECT {WATCH_COLUMNS} FROM watches"))
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.into_iter().filter_map(watch_from_row).collect())
+}
+
+pub async fn enqueue_outbox_entry(pool: &SqlitePool, entry: &OutboxEntry) -> anyhow::Result<()> {
+    let ChatId(chat_id) = entry.chat_id;
+    let MessageId(reply_to) = entry.reply_to;
+    // stored as `[chain id, token CA]` pairs
+    let tokens: Vec<(&str, &str)> = entry
+        .reply
+        .tokens
+        .iter()
+        .map(|token| (token.chain_id, token.token_ca.as_str()))
+        .collect();
+    sqlx::query(
+        "INSERT INTO outbox (chat_id, thread_id, reply_to, text, buttons, tokens, full_text, attempts,
+            next_attempt_at, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(chat_id)
+    .bind(entry.thread_id.map(|ThreadId(MessageId(id))| id))
+    .bind(reply_to)
+    .bind(&entry.reply.text)
+    .bind(serde_json::to_string(&entry.reply.buttons)?)
+    .bind(serde_json::to_string(&tokens)?)
+    .bind(&entry.reply.full_text)
+    .bind(entry.attempts)
+    .bind(entry.next_attempt_at.timestamp())
+    .bind(entry.created_at.timestamp())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+type OutboxRow = (
+    i64,
+    i64,
+    Option<i32>,
+    i32,
+    String,
+    String,
+    String,
+    Option<String>,
+    u32,
+    i64,
+    i64,
+);
+
+/// Up to `limit` oldest entries due to be resent at `now`
+pub async fn load_due_outbox(
+    pool: &SqlitePool,
+    now: DateTime<Utc>,
+    limit: i64,
+) -> anyhow::Result<Vec<OutboxEntry>> {
+    let rows = sqlx::query_as::<_, OutboxRow>(
+        "SELECT id, chat_id, thread_id, reply_to, text, buttons, tokens, full_text, attempts,
+            next_attempt_at, created_at
+         FROM outbox WHERE next_attempt_at <= ? ORDER BY id LIMIT ?",
+    )
+    .bind(now.timestamp())
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    let mut entries = Vec::with_capacity(rows.len());
+    for (
+        id,
+        chat_id,
+        thread_id,
+        reply_to,
+        text,
+        buttons,
+        tokens,
+        full_text,
+        attempts,
+        next_attempt_at,
+        created_at,
+    ) in rows
+    {
+        let tokens = serde_json::from_str::<Vec<(String, String)>>(&tokens)?
+            .into_iter()
+            .filter_map(|(chain_id, token_ca)| {
+                Some(CardToken {
+                    chain_id: dexscreener_chain_id(&chain_id)?,
+                    token_ca,
+                })
+            })
+            .collect();
+
+        entries.push(OutboxEntry {
+            id,
+            chat_id: ChatId(chat_id),
+            thread_id: thread_id.map(|id| ThreadId(MessageId(id))),
+            reply_to: MessageId(reply_to),
+            reply: CardReply {
+                text,
+                buttons: serde_json::from_str(&buttons)?,
+                tokens,
+                full_text,
+            },
+            attempts,
+            next_attempt_at: DateTime::from_timestamp(next_attempt_at, 0).unwrap_or_default(),
+            created_at: DateTime::from_timestamp(created_at, 0).unwrap_or_default(),
+        });
+    }
+
+    Ok(entries)
+}
+
+pub async fn reschedule_outbox_entry(
+    pool: &SqlitePool,
+    id: i64,
+    attempts: u32,
+    next_attempt_at: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    sqlx::query("UPDATE outbox SET attempts = ?, next_attempt_at = ? WHERE id = ?")
+        .bind(attempts)
+        .bind(next_attempt_at.timestamp())
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_outbox_entry(pool: &SqlitePool, id: i64) -> anyhow::Result<()> {
+    sqlx::query("DELETE FROM outbox WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Replaces the tokens of the list `source`, symbols are stored in uppercase.
+/// The first token of a symbol on a chain wins.
+pub async fn replace_token_list(
+    pool: &SqlitePool,
+    source: &str,
+    tokens: &[ListedToken],
+) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM token_list WHERE source = ?")
+        .bind(source)
+        .execute(&mut *tx)
+        .await?;
+
+    for token in tokens {
+        sqlx::query(
+            "INSERT OR IGNORE INTO token_list (source, chain_id, symbol, token_ca) VALUES (?, ?, ?, ?)",
+        )
+        .bind(source)
+        .bind(token.chain_id)
+        .bind(token.symbol.to_uppercase())
+        .bind(&token.token_ca)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    info!("Saved {} tokens of the {source} token list", tokens.len());
+
+    Ok(())
+}
+
+/// Addresses of the listed tokens with the symbol on any chain
+pub async fn find_listed_tokens(pool: &SqlitePool, symbol: &str) -> anyhow::Result<Vec<String>> {
+    let rows = sqlx::query_as::<_, (String,)>("SELECT token_ca FROM token_list WHERE symbol = ?")
+        .bind(symbol.to_uppercase())
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.into_iter().map(|(token_ca,)| token_ca).collect())
+}
+
+/// Replaces the stored category tags of the token
+pub async fn save_token_tags(
+    pool: &SqlitePool,
+    chain_id: &str,
+    token_ca: &str,
+    tags: &[String],
+) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM token_tags WHERE chain_id = ? AND token_ca = ?")
+        .bind(chain_id)
+        .bind(token_ca)
+        .execute(&mut *tx)
+        .await?;
+
+    let now = Utc::now().timestamp();
+    for tag in tags {
+        sqlx::query(
+            "INSERT OR IGNORE INTO token_tags (chain_id, token_ca, tag, tagged_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(chain_id)
+        .bind(token_ca)
+        .bind(tag)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Tags of the tokens mentioned in the chat after `since` with the number of such tokens
+pub async fn load_chat_tag_counts(
+    pool: &SqlitePool,
+    chat_id: ChatId,
+    since: DateTime<Utc>,
+) -> anyhow::Result<Vec<(String, i64)>> {
+    let ChatId(chat_id) = chat_id;
+    let counts = sqlx::query_as::<_, (String, i64)>(
+        "SELECT t.tag, COUNT(*) AS tokens FROM mentions m
+        JOIN token_tags t ON t.chain_id = m.chain_id AND t.token_ca = m.token_ca
+        WHERE m.chat_id = ? AND m.mentioned_at > ?
+        GROUP BY t.tag
+        ORDER BY tokens DESC, t.tag",
+    )
+    .bind(chat_id)
+    .bind(since.timestamp())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(counts)
+}
+
+/// Deletes the persisted throttle entries older than `before`, returns how many were deleted
+pub async fn prune_throttling_info(
+    pool: &SqlitePool,
+    before: DateTime<Utc>,
+) -> anyhow::Result<u64> {
+    let result = sqlx::query("DELETE FROM throttle WHERE sent_at < ?")
+        .bind(before.timestamp())
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Deletes the mentions first made before `before`, returns the number of deleted ones
+pub async fn prune_mentions(pool: &SqlitePool, before: DateTime<Utc>) -> anyhow::Result<u64> {
+    let result = sqlx::query("DELETE FROM mentions WHERE mentioned_at < ?")
+        .bind(before.timestamp())
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Deletes the alerts set before `before`, returns the number of deleted ones
+pub async fn prune_watches(pool: &SqlitePool, before: DateTime<Utc>) -> anyhow::Result<u64> {
+    let result = sqlx::query("DELETE FROM watches WHERE created_at < ?")
+        .bind(before.timestamp())
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Deletes the replies queued before `before`, returns the number of deleted ones
+pub async fn prune_outbox(pool: &SqlitePool, before: DateTime<Utc>) -> anyhow::Result<u64> {
+    let result = sqlx::query("DELETE FROM outbox WHERE created_at < ?")
+        .bind(before.timestamp())
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Deletes the tags saved before `before`, returns the number of deleted ones
+pub async fn prune_token_tags(pool: &SqlitePool, before: DateTime<Utc>) -> anyhow::Result<u64> {
+    let result = sqlx::query("DELETE FROM token_tags WHERE tagged_at < ?")
+        .bind(before.timestamp())
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn save_archived_chat(
+    pool: &SqlitePool,
+    chat_id: ChatId,
+    archived_at: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    let ChatId(chat_id) = chat_id;
+    sqlx::query("INSERT OR REPLACE INTO archived_chats (chat_id, archived_at) VALUES (?, ?)")
+        .bind(chat_id)
+        .bind(archived_at.timestamp())
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Archived chats along with when they were archived
+pub async fn load_archived_chats(
+    pool: &SqlitePool,
+) -> anyhow::Result<Vec<(ChatId, DateTime<Utc>)>> {
+    let rows = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT chat_id, archived_at FROM archived_chats ORDER BY archived_at",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(chat_id, archived_at)| {
+            (
+                ChatId(chat_id),
+                DateTime::from_timestamp(archived_at, 0).unwrap_or_default(),
+            )
+        })
+        .collect())
+}
+
+pub async fn delete_archived_chat(pool: &SqlitePool, chat_id: ChatId) -> anyhow::Result<()> {
+    let ChatId(chat_id) = chat_id;
+    sqlx::query("DELETE FROM archived_chats WHERE chat_id = ?")
+        .bind(chat_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Deletes everything stored for the chat and its archive record, returns the number of deleted rows
+pub async fn delete_chat_data(pool: &SqlitePool, chat_id: ChatId) -> anyhow::Result<u64> {
+    let ChatId(chat_id) = chat_id;
+    let mut tx = pool.begin().await?;
+
+    let mut deleted = 0;
+    for table in [
+        "throttle",
+        "aliases",
+        "mentions",
+        "watches",
+        "outbox",
+        "chat_settings",
+    ] {
+        let result = sqlx::query(&format!("DELETE FROM {table} WHERE chat_id = ?"))
+            .bind(chat_id)
+            .execute(&mut *tx)
+            .await?;
+        deleted += result.rows_affected();
+    }
+
+    sqlx::query("DELETE FROM archived_chats WHERE chat_id = ?")
+        .bind(chat_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(deleted)
+}
+
+pub async fn save_chat_settings(
+    pool: &SqlitePool,
+    chat_id: ChatId,
+    settings: &ChatSettings,
+) -> anyhow::Result<()> {
+    let ChatId(chat_id) = chat_id;
+    sqlx::query(
+        "INSERT OR REPLACE INTO chat_settings
+            (chat_id, throttle_mins, link_set, min_mcap, auto_delete_mins, silent, observe_only,
+                scan_replies)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(chat_id)
+    .bind(settings.throttle_mins)
+    .bind(&settings.link_set)
+    .bind(settings.min_mcap)
+    .bind(settings.auto_delete_mins)
+    .bind(settings.silent)
+    .bind(settings.observe_only)
+    .bind(settings.scan_replies)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn load_chat_settings(pool: &SqlitePool) -> anyhow::Result<Vec<(ChatId, ChatSettings)>> {
+    let rows = sqlx::query_as::<
+        _,
+        (
+            i64,
+            Option<i64>,
+            Option<String>,
+            Option<f64>,
+            Option<i64>,
+            bool,
+            bool,
+            bool,
+        ),
+    >(
+        "SELECT chat_id, throttle_mins, link_set, min_mcap, auto_delete_mins, silent, observe_only,
+            scan_replies FROM chat_settings",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(
+                chat_id,
+                throttle_mins,
+                link_set,
+                min_mcap,
+                auto_delete_mins,
+                silent,
+                observe_only,
+                scan_replies,
+            )| {
+                let settings = ChatSettings {
+                    throttle_mins,
+                    link_set,
+                    min_mcap,
+                    auto_delete_mins,
+                    silent,
+                    observe_only,
+                    scan_replies,
+                };
+                (ChatId(chat_id), settings)
+            },
+        )
+        .collect())
+}
+
+/// Adds the provider calls and units (e.g. Moralis compute units) to the day's totals
+pub async fn add_provider_usage(
+    pool: &SqlitePool,
+    day: &str,
+    provider: &str,
+    calls: i64,
+    units: i64,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO provider_usage (day, provider, calls, units) VALUES (?, ?, ?, ?)
+            ON CONFLICT (day, provider)
+            DO UPDATE SET calls = calls + excluded.calls, units = units + excluded.units",
+    )
+    .bind(day)
+    .bind(provider)
+    .bind(calls)
+    .bind(units)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Provider, calls and units of the day
+pub async fn load_provider_usage(
+    pool: &SqlitePool,
+    day: &str,
+) -> anyhow::Result<Vec<(String, i64, i64)>> {
+    let rows = sqlx::query_as::<_, (String, i64, i64)>(
+        "SELECT provider, calls, units FROM provider_usage WHERE day = ? ORDER BY provider",
+    )
+    .bind(day)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Remembers that the user has started a private chat with the bot
+pub async fn save_private_chat_user(pool: &SqlitePool, user_id: UserId) -> anyhow::Result<()> {
+    let UserId(user_id) = user_id;
+    sqlx::query("INSERT OR IGNORE INTO private_chat_users (user_id, started_at) VALUES (?, ?)")
+        .bind(i64::try_from(user_id)?)
+        .bind(Utc::now().timestamp())
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_private_chat_user(pool: &SqlitePool, user_id: UserId) -> anyhow::Result<()> {
+    let UserId(user_id) = user_id;
+    sqlx::query("DELETE FROM private_chat_users WHERE user_id = ?")
+        .bind(i64::try_from(user_id)?)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn load_private_chat_users(pool: &SqlitePool) -> anyhow::Result<Vec<UserId>> {
+    let rows = sqlx::query_as::<_, (i64,)>("SELECT user_id FROM private_chat_users")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(user_id,)| u64::try_from(user_id).ok().map(UserId))
+        .collect())
+}
+
+/// Deletes the usage of the days before `before` (`YYYY-MM-DD`), returns the number of deleted rows
+pub async fn prune_provider_usage(pool: &SqlitePool, before: &str) -> anyhow::Result<u64> {
+    let result = sqlx::query("DELETE FROM provider_usage WHERE day < ?")
+        .bind(before)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Size of the database file in bytes
+pub async fn storage_size(pool: &SqlitePool) -> anyhow::Result<i64> {
+    let (page_count,) = sqlx::query_as::<_, (i64,)>("PRAGMA page_count")
+        .fetch_one(pool)
+        .await?;
+    let (page_size,) = sqlx::query_as::<_, (i64,)>("PRAGMA page_size")
+        .fetch_one(pool)
+        .await?;
+
+    Ok(page_count * page_size)
+}
+
+/// Rebuilds the database file to give the space of deleted rows back
+pub async fn compact_storage(pool: &SqlitePool) -> anyhow::Result<()> {
+    sqlx::query("VACUUM").execute(pool).await?;
+    sqlx::query("PRAGMA optimize").execute(pool).await?;
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use log::{debug, error, info, warn};
+
+use crate::health::is_healthy;
+
+/// Exit code for invalid or missing configuration (`EX_CONFIG`), restarting won't help
+pub const EXIT_CONFIG_ERROR: i32 = 78;
+
+/// Exit code for failures at runtime (`EX_SOFTWARE`), a restart may help
+pub const EXIT_RUNTIME_FAILURE: i32 = 70;
+
+/// Logs the reason and exits with the given code
+pub fn exit_with(code: i32, reason: &str) -> ! {
+    error!("{reason} - exiting with code {code}");
+    notify_systemd(&format!("STATUS={reason}"));
+    std::process::exit(code)
+}
+
+/// Tells systemd (`Type=notify`) that the bot is up
+pub fn notify_ready() {
+    notify_systemd("READY=1");
+}
+
+pub fn notify_stopping() {
+    notify_systemd("STOPPING=1");
+}
+
+/// Sends watchdog keepalives while the bot is healthy (see `/healthz`), so
+/// systemd restarts the service when polling stalls. Does nothing unless
+/// `WatchdogSec=` is set for the service.
+pub fn spawn_watchdog() {
+    let Some(timeout) = watchdog_timeout() else {
+        debug!("Systemd watchdog is not enabled");
+        return;
+    };
+
+    info!("Sending systemd watchdog keepalives, timeout is {timeout:?}");
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(timeout / 2);
+        loop {
+            interval.tick().await;
+            if is_healthy() {
+                notify_systemd("WATCHDOG=1");
+            } else {
+                warn!("Bot is unhealthy - skipping systemd watchdog keepalive");
+            }
+        }
+    });
+}
+
+fn watchdog_timeout() -> Option<Duration> {
+    // the watchdog is meant for the main process only
+    if let Ok(pid) = std::env::var("WATCHDOG_PID")
+        && pid.parse() != Ok(std::process::id())
+    {
+        return None;
+    }
+
+    let usec = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    Some(Duration::from_micros(usec)).filter(|timeout| !timeout.is_zero())
+}
+
+#[cfg(unix)]
+fn notify_systemd(state: &str) {
+    let Some(socket_path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+
+    if let Err(e) = send_to_notify_socket(&socket_path, state) {
+        warn!("Failed to notify systemd with {state} - {e:?}");
+    }
+}
+
+#[cfg(not(unix))]
+fn notify_systemd(_state: &str) {}
+
+#[cfg(unix)]
+fn send_to_notify_socket(socket_path: &std::ffi::OsStr, state: &str) -> std::io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::UnixDatagram;
+
+    let socket = UnixDatagram::unbound()?;
+    match socket_path.as_bytes().strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            use std::os::unix::net::SocketAddr;
+
+            let addr = SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        _ => {
+            socket.send_to(state.as_bytes(), socket_path)?;
+        }
+    }
+
+    Ok(())
+}
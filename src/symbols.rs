@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use teloxide::types::ChatId;
+use tokio::sync::RwLock;
+
+/// How long a posted symbol is remembered
+const SYMBOL_MEMORY: TimeDelta = TimeDelta::hours(24);
+
+#[derive(Debug, Clone)]
+struct SeenSymbol {
+    chain: String,
+    token_ca: String,
+    seen_at: DateTime<Utc>,
+}
+
+/// Symbols carded recently, per chat
+type SymbolIndex = HashMap<(ChatId, String), Vec<SeenSymbol>>;
+
+static SEEN_SYMBOLS: LazyLock<RwLock<SymbolIndex>> = LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Token with the same symbol posted recently on another chain
+#[derive(Debug, Clone)]
+pub struct SymbolCollision {
+    pub chain: String,
+    pub seen_at: DateTime<Utc>,
+}
+
+impl SymbolCollision {
+    /// e.g. "a different $PEPE on BSC was posted 1h ago", not escaped
+    pub fn describe(&self, symbol: &str) -> String {
+        let ago = Utc::now() - self.seen_at;
+        let ago = if ago.num_hours() > 0 {
+            format!("{}h ago", ago.num_hours())
+        } else if ago.num_minutes() > 0 {
+            format!("{}m ago", ago.num_minutes())
+        } else {
+            "just now".to_owned()
+        };
+
+        format!("a different ${symbol} on {} was posted {ago}", self.chain)
+    }
+}
+
+/// Remembers the symbol posted in the chat and returns the latest token with the
+/// same symbol posted there on another chain, if any
+pub async fn record_symbol(
+    chat_id: ChatId,
+    symbol: &str,
+    chain: &str,
+    token_ca: &str,
+) -> Option<SymbolCollision> {
+    let now = Utc::now();
+    let is_fresh = |seen: &SeenSymbol| now - seen.seen_at < SYMBOL_MEMORY;
+    let key = (chat_id, symbol.trim().to_uppercase());
+
+    let mut seen_symbols = SEEN_SYMBOLS.write().await;
+
+    if !seen_symbols.contains_key(&key) {
+        // drop stale symbols once in a while so the index stays small
+        seen_symbols.retain(|_, seen| {
+            seen.retain(is_fresh);
+            !seen.is_empty()
+        });
+    }
+
+    let seen = seen_symbols.entry(key).or_default();
+    seen.retain(|s| is_fresh(s) && s.token_ca != token_ca);
+
+    let collision = seen
+        .iter()
+        .filter(|s| s.chain != chain)
+        .max_by_key(|s| s.seen_at)
+        .map(|s| SymbolCollision {
+            chain: s.chain.clone(),
+            seen_at: s.seen_at,
+        });
+
+    seen.push(SeenSymbol {
+        chain: chain.to_owned(),
+        token_ca: token_ca.to_owned(),
+        seen_at: now,
+    });
+
+    collision
+}
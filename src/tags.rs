@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use log::warn;
+use sqlx::SqlitePool;
+use teloxide::types::ChatId;
+
+use crate::storage::{load_chat_tag_counts, save_token_tags};
+
+/// Max number of categories listed in a breakdown
+const MAX_BREAKDOWN_TAGS: usize = 5;
+
+/// Provider tags as hashtags, e.g. `dog-themed` -> `dogthemed`
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag: String = tag
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+/// Line like `#meme #dogthemed` shown at the bottom of the card
+pub fn hashtags(tags: &[String]) -> Option<String> {
+    let tags = normalize_tags(tags);
+    (!tags.is_empty()).then(|| {
+        tags.iter()
+            .map(|tag| format!("#{tag}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    })
+}
+
+/// Stores the tags so the mentions can be broken down by category later
+pub async fn record_tags(storage: &SqlitePool, chain_id: &str, token_ca: &str, tags: &[String]) {
+    let tags = normalize_tags(tags);
+    if tags.is_empty() {
+        return;
+    }
+
+    if let Err(e) = save_token_tags(storage, chain_id, token_ca, &tags).await {
+        warn!("Failed to save tags of {token_ca} - {e:?}");
+    }
+}
+
+/// Line like `Categories: #meme 12, #ai 4` of the tokens mentioned in the chat after `since`
+pub async fn category_breakdown(
+    storage: &SqlitePool,
+    chat_id: ChatId,
+    since: DateTime<Utc>,
+) -> Option<String> {
+    let counts = match load_chat_tag_counts(storage, chat_id, since).await {
+        Ok(counts) => counts,
+        Err(e) => {
+            warn!("Failed to load tag counts of {chat_id} - {e:?}");
+            return None;
+        }
+    };
+
+    let categories = counts
+        .iter()
+        .take(MAX_BREAKDOWN_TAGS)
+        .map(|(tag, tokens)| format!("#{tag} {tokens}"))
+        .collect::<Vec<_>>();
+    (!categories.is_empty()).then(|| format!("Categories: {}", categories.join(", ")))
+}
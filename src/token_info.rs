@@ -1,22 +1,41 @@
-use std::sync::OnceLock;
+use std::fmt;
+use std::sync::{LazyLock, OnceLock};
 
 use anyhow::anyhow;
+use chrono::{DateTime, Utc};
 use log::{debug, warn};
 use regex::{Regex, RegexBuilder};
+use reqwest::StatusCode;
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::{Decimal, dec};
 use rust_translate::translate_to_english;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::APP_CONFIG;
+use crate::birdeye::fetch_birdeye_token_info;
+use crate::cache::{CachedTokenInfo, TokenInfoKey, caches};
+use crate::dexscreener::retrieve_token_pair;
+use crate::moralis_keys::{TOKEN_HOLDERS_CU, TOKEN_METADATA_CU, send_moralis};
+use crate::notify::is_provider_outage;
+use crate::providers::{CircuitOpen, Provider, guarded};
+use crate::retry::send_with_retry;
+use crate::singleflight::SingleFlight;
 
 const ONE_THOUSAND: Decimal = Decimal::ONE_THOUSAND;
 const ONE_MILLION: Decimal = dec!(1_000_000);
 const ONE_BILLION: Decimal = dec!(1_000_000_000);
 
-fn format_human_readable(num: Decimal, decimal_places: usize) -> String {
+/// FDV this many times above the circulating mcap usually means big unlocks ahead
+const FDV_UNLOCK_RATIO: Decimal = dec!(2);
+
+/// Share of the quote reserve a constant product pool pays out before the
+/// price drops by 20% - `1 - sqrt(0.8)`
+const EXIT_CAPACITY_SHARE: Decimal = dec!(0.105572809);
+
+pub fn format_human_readable(num: Decimal, decimal_places: usize) -> String {
     let abs_num = num.abs();
     let prec = decimal_places;
-    
+
     if abs_num >= ONE_BILLION {
         format!("{:.prec$}B", num / ONE_BILLION)
     } else if abs_num >= ONE_MILLION {
@@ -28,22 +47,161 @@ fn format_human_readable(num: Decimal, decimal_places: usize) -> String {
     }
 }
 
+/// Formats a USD price keeping 4 significant digits for sub-dollar prices
+pub fn format_price(price: Decimal) -> String {
+    if price >= Decimal::ONE {
+        return format!("${:.2}", price.round_dp(2));
+    }
+
+    let mut leading_zeros = 0;
+    let mut scaled = price;
+    while scaled > Decimal::ZERO && scaled < dec!(0.1) && leading_zeros < 24 {
+        scaled *= Decimal::TEN;
+        leading_zeros += 1;
+    }
+
+    format!("${}", price.round_dp(leading_zeros + 4).normalize())
+}
+
+/// Price line like `$0.0041 (▲ 12.3%)`, not escaped
+fn format_price_with_change(price: Option<Decimal>, change: Option<Decimal>) -> Option<String> {
+    let price = format_price(price?);
+    match change {
+        Some(change) => Some(format!("{price} ({})", format_price_change(change))),
+        None => Some(price),
+    }
+}
+
+/// Line like `Liq $120.5K · Vol 24h $1.2M`, not escaped
+fn format_liquidity_with_volume(
+    liquidity: Option<Decimal>,
+    volume: Option<Decimal>,
+) -> Option<String> {
+    let format_usd = |value: Option<Decimal>| match value {
+        Some(value) => format!("${}", format_human_readable(value, 1)),
+        None => "?".to_owned(),
+    };
+
+    if liquidity.is_none() && volume.is_none() {
+        return None;
+    }
+
+    Some(format!(
+        "Liq {} · Vol 24h {}",
+        format_usd(liquidity),
+        format_usd(volume)
+    ))
+}
+
+/// Mcap alone or `MC 2.10M · FDV 4.80M` when FDV is noticeably above it, not escaped
+fn format_mcap_with_fdv(mcap: Decimal, fdv: Option<Decimal>) -> String {
+    let mcap_str = format_human_readable(mcap, 2);
+
+    match fdv {
+        Some(fdv) if fdv > mcap * dec!(1.01) => {
+            let warning = if fdv >= mcap * FDV_UNLOCK_RATIO {
+                " ⚠️ big unlocks"
+            } else {
+                ""
+            };
+            format!(
+                "MC {mcap_str} · FDV {}{warning}",
+                format_human_readable(fdv, 2)
+            )
+        }
+        _ => mcap_str,
+    }
+}
+
+/// Line like `Holders 1.2K · top 10 hold 34.5%`, not escaped
+fn format_holders(holders: Option<u64>, top10_share: Option<Decimal>) -> Option<String> {
+    match (holders, top10_share) {
+        (None, None) => None,
+        (Some(holders), None) => Some(format!(
+            "Holders {}",
+            format_human_readable(Decimal::from(holders), 1)
+        )),
+        (None, Some(share)) => Some(format!("Top 10 hold {share:.1}%")),
+        (Some(holders), Some(share)) => Some(format!(
+            "Holders {} · top 10 hold {share:.1}%",
+            format_human_readable(Decimal::from(holders), 1)
+        )),
+    }
+}
+
+/// Rough USD value which can be sold into the pool before a 20% price impact
+pub fn estimate_exit_capacity(quote_reserve_usd: Decimal) -> Decimal {
+    quote_reserve_usd * EXIT_CAPACITY_SHARE
+}
+
+/// Line like `exit capacity ~$8.2K`, not escaped
+fn format_exit_capacity(quote_reserve_usd: Option<Decimal>) -> Option<String> {
+    let capacity = estimate_exit_capacity(quote_reserve_usd?);
+    Some(format!(
+        "exit capacity ~${}",
+        format_human_readable(capacity, 1)
+    ))
+}
+
+/// Formats a percentage change as `▲ 12.3%` / `▼ 4.5%`
+pub fn format_price_change(change: Decimal) -> String {
+    if change >= Decimal::ZERO {
+        format!("▲ {:.1}%", change)
+    } else {
+        format!("▼ {:.1}%", change.abs())
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct EvmTokenInfoSerialized {
     pub address: String,
     pub name: String,
     pub symbol: String,
     pub market_cap: Decimal,
+    pub fully_diluted_valuation: Option<Decimal>,
     pub created_at: Option<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MoralisHolders {
+    total_holders: Option<u64>,
+    holder_supply: Option<MoralisHolderSupply>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoralisHolderSupply {
+    top10: Option<MoralisSupplyShare>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MoralisSupplyShare {
+    supply_percent: Option<Decimal>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct EvmTokenInfo {
     pub id: String,
     pub name: String,
     pub symbol: String,
     pub mcap: Decimal,
+    pub fdv: Option<Decimal>,
     pub chain: Chain,
+    pub price_usd: Option<Decimal>,
+    /// Price change over the last 24h in percents
+    pub price_change_24h: Option<Decimal>,
+    pub liquidity_usd: Option<Decimal>,
+    pub volume_24h: Option<Decimal>,
+    pub quote_reserve_usd: Option<Decimal>,
+    /// Moralis categories, e.g. `meme`
+    pub tags: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub holders: Option<u64>,
+    /// Share of the supply held by the top 10 holders in percents
+    pub top10_holders_share: Option<Decimal>,
 }
 
 impl EvmTokenInfo {
@@ -51,6 +209,8 @@ impl EvmTokenInfo {
         let chain = match self.chain {
             Chain::Bsc => "bsc",
             Chain::Base => "base",
+            Chain::Arbitrum => "arb",
+            Chain::Polygon => "polygon",
         };
         format!("https://gmgn.ai/{chain}/token/{}", self.id)
     }
@@ -59,20 +219,26 @@ impl EvmTokenInfo {
         let chain = match self.chain {
             Chain::Bsc => "bsc",
             Chain::Base => "base",
-            // Chain::Arbitrum => "arb",
+            Chain::Arbitrum => "arb",
+            Chain::Polygon => "matic",
             // Chain::Monad => "mon",
         };
 
-        format!("https://www.defined.fi/{chain}/{}", self.id) 
+        format!("https://www.defined.fi/{chain}/{}", self.id)
     }
 
     pub fn dextools_url(&self) -> String {
         let chain = match self.chain {
             Chain::Bsc => "bnb",
             Chain::Base => "base",
+            Chain::Arbitrum => "arbitrum",
+            Chain::Polygon => "polygon",
         };
 
-        format!("https://www.dextools.io/app/en/{chain}/pair-explorer/{}", self.id)
+        format!(
+            "https://www.dextools.io/app/en/{chain}/pair-explorer/{}",
+            self.id
+        )
     }
 
     pub fn uniswap_add_to_usdt_pool(&self) -> String {
@@ -95,6 +261,8 @@ impl EvmTokenInfo {
         let chain = match self.chain {
             Chain::Bsc => "bsc",
             Chain::Base => "base",
+            Chain::Arbitrum => "arb",
+            Chain::Polygon => "polygon",
         };
 
         let base = &self.id;
@@ -107,6 +275,8 @@ impl EvmTokenInfo {
         let chain = match self.chain {
             Chain::Bsc => "bnb",
             Chain::Base => "base",
+            Chain::Arbitrum => "arbitrum",
+            Chain::Polygon => "polygon",
         };
 
         let base = &self.id;
@@ -119,7 +289,8 @@ impl EvmTokenInfo {
         match self.chain {
             Chain::Bsc => "0x55d398326f99059ff775485246999027b3197955",
             Chain::Base => "0xfde4c96c8593536e31f229ea8f37b2ada2699bb2",
-            // Chain::Arbitrum => "0xfd086bc7cd5c481dcc9c85ebe478a1c0b69fcbb9",
+            Chain::Arbitrum => "0xfd086bc7cd5c481dcc9c85ebe478a1c0b69fcbb9",
+            Chain::Polygon => "0xc2132d05d31c914a87c6611c10748aeb04b58e8f",
             // Chain::Monad => "0xe7cd86e13AC4309349F30B3435a9d337750fC82D",
         }
     }
@@ -128,30 +299,57 @@ impl EvmTokenInfo {
         match self.chain {
             Chain::Bsc => "0x8ac76a51cc950d9822d68b83fe1ad97b32cd580d",
             Chain::Base => "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
-            // Chain::Arbitrum => "0xaf88d065e77c8cc2239327c5edb3a432268e5831",
+            Chain::Arbitrum => "0xaf88d065e77c8cc2239327c5edb3a432268e5831",
+            Chain::Polygon => "0x3c499c542cef5e3811e1192ce70d8cc03d5c3359",
             // Chain::Monad => "0x754704bc059f8c67012fed69bc8a327a5aafb603",
         }
     }
 
     pub fn human_readable_mcap(&self) -> String {
         if self.mcap > Decimal::ZERO {
-            format_human_readable(self.mcap, 2)
+            format_mcap_with_fdv(self.mcap, self.fdv)
         } else {
             "??.??K".to_owned()
         }
     }
 
+    pub fn human_readable_price(&self) -> Option<String> {
+        format_price_with_change(self.price_usd, self.price_change_24h)
+    }
+
+    pub fn human_readable_liquidity(&self) -> Option<String> {
+        format_liquidity_with_volume(self.liquidity_usd, self.volume_24h)
+    }
+
+    pub fn human_readable_exit_capacity(&self) -> Option<String> {
+        format_exit_capacity(self.quote_reserve_usd)
+    }
+
+    pub fn human_readable_holders(&self) -> Option<String> {
+        format_holders(self.holders, self.top10_holders_share)
+    }
+
+    pub fn dexscreener_chain_id(&self) -> &'static str {
+        match self.chain {
+            Chain::Bsc => "bsc",
+            Chain::Base => "base",
+            Chain::Arbitrum => "arbitrum",
+            Chain::Polygon => "polygon",
+        }
+    }
+
     pub fn chain_name(&self) -> &str {
         match self.chain {
             Chain::Bsc => "BSC",
             Chain::Base => "BASE",
-            // Chain::Arbitrum => "ARB",
+            Chain::Arbitrum => "ARB",
+            Chain::Polygon => "POL",
             // Chain::Monad => "MON",
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SolanaTokenInfo {
     pub id: String,
     pub name: String,
@@ -161,9 +359,118 @@ pub struct SolanaTokenInfo {
     // for non-graduated tokens jupiter skips mcap field
     // in the response
     pub mcap: Option<Decimal>,
+    pub fdv: Option<Decimal>,
+    #[serde(rename = "usdPrice")]
+    pub price_usd: Option<Decimal>,
+    pub liquidity: Option<Decimal>,
+    #[serde(rename = "stats24h", default)]
+    pub stats_24h: Option<JupiterTokenStats>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub audit: Option<JupiterAudit>,
+    pub twitter: Option<String>,
+    pub website: Option<String>,
+    pub telegram: Option<String>,
+    #[serde(rename = "firstPool", default)]
+    pub first_pool: Option<JupiterPool>,
+    #[serde(rename = "holderCount", default)]
+    pub holders: Option<u64>,
+    /// How far the token is along its launchpad bonding curve in percents
+    #[serde(rename = "bondingCurve", default)]
+    pub bonding_curve: Option<Decimal>,
+    /// The pool the token migrated to once the bonding curve completed
+    #[serde(rename = "graduatedPool", default)]
+    pub graduated_pool: Option<String>,
+    #[serde(rename = "isVerified", default)]
+    pub is_verified: Option<bool>,
+    /// Jupiter's score of the real (non-bot) trading activity, 0-100
+    #[serde(rename = "organicScore", default)]
+    pub organic_score: Option<Decimal>,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JupiterAudit {
+    pub mint_authority_disabled: Option<bool>,
+    pub freeze_authority_disabled: Option<bool>,
+    /// Share of the supply held by the top 10 holders in percents
+    pub top_holders_percentage: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JupiterPool {
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JupiterTokenStats {
+    /// Price change over the period in percents
+    pub price_change: Option<Decimal>,
+    pub buy_volume: Option<Decimal>,
+    pub sell_volume: Option<Decimal>,
+}
+
+/// Segments of the bonding curve progress bar
+const PROGRESS_BAR_LEN: usize = 10;
+
 impl SolanaTokenInfo {
+    /// Badge like `💊 pump.fun` of the launchpad the token was created on
+    pub fn launchpad_badge(&self) -> Option<String> {
+        let launchpad = self.launchpad.as_deref().filter(|l| !l.is_empty())?;
+        let badge = match launchpad.to_lowercase().as_str() {
+            "pump.fun" => "💊 pump.fun".to_owned(),
+            "letsbonk.fun" | "bonk" => "🐕 bonk".to_owned(),
+            "moonshot" => "🌙 moonshot".to_owned(),
+            _ => format!("🚀 {launchpad}"),
+        };
+        Some(badge)
+    }
+
+    pub fn is_graduated(&self) -> bool {
+        self.graduated_pool.is_some()
+            || self
+                .bonding_curve
+                .is_some_and(|progress| progress >= Decimal::ONE_HUNDRED)
+    }
+
+    /// Line like `▰▰▰▱▱▱▱▱▱▱ 34% bonded` for tokens still on the bonding curve, not escaped
+    pub fn bonding_curve_progress(&self) -> Option<String> {
+        if self.is_graduated() {
+            return None;
+        }
+
+        let progress = self
+            .bonding_curve?
+            .clamp(Decimal::ZERO, Decimal::ONE_HUNDRED);
+        let filled = (progress * Decimal::from(PROGRESS_BAR_LEN) / Decimal::ONE_HUNDRED)
+            .floor()
+            .to_usize()
+            .unwrap_or_default();
+        Some(format!(
+            "{}{} {progress:.0}% bonded",
+            "▰".repeat(filled),
+            "▱".repeat(PROGRESS_BAR_LEN - filled)
+        ))
+    }
+
+    pub fn has_socials(&self) -> bool {
+        [&self.twitter, &self.website, &self.telegram]
+            .iter()
+            .any(|link| link.as_deref().is_some_and(|link| !link.is_empty()))
+    }
+
+    /// When the first pool of the token was created
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        self.first_pool
+            .as_ref()?
+            .created_at
+            .as_deref()
+            .and_then(parse_timestamp)
+    }
+
     pub fn trenchradar_url(&self) -> String {
         format!("https://trench.bot/bundles/{}", self.id)
     }
@@ -184,10 +491,47 @@ impl SolanaTokenInfo {
         format!("https://jup.ag/tokens/{}", self.id)
     }
 
+    pub fn price_change_24h(&self) -> Option<Decimal> {
+        self.stats_24h.as_ref().and_then(|stats| stats.price_change)
+    }
+
+    pub fn human_readable_price(&self) -> Option<String> {
+        format_price_with_change(self.price_usd, self.price_change_24h())
+    }
+
+    pub fn volume_24h(&self) -> Option<Decimal> {
+        let stats = self.stats_24h.as_ref()?;
+        match (stats.buy_volume, stats.sell_volume) {
+            (None, None) => None,
+            (buy, sell) => Some(buy.unwrap_or_default() + sell.unwrap_or_default()),
+        }
+    }
+
+    pub fn human_readable_liquidity(&self) -> Option<String> {
+        format_liquidity_with_volume(self.liquidity, self.volume_24h())
+    }
+
+    /// Jupiter has no pool reserves, so the quote side is assumed to be half of the liquidity
+    pub fn human_readable_exit_capacity(&self) -> Option<String> {
+        format_exit_capacity(self.liquidity.map(|liquidity| liquidity / Decimal::TWO))
+    }
+
+    pub fn human_readable_holders(&self) -> Option<String> {
+        let top10_share = self
+            .audit
+            .as_ref()
+            .and_then(|audit| audit.top_holders_percentage);
+        format_holders(self.holders, top10_share)
+    }
+
     pub fn human_readable_mcap(&self) -> String {
         match self.mcap {
-            Some(mcap) if mcap > Decimal::ZERO => format_human_readable(mcap, 2),
+            Some(mcap) if mcap > Decimal::ZERO => format_mcap_with_fdv(mcap, self.fdv),
             _ => {
+                // Jupiter skips mcap of the tokens still on the bonding curve
+                if let Some(progress) = self.bonding_curve_progress() {
+                    return progress;
+                }
                 warn!("Token {} has no mcap", self.id);
                 "??.??K".to_owned()
             }
@@ -195,31 +539,418 @@ impl SolanaTokenInfo {
     }
 }
 
+/// Why a token couldn't be looked up
+#[derive(Debug, Clone)]
+pub enum TokenInfoError {
+    /// The provider doesn't know the token, usually the CA is on another chain or made up
+    NotFound,
+    /// Timeouts, connection errors and 5xx responses
+    ProviderUnavailable(String),
+    /// The provider isn't called at all until its circuit closes again
+    CircuitOpen(Provider),
+    RateLimited,
+    /// The provider responded with something we can't parse
+    Decode(String),
+    Other(String),
+}
+
+impl fmt::Display for TokenInfoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenInfoError::NotFound => write!(f, "token not found"),
+            TokenInfoError::ProviderUnavailable(err) => {
+                write!(f, "provider is unavailable - {err}")
+            }
+            TokenInfoError::CircuitOpen(provider) => write!(f, "{provider:?} circuit is open"),
+            TokenInfoError::RateLimited => write!(f, "rate limited by the provider"),
+            TokenInfoError::Decode(err) => write!(f, "failed to decode the response - {err}"),
+            TokenInfoError::Other(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for TokenInfoError {}
+
+impl From<anyhow::Error> for TokenInfoError {
+    fn from(err: anyhow::Error) -> Self {
+        if let Some(err) = err.downcast_ref::<TokenInfoError>() {
+            return err.clone();
+        }
+        if let Some(CircuitOpen(provider)) = err.downcast_ref::<CircuitOpen>() {
+            return TokenInfoError::CircuitOpen(*provider);
+        }
+        if is_provider_outage(&err) {
+            return TokenInfoError::ProviderUnavailable(format!("{err:#}"));
+        }
+
+        match err.downcast_ref::<reqwest::Error>() {
+            Some(e) if e.status() == Some(StatusCode::NOT_FOUND) => TokenInfoError::NotFound,
+            Some(e) if e.status() == Some(StatusCode::TOO_MANY_REQUESTS) => {
+                TokenInfoError::RateLimited
+            }
+            Some(e) if e.is_decode() => TokenInfoError::Decode(format!("{err:#}")),
+            _ => TokenInfoError::Other(format!("{err:#}")),
+        }
+    }
+}
+
+static TOKEN_INFO_LOOKUPS: LazyLock<SingleFlight<TokenInfoKey, CachedTokenInfo, TokenInfoError>> =
+    LazyLock::new(SingleFlight::default);
+
+/// Serves the lookup from the cache, or runs it making sure the same token
+/// isn't looked up several times concurrently
+async fn lookup_token_info(
+    key: TokenInfoKey,
+    lookup: impl Future<Output = Result<CachedTokenInfo, TokenInfoError>>,
+) -> Result<CachedTokenInfo, TokenInfoError> {
+    if let Some(info) = caches().token_info.get(&key).await {
+        return Ok(info);
+    }
+
+    TOKEN_INFO_LOOKUPS
+        .run(key.clone(), async {
+            let info = lookup.await?;
+            caches().token_info.insert(key, info.clone()).await;
+            Ok(info)
+        })
+        .await
+}
+
 pub async fn retrieve_solana_token_info(
     token_ca: &str,
     client: reqwest::Client,
+) -> Result<SolanaTokenInfo, TokenInfoError> {
+    let key = ("solana", token_ca.to_owned());
+    let lookup = async {
+        let jupiter = fetch_solana_token_info(token_ca, client.clone());
+        let info = match guarded(Provider::Jupiter, jupiter)
+            .await
+            .map_err(TokenInfoError::from)
+        {
+            // Jupiter's search misses brand-new mints now and then
+            Err(TokenInfoError::NotFound) => {
+                let Some(api_key) = &APP_CONFIG.get().unwrap().birdeye_token else {
+                    return Err(TokenInfoError::NotFound);
+                };
+                debug!("Token {token_ca} not found on Jupiter - trying Birdeye");
+                let birdeye = fetch_birdeye_token_info(token_ca, api_key, client);
+                guarded(Provider::Birdeye, birdeye).await?
+            }
+            result => result?,
+        };
+        Ok(CachedTokenInfo::Solana(info))
+    };
+
+    match lookup_token_info(key, lookup).await? {
+        CachedTokenInfo::Solana(info) => Ok(info),
+        other => Err(anyhow!("Unexpected token info {other:?} for {token_ca}").into()),
+    }
+}
+
+async fn fetch_solana_token_info(
+    token_ca: &str,
+    client: reqwest::Client,
 ) -> anyhow::Result<SolanaTokenInfo> {
     let cfg = APP_CONFIG.get().unwrap();
-    let url = format!("https://api.jup.ag/tokens/v2/search?query={token_ca}");
+    let url = format!(
+        "{}/tokens/v2/search?query={token_ca}",
+        cfg.app_config().provider_urls.jupiter
+    );
 
-    let mut response = client
-        .get(url)
-        .header("x-api-key", cfg.jup_token.as_str())
-        .send()
+    let request = client.get(url).header("x-api-key", cfg.jup_token.as_str());
+    let response = send_with_retry(request)
         .await?
         .error_for_status()?
         .json::<Vec<SolanaTokenInfo>>()
         .await?;
 
-    response.pop().ok_or(anyhow!("Token CA {token_ca} not found on Jupiter"))
+    if response.len() > 1 {
+        let candidates: Vec<&str> = response.iter().map(|info| info.id.as_str()).collect();
+        debug!("Jupiter search for {token_ca} returned several tokens - {candidates:?}");
+    }
+
+    Ok(pick_search_result(token_ca, response).ok_or(TokenInfoError::NotFound)?)
+}
+
+/// The search matches symbols and name parts too, so the exact mint wins,
+/// then verified tokens, then the organic score and mcap
+fn pick_search_result(token_ca: &str, results: Vec<SolanaTokenInfo>) -> Option<SolanaTokenInfo> {
+    results.into_iter().max_by_key(|info| {
+        (
+            info.id == token_ca,
+            info.is_verified.unwrap_or(false),
+            info.organic_score.unwrap_or_default(),
+            info.mcap.unwrap_or_default(),
+        )
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct TonJettonSerialized {
+    metadata: TonJettonMetadata,
+}
+
+#[derive(Debug, Deserialize)]
+struct TonJettonMetadata {
+    name: String,
+    symbol: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TonTokenInfo {
+    pub id: String,
+    pub name: String,
+    pub symbol: String,
+    pub mcap: Option<Decimal>,
+    pub fdv: Option<Decimal>,
+    pub price_usd: Option<Decimal>,
+    /// Price change over the last 24h in percents
+    pub price_change_24h: Option<Decimal>,
+    pub liquidity_usd: Option<Decimal>,
+    pub volume_24h: Option<Decimal>,
+    pub quote_reserve_usd: Option<Decimal>,
+}
+
+impl TonTokenInfo {
+    pub fn gmgn_url(&self) -> String {
+        format!("https://gmgn.ai/ton/token/{}", self.id)
+    }
+
+    pub fn dexscreener_url(&self) -> String {
+        format!("https://dexscreener.com/ton/{}", self.id)
+    }
+
+    pub fn tonviewer_url(&self) -> String {
+        format!("https://tonviewer.com/{}", self.id)
+    }
+
+    pub fn human_readable_price(&self) -> Option<String> {
+        format_price_with_change(self.price_usd, self.price_change_24h)
+    }
+
+    pub fn human_readable_liquidity(&self) -> Option<String> {
+        format_liquidity_with_volume(self.liquidity_usd, self.volume_24h)
+    }
+
+    pub fn human_readable_exit_capacity(&self) -> Option<String> {
+        format_exit_capacity(self.quote_reserve_usd)
+    }
+
+    pub fn human_readable_mcap(&self) -> String {
+        match self.mcap {
+            Some(mcap) if mcap > Decimal::ZERO => format_mcap_with_fdv(mcap, self.fdv),
+            _ => {
+                warn!("Token {} has no mcap", self.id);
+                "??.??K".to_owned()
+            }
+        }
+    }
+}
+
+pub async fn retrieve_ton_token_info(
+    token_ca: &str,
+    client: reqwest::Client,
+) -> Result<TonTokenInfo, TokenInfoError> {
+    let key = ("ton", token_ca.to_owned());
+    let lookup = async {
+        let info = guarded(Provider::TonApi, fetch_ton_token_info(token_ca, client)).await?;
+        Ok(CachedTokenInfo::Ton(info))
+    };
+
+    match lookup_token_info(key, lookup).await? {
+        CachedTokenInfo::Ton(info) => Ok(info),
+        other => Err(anyhow!("Unexpected token info {other:?} for {token_ca}").into()),
+    }
+}
+
+/// Jetton metadata comes from TonAPI, market data from the most liquid DexScreener pair
+async fn fetch_ton_token_info(
+    token_ca: &str,
+    client: reqwest::Client,
+) -> anyhow::Result<TonTokenInfo> {
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+    let url = format!("{}/v2/jettons/{token_ca}", app_config.provider_urls.tonapi);
+    debug!("Going to hit url - {url}");
+
+    let jetton = send_with_retry(client.get(url))
+        .await?
+        .error_for_status()?
+        .json::<TonJettonSerialized>()
+        .await?;
+
+    let mut info = TonTokenInfo {
+        id: token_ca.to_owned(),
+        name: jetton.metadata.name,
+        symbol: jetton.metadata.symbol,
+        mcap: None,
+        fdv: None,
+        price_usd: None,
+        price_change_24h: None,
+        liquidity_usd: None,
+        volume_24h: None,
+        quote_reserve_usd: None,
+    };
+
+    match retrieve_token_pair("ton", token_ca, client).await {
+        Ok(Some(pair)) => {
+            info.mcap = pair.market_cap;
+            info.fdv = pair.fdv;
+            info.price_usd = pair.price_usd;
+            info.price_change_24h = pair.price_change.h24;
+            info.quote_reserve_usd = pair.quote_reserve_usd();
+            info.liquidity_usd = pair.liquidity.usd;
+            info.volume_24h = pair.volume.h24;
+        }
+        Ok(None) => {}
+        Err(err) => {
+            warn!("Failed to retrieve price for {token_ca} on TON - {err:?}");
+        }
+    }
+
+    Ok(info)
+}
+
+#[derive(Debug, Deserialize)]
+struct TronScanTokensResponse {
+    #[serde(default)]
+    trc20_tokens: Vec<TronScanToken>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TronScanToken {
+    contract_address: String,
+    name: String,
+    symbol: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TronTokenInfo {
+    pub id: String,
+    pub name: String,
+    pub symbol: String,
+    pub mcap: Option<Decimal>,
+    pub fdv: Option<Decimal>,
+    pub price_usd: Option<Decimal>,
+    /// Price change over the last 24h in percents
+    pub price_change_24h: Option<Decimal>,
+    pub liquidity_usd: Option<Decimal>,
+    pub volume_24h: Option<Decimal>,
+    pub quote_reserve_usd: Option<Decimal>,
+}
+
+impl TronTokenInfo {
+    pub fn sunpump_url(&self) -> String {
+        format!("https://sunpump.meme/token/{}", self.id)
+    }
+
+    pub fn dexscreener_url(&self) -> String {
+        format!("https://dexscreener.com/tron/{}", self.id)
+    }
+
+    pub fn tronscan_url(&self) -> String {
+        format!("https://tronscan.org/#/token20/{}", self.id)
+    }
+
+    pub fn human_readable_price(&self) -> Option<String> {
+        format_price_with_change(self.price_usd, self.price_change_24h)
+    }
+
+    pub fn human_readable_liquidity(&self) -> Option<String> {
+        format_liquidity_with_volume(self.liquidity_usd, self.volume_24h)
+    }
+
+    pub fn human_readable_exit_capacity(&self) -> Option<String> {
+        format_exit_capacity(self.quote_reserve_usd)
+    }
+
+    pub fn human_readable_mcap(&self) -> String {
+        match self.mcap {
+            Some(mcap) if mcap > Decimal::ZERO => format_mcap_with_fdv(mcap, self.fdv),
+            _ => {
+                warn!("Token {} has no mcap", self.id);
+                "??.??K".to_owned()
+            }
+        }
+    }
+}
+
+pub async fn retrieve_tron_token_info(
+    token_ca: &str,
+    client: reqwest::Client,
+) -> Result<TronTokenInfo, TokenInfoError> {
+    let key = ("tron", token_ca.to_owned());
+    let lookup = async {
+        let info = guarded(Provider::TronScan, fetch_tron_token_info(token_ca, client)).await?;
+        Ok(CachedTokenInfo::Tron(info))
+    };
+
+    match lookup_token_info(key, lookup).await? {
+        CachedTokenInfo::Tron(info) => Ok(info),
+        other => Err(anyhow!("Unexpected token info {other:?} for {token_ca}").into()),
+    }
+}
+
+/// Token metadata comes from TronScan, market data from the most liquid DexScreener pair
+async fn fetch_tron_token_info(
+    token_ca: &str,
+    client: reqwest::Client,
+) -> anyhow::Result<TronTokenInfo> {
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+    let url = format!("{}/api/token_trc20", app_config.provider_urls.tronscan);
+    debug!("Going to hit url - {url}");
+
+    let request = client
+        .get(url)
+        .query(&[("contract", token_ca), ("showAll", "1")]);
+    let token = send_with_retry(request)
+        .await?
+        .error_for_status()?
+        .json::<TronScanTokensResponse>()
+        .await?
+        .trc20_tokens
+        .into_iter()
+        .find(|token| token.contract_address == token_ca)
+        .ok_or(TokenInfoError::NotFound)?;
+
+    let mut info = TronTokenInfo {
+        id: token_ca.to_owned(),
+        name: token.name,
+        symbol: token.symbol,
+        mcap: None,
+        fdv: None,
+        price_usd: None,
+        price_change_24h: None,
+        liquidity_usd: None,
+        volume_24h: None,
+        quote_reserve_usd: None,
+    };
+
+    match retrieve_token_pair("tron", token_ca, client).await {
+        Ok(Some(pair)) => {
+            info.mcap = pair.market_cap;
+            info.fdv = pair.fdv;
+            info.price_usd = pair.price_usd;
+            info.price_change_24h = pair.price_change.h24;
+            info.quote_reserve_usd = pair.quote_reserve_usd();
+            info.liquidity_usd = pair.liquidity.usd;
+            info.volume_24h = pair.volume.h24;
+        }
+        Ok(None) => {}
+        Err(err) => {
+            warn!("Failed to retrieve price for {token_ca} on Tron - {err:?}");
+        }
+    }
+
+    Ok(info)
 }
 
 pub static SOLANA_TOKEN_CA_REGEX: OnceLock<Regex> = OnceLock::new();
 
 pub fn init_solana_token_ca_regex() {
     // this is safe as long as the regex itself is valid
+    // launchpad links (pump.fun, letsbonk.fun, moonshot) are shared far more often than raw mints
     let regex = RegexBuilder::new(
-        "(?:https:\\/\\/gmgn\\.ai\\/sol\\/token\\/(?:[a-zA-Z0-9]{4,10}_)?|https:\\/\\/jup\\.ag\\/tokens\\/|^|\\s)(?P<token_ca>[1-9A-HJ-NP-Za-km-z]{32,44})",
+        "(?:https:\\/\\/gmgn\\.ai\\/sol\\/token\\/(?:[a-zA-Z0-9]{4,10}_)?|https:\\/\\/jup\\.ag\\/tokens\\/|https:\\/\\/(?:www\\.)?pump\\.fun\\/(?:coin\\/)?|https:\\/\\/(?:www\\.)?(?:letsbonk|bonk)\\.fun\\/token\\/|https:\\/\\/(?:www\\.)?moonshot\\.(?:money|com)\\/(?:token\\/)?|^|\\s)(?P<token_ca>[1-9A-HJ-NP-Za-km-z]{32,44})",
     )
     .multi_line(true)
     .build()
@@ -228,6 +959,62 @@ pub fn init_solana_token_ca_regex() {
     SOLANA_TOKEN_CA_REGEX.set(regex).unwrap();
 }
 
+pub static TON_TOKEN_CA_REGEX: OnceLock<Regex> = OnceLock::new();
+
+pub fn init_ton_token_ca_regex() {
+    // this is safe as long as the regex itself is valid
+    // user-friendly (base64url, bounceable or not) and raw (workchain:hex) addresses
+    let regex = RegexBuilder::new(
+        "(?:https:\\/\\/gmgn\\.ai\\/ton\\/token\\/(?:[a-zA-Z0-9]{4,10}_)?|https:\\/\\/tonviewer\\.com\\/|^|\\s)(?P<token_ca>(?:EQ|UQ)[A-Za-z0-9_-]{46}|-?[01]:[a-fA-F0-9]{64})",
+    )
+    .multi_line(true)
+    .build()
+    .unwrap();
+    // This is safe if init_ton_token_ca_regex is called just once directly in the main fn
+    TON_TOKEN_CA_REGEX.set(regex).unwrap();
+}
+
+pub fn extract_ton_cas(text: &str) -> Vec<String> {
+    extract_cas(TON_TOKEN_CA_REGEX.get().unwrap(), text)
+}
+
+pub static TRON_TOKEN_CA_REGEX: OnceLock<Regex> = OnceLock::new();
+
+pub fn init_tron_token_ca_regex() {
+    // this is safe as long as the regex itself is valid
+    // Tron addresses are always 34 chars long, the trailing \b keeps longer Solana CAs out
+    let regex = RegexBuilder::new(
+        "(?:https:\\/\\/sunpump\\.meme\\/token\\/|https:\\/\\/tronscan\\.org\\/#\\/token20\\/|^|\\s)(?P<token_ca>T[1-9A-HJ-NP-Za-km-z]{33})\\b",
+    )
+    .multi_line(true)
+    .build()
+    .unwrap();
+    // This is safe if init_tron_token_ca_regex is called just once directly in the main fn
+    TRON_TOKEN_CA_REGEX.set(regex).unwrap();
+}
+
+pub fn extract_tron_cas(text: &str) -> Vec<String> {
+    extract_cas(TRON_TOKEN_CA_REGEX.get().unwrap(), text)
+}
+
+pub fn extract_solana_cas(text: &str) -> Vec<String> {
+    extract_cas(SOLANA_TOKEN_CA_REGEX.get().unwrap(), text)
+}
+
+pub fn extract_evm_cas(text: &str) -> Vec<String> {
+    extract_cas(EVM_TOKEN_CA_REGEX.get().unwrap(), text)
+}
+
+fn extract_cas(regex: &Regex, text: &str) -> Vec<String> {
+    let mut cas: Vec<String> = Vec::new();
+    for (_, [token_ca]) in regex.captures_iter(text).map(|c| c.extract()) {
+        if !cas.iter().any(|ca| ca == token_ca) {
+            cas.push(token_ca.to_owned());
+        }
+    }
+    cas
+}
+
 pub static EVM_TOKEN_CA_REGEX: OnceLock<Regex> = OnceLock::new();
 
 pub fn init_evm_token_ca_regex() {
@@ -242,37 +1029,108 @@ pub fn init_evm_token_ca_regex() {
     EVM_TOKEN_CA_REGEX.set(regex).unwrap();
 }
 
-#[derive(Debug, Copy, Clone, Deserialize)]
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Chain {
     Bsc,
     Base,
-    // Arbitrum,
+    Arbitrum,
+    Polygon,
     // Monad,
 }
 
+impl Chain {
+    pub fn from_dexscreener_id(chain_id: &str) -> Option<Chain> {
+        match chain_id {
+            "bsc" => Some(Chain::Bsc),
+            "base" => Some(Chain::Base),
+            "arbitrum" => Some(Chain::Arbitrum),
+            "polygon" => Some(Chain::Polygon),
+            _ => None,
+        }
+    }
+
+    pub fn dexscreener_id(&self) -> &'static str {
+        match self {
+            Chain::Bsc => "bsc",
+            Chain::Base => "base",
+            Chain::Arbitrum => "arbitrum",
+            Chain::Polygon => "polygon",
+        }
+    }
+}
+
 pub async fn retrieve_evm_token_info(
     token_ca: &str,
     chain: Chain,
     client: reqwest::Client,
+) -> Result<EvmTokenInfo, TokenInfoError> {
+    let key = (chain.dexscreener_id(), token_ca.to_owned());
+    let lookup = async {
+        let fetch = fetch_evm_token_info(token_ca, chain, client);
+        let info = guarded(Provider::Moralis, fetch).await?;
+        Ok(CachedTokenInfo::Evm(info))
+    };
+
+    match lookup_token_info(key, lookup).await? {
+        CachedTokenInfo::Evm(info) => Ok(info),
+        other => Err(anyhow!("Unexpected token info {other:?} for {token_ca}").into()),
+    }
+}
+
+/// Looks the token up on all the chains at once. The same CA may be deployed on several
+/// chains, then the most liquid one wins, ties going to the earlier chain in the list.
+pub async fn probe_evm_chains(
+    token_ca: &str,
+    chains: &[Chain],
+    client: reqwest::Client,
+) -> Result<EvmTokenInfo, Vec<(Chain, TokenInfoError)>> {
+    let lookups = chains
+        .iter()
+        .map(|&chain| retrieve_evm_token_info(token_ca, chain, client.clone()));
+    let results = futures::future::join_all(lookups).await;
+
+    let mut found = Vec::new();
+    let mut errors = Vec::new();
+    for (&chain, result) in chains.iter().zip(results) {
+        match result {
+            Ok(info) => found.push(info),
+            Err(err) => errors.push((chain, err)),
+        }
+    }
+
+    found
+        .into_iter()
+        .rev()
+        .max_by_key(|info| info.liquidity_usd)
+        .ok_or(errors)
+}
+
+async fn fetch_evm_token_info(
+    token_ca: &str,
+    chain: Chain,
+    client: reqwest::Client,
 ) -> anyhow::Result<EvmTokenInfo> {
     let chain_str = match chain {
         Chain::Bsc => "bsc",
         Chain::Base => "base",
-        // Chain::Arbitrum => "arbitrum",
+        Chain::Arbitrum => "arbitrum",
+        Chain::Polygon => "polygon",
         // Chain::Monad => "monad",
     };
 
     let cfg = APP_CONFIG.get().unwrap();
 
-    let url = "https://deep-index.moralis.io/api/v2.2/erc20/metadata";
+    let url = format!(
+        "{}/api/v2.2/erc20/metadata",
+        cfg.app_config().provider_urls.moralis
+    );
     debug!("Going to hit url - {url}");
 
-    let mut response = client
+    let request = client
         .get(url)
-        .query(&[("chain", chain_str), ("addresses[0]", token_ca)])
-        .header("X-API-Key", cfg.moralis_token.as_str())
-        .send()
+        .query(&[("chain", chain_str), ("addresses[0]", token_ca)]);
+    let mut response = send_moralis(request, TOKEN_METADATA_CU)
         .await?
         .error_for_status()?
         .json::<Vec<EvmTokenInfoSerialized>>()
@@ -280,10 +1138,11 @@ pub async fn retrieve_evm_token_info(
 
     let mut response = response
         .pop()
-        .ok_or(anyhow!("Token CA {token_ca} not found on Moralis at all"))
+        .ok_or(TokenInfoError::NotFound.into())
         .and_then(|info| {
             if info.created_at.is_none() {
-                return Err(anyhow!("Token {token_ca} not found on {chain:?}"));
+                debug!("Token {token_ca} not found on {chain:?}");
+                return Err(TokenInfoError::NotFound.into());
             }
 
             Ok(EvmTokenInfo {
@@ -291,13 +1150,56 @@ pub async fn retrieve_evm_token_info(
                 name: info.name,
                 symbol: info.symbol,
                 mcap: info.market_cap,
+                fdv: info.fully_diluted_valuation,
                 chain,
+                price_usd: None,
+                price_change_24h: None,
+                liquidity_usd: None,
+                volume_24h: None,
+                quote_reserve_usd: None,
+                tags: info.categories,
+                created_at: info.created_at.as_deref().and_then(parse_timestamp),
+                holders: None,
+                top10_holders_share: None,
             })
         });
 
+    // Moralis metadata has no market data, so take it from the most liquid DexScreener pair
+    if let Ok(info) = response.as_mut() {
+        match retrieve_token_pair(info.dexscreener_chain_id(), token_ca, client.clone()).await {
+            Ok(Some(pair)) => {
+                info.price_usd = pair.price_usd;
+                info.price_change_24h = pair.price_change.h24;
+                info.fdv = info.fdv.or(pair.fdv);
+                info.quote_reserve_usd = pair.quote_reserve_usd();
+                info.liquidity_usd = pair.liquidity.usd;
+                info.volume_24h = pair.volume.h24;
+            }
+            Ok(None) => {}
+            Err(err) => {
+                warn!("Failed to retrieve price for {token_ca} on {chain:?} - {err:?}");
+            }
+        }
+    }
+
+    if let Ok(info) = response.as_mut() {
+        match fetch_evm_holders(token_ca, chain_str, client).await {
+            Ok(holders) => {
+                info.holders = holders.total_holders;
+                info.top10_holders_share = holders
+                    .holder_supply
+                    .and_then(|supply| supply.top10)
+                    .and_then(|top10| top10.supply_percent);
+            }
+            Err(err) => {
+                warn!("Failed to retrieve holders for {token_ca} on {chain:?} - {err:?}");
+            }
+        }
+    }
+
     if let Ok(info) = response.as_mut()
         && is_cjk_only(&info.name)
-        && let Ok(translation) = translate_to_english(&info.name).await
+        && let Some(translation) = translate_token_name(&info.name).await
     {
         let new_name = format!("{} ({})", info.name, translation);
         info.name = new_name;
@@ -306,8 +1208,53 @@ pub async fn retrieve_evm_token_info(
     response
 }
 
-pub async fn translate_token_name() {
+async fn fetch_evm_holders(
+    token_ca: &str,
+    chain: &str,
+    client: reqwest::Client,
+) -> anyhow::Result<MoralisHolders> {
+    let cfg = APP_CONFIG.get().unwrap();
+    let url = format!(
+        "{}/api/v2.2/erc20/{token_ca}/holders",
+        cfg.app_config().provider_urls.moralis
+    );
+    debug!("Going to hit url - {url}");
+
+    let request = client.get(url).query(&[("chain", chain)]);
+    let holders = send_moralis(request, TOKEN_HOLDERS_CU)
+        .await?
+        .error_for_status()?
+        .json::<MoralisHolders>()
+        .await?;
+
+    Ok(holders)
+}
+
+pub async fn translate_token_name(name: &str) -> Option<String> {
+    let translations = &caches().translations;
+    let key = name.to_owned();
+
+    if let Some(translation) = translations.get(&key).await {
+        return Some(translation);
+    }
+
+    // the error type isn't Send so it must not be held across the await below
+    let translation = match translate_to_english(name).await {
+        Ok(translation) => translation,
+        Err(err) => {
+            warn!("Failed to translate token name {name} - {err:?}");
+            return None;
+        }
+    };
+
+    translations.insert(key, translation.clone()).await;
+    Some(translation)
+}
 
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
 }
 
 fn is_cjk_only(s: &str) -> bool {
@@ -0,0 +1,231 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use regex::Regex;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+
+use crate::APP_CONFIG;
+use crate::dexscreener::search_pairs;
+use crate::retry::send_with_retry;
+use crate::storage::{find_listed_tokens, replace_token_list};
+
+/// Resolving `$SYMBOL` cashtags to token addresses. Established tokens are
+/// looked up in the token lists kept in storage, others via DexScreener search.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TokenListsConfig {
+    pub enabled: bool,
+    /// Token list in the Uniswap format, only BSC and Base tokens are used
+    pub evm_list_url: String,
+    pub refresh_hours: u64,
+}
+
+impl Default for TokenListsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            evm_list_url: "https://tokens.uniswap.org".to_owned(),
+            refresh_hours: 24,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ListedToken {
+    /// DexScreener chain id
+    pub chain_id: &'static str,
+    pub symbol: String,
+    pub token_ca: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct JupiterListedToken {
+    id: String,
+    symbol: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EvmListedToken {
+    chain_id: u64,
+    address: String,
+    symbol: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EvmTokenList {
+    tokens: Vec<EvmListedToken>,
+}
+
+static CASHTAG_REGEX: OnceLock<Regex> = OnceLock::new();
+
+pub fn init_cashtag_regex() {
+    // This is safe if init_cashtag_regex is called just once directly in the main fn
+    CASHTAG_REGEX
+        .set(Regex::new(r"\$([A-Za-z][A-Za-z0-9]{1,9})\b").unwrap())
+        .unwrap();
+}
+
+async fn fetch_jupiter_verified_tokens(
+    client: reqwest::Client,
+) -> anyhow::Result<Vec<ListedToken>> {
+    let cfg = APP_CONFIG.get().unwrap();
+    let url = format!(
+        "{}/tokens/v2/tag?query=verified",
+        cfg.app_config().provider_urls.jupiter
+    );
+    debug!("Going to hit url - {url}");
+
+    let request = client.get(url).header("x-api-key", cfg.jup_token.as_str());
+    let tokens = send_with_retry(request)
+        .await?
+        .error_for_status()?
+        .json::<Vec<JupiterListedToken>>()
+        .await?;
+
+    Ok(tokens
+        .into_iter()
+        .map(|token| ListedToken {
+            chain_id: "solana",
+            symbol: token.symbol,
+            token_ca: token.id,
+        })
+        .collect())
+}
+
+async fn fetch_evm_tokens(client: reqwest::Client) -> anyhow::Result<Vec<ListedToken>> {
+    let url = APP_CONFIG
+        .get()
+        .unwrap()
+        .app_config()
+        .token_lists
+        .evm_list_url
+        .clone();
+    debug!("Going to hit url - {url}");
+
+    let list = send_with_retry(client.get(url))
+        .await?
+        .error_for_status()?
+        .json::<EvmTokenList>()
+        .await?;
+
+    Ok(list
+        .tokens
+        .into_iter()
+        .filter_map(|token| {
+            let chain_id = match token.chain_id {
+                56 => "bsc",
+                8453 => "base",
+                42161 => "arbitrum",
+                137 => "polygon",
+                _ => return None,
+            };
+            Some(ListedToken {
+                chain_id,
+                symbol: token.symbol,
+                token_ca: token.address,
+            })
+        })
+        .collect())
+}
+
+async fn refresh_token_lists(storage: &SqlitePool, client: reqwest::Client) {
+    match fetch_jupiter_verified_tokens(client.clone()).await {
+        Ok(tokens) => {
+            if let Err(e) = replace_token_list(storage, "jupiter", &tokens).await {
+                warn!("Failed to save Jupiter token list - {e:?}");
+            }
+        }
+        Err(e) => warn!("Failed to retrieve Jupiter token list - {e:?}"),
+    }
+
+    match fetch_evm_tokens(client).await {
+        Ok(tokens) => {
+            if let Err(e) = replace_token_list(storage, "evm", &tokens).await {
+                warn!("Failed to save EVM token list - {e:?}");
+            }
+        }
+        Err(e) => warn!("Failed to retrieve EVM token list - {e:?}"),
+    }
+}
+
+/// Refreshes the stored token lists on startup and then periodically, the lists
+/// from the previous run are used until the first refresh is done
+pub fn spawn_token_list_refresher(storage: SqlitePool, client: reqwest::Client) {
+    tokio::spawn(async move {
+        loop {
+            let cfg = APP_CONFIG.get().unwrap().app_config();
+            if cfg.token_lists.enabled {
+                refresh_token_lists(&storage, client.clone()).await;
+            }
+
+            let refresh_interval = Duration::from_secs(cfg.token_lists.refresh_hours.max(1) * 3600);
+            tokio::time::sleep(refresh_interval).await;
+        }
+    });
+}
+
+/// The most liquid DexScreener pair with exactly this symbol
+async fn search_token_by_symbol(symbol: &str, client: reqwest::Client) -> Option<String> {
+    let pairs = match search_pairs(symbol, client).await {
+        Ok(pairs) => pairs,
+        Err(e) => {
+            warn!("Failed to search DexScreener for ${symbol} - {e:?}");
+            return None;
+        }
+    };
+
+    pairs
+        .into_iter()
+        .filter(|pair| pair.base_token.symbol.eq_ignore_ascii_case(symbol))
+        .filter(|pair| {
+            matches!(
+                pair.chain_id.as_str(),
+                "solana" | "bsc" | "base" | "arbitrum" | "polygon" | "ton" | "tron"
+            )
+        })
+        .max_by_key(|pair| pair.liquidity_usd())
+        .map(|pair| pair.base_token.address)
+}
+
+/// Addresses of the tokens mentioned as `$SYMBOL` in the text, separated by spaces
+pub async fn find_cashtag_cas(storage: &SqlitePool, text: &str, client: reqwest::Client) -> String {
+    if !APP_CONFIG.get().unwrap().app_config().token_lists.enabled {
+        return String::new();
+    }
+
+    let mut token_cas = Vec::new();
+    for captures in CASHTAG_REGEX.get().unwrap().captures_iter(text) {
+        token_cas.extend(resolve_symbol(storage, &captures[1], client.clone()).await);
+    }
+
+    token_cas.join(" ")
+}
+
+/// Addresses of the tokens with the symbol, from the token lists or else the most liquid
+/// DexScreener match
+pub async fn resolve_symbol(
+    storage: &SqlitePool,
+    symbol: &str,
+    client: reqwest::Client,
+) -> Vec<String> {
+    let listed = find_listed_tokens(storage, symbol)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to look up ${symbol} in the token lists - {e:?}");
+            Vec::new()
+        });
+
+    if !listed.is_empty() {
+        debug!("Resolved ${symbol} to {listed:?} via the token lists");
+        return listed;
+    }
+
+    let token_ca = search_token_by_symbol(symbol, client).await;
+    if let Some(token_ca) = &token_ca {
+        info!("Resolved ${symbol} to {token_ca} via DexScreener");
+    }
+    token_ca.into_iter().collect()
+}
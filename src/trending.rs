@@ -0,0 +1,124 @@
+use log::{debug, warn};
+use teloxide::Bot;
+use teloxide::payloads::SendMessageSetters;
+use teloxide::prelude::Requester;
+use teloxide::sugar::request::{RequestLinkPreviewExt, RequestReplyExt};
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, Message};
+
+use crate::APP_CONFIG;
+use crate::dexscreener::{dexscreener_chain_id, retrieve_token_pairs, retrieve_top_boosted_tokens};
+use crate::fmt::MessageBuilder;
+use crate::formatter::telegram_text;
+use crate::sanitize::sanitize_text;
+use crate::token_info::{format_human_readable, format_price_change};
+
+/// Prefix of the callback data attached to the "expand" buttons of the trending list
+pub const EXPAND_CALLBACK_PREFIX: &str = "expand:";
+
+const DEFAULT_TRENDING_CHAIN: &str = "sol";
+
+/// DexScreener has no organic trending list, the boosted tokens are the closest thing to it
+const BOOSTED_NOTE: &str = "Paid DexScreener boosts, not organic trends - DYOR";
+
+pub async fn send_trending(bot: &Bot, message: &Message, client: reqwest::Client, chain_arg: &str) {
+    let chain_arg = match chain_arg.trim() {
+        "" => DEFAULT_TRENDING_CHAIN.to_owned(),
+        arg => arg.to_lowercase(),
+    };
+
+    let Some(chain_id) = dexscreener_chain_id(&chain_arg) else {
+        reply_plain(bot, message, "Usage: /trending [sol|bsc|base|ton|tron]").await;
+        return;
+    };
+
+    let limit = APP_CONFIG.get().unwrap().app_config().trending_limit;
+
+    let boosts = match retrieve_top_boosted_tokens(client.clone()).await {
+        Ok(boosts) => boosts,
+        Err(err) => {
+            warn!("Failed to retrieve boosted tokens for {chain_id} - {err:?}");
+            reply_plain(bot, message, "Failed to retrieve boosted tokens, try again later").await;
+            return;
+        }
+    };
+
+    let mut token_cas: Vec<&str> = Vec::with_capacity(limit);
+    for boost in boosts.iter().filter(|b| b.chain_id == chain_id) {
+        if token_cas.len() >= limit {
+            break;
+        }
+        if !token_cas.contains(&boost.token_address.as_str()) {
+            token_cas.push(boost.token_address.as_str());
+        }
+    }
+
+    if token_cas.is_empty() {
+        reply_plain(bot, message, "No boosted tokens found for this chain right now").await;
+        return;
+    }
+
+    let pairs = match retrieve_token_pairs(chain_id, &token_cas, client).await {
+        Ok(pairs) => pairs,
+        Err(err) => {
+            warn!("Failed to retrieve pairs for boosted tokens on {chain_id} - {err:?}");
+            reply_plain(bot, message, "Failed to retrieve boosted tokens, try again later").await;
+            return;
+        }
+    };
+
+    let mut text = MessageBuilder::new()
+        .text("🔥 ")
+        .bold(&format!("Boosted on {}", chain_arg.to_uppercase()))
+        .line()
+        .text(BOOSTED_NOTE);
+    let mut buttons = Vec::with_capacity(pairs.len());
+
+    // keep the order of the boosted list
+    for (idx, pair) in token_cas
+        .iter()
+        .filter_map(|ca| pairs.iter().find(|p| p.base_token.address == *ca))
+        .enumerate()
+    {
+        let mcap = match pair.market_cap {
+            Some(mcap) => format_human_readable(mcap, 2),
+            None => "??.??K".to_owned(),
+        };
+        let change = match pair.price_change.h24 {
+            Some(change) => format_price_change(change),
+            None => "—".to_owned(),
+        };
+
+        text = text
+            .line()
+            .text(&format!("{}. ", idx + 1))
+            .bold(&sanitize_text(&pair.base_token.symbol))
+            .text(&format!(" - {mcap} ({change})"));
+        buttons.push(InlineKeyboardButton::callback(
+            format!("🔎 {}. {}", idx + 1, sanitize_text(&pair.base_token.symbol)),
+            format!("{EXPAND_CALLBACK_PREFIX}{}", pair.base_token.address),
+        ));
+    }
+
+    let message_text = text.build();
+    debug!("Prepared trending message {message_text}");
+
+    let keyboard = InlineKeyboardMarkup::new(buttons.chunks(2).map(|row| row.to_vec()));
+
+    let (text, parse_mode) = telegram_text(&message_text);
+    if let Err(e) = bot
+        .send_message(message.chat.id, text)
+        .parse_mode(parse_mode)
+        .disable_link_preview(true)
+        .reply_markup(keyboard)
+        .reply_to(message.id)
+        .await
+    {
+        warn!("Failed to send boosted tokens for {chain_id} - {e:?}");
+    }
+}
+
+async fn reply_plain(bot: &Bot, message: &Message, text: &str) {
+    if let Err(e) = bot.send_message(message.chat.id, text).reply_to(message.id).await {
+        warn!("Failed to send reply - {e:?}");
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use log::debug;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::APP_CONFIG;
+use crate::cache::caches;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LlamaEmission {
+    /// `chain:address` of the token, e.g. `solana:<mint>` or `bsc:0x...`
+    token: Option<String>,
+    circ_supply: Option<Decimal>,
+    next_event: Option<LlamaUnlockEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LlamaUnlockEvent {
+    /// Unix timestamp
+    date: i64,
+    to_unlock: Option<Decimal>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NextUnlock {
+    pub at: DateTime<Utc>,
+    /// Unlocked amount relative to the circulating supply, in percents
+    pub percent: Option<Decimal>,
+}
+
+impl NextUnlock {
+    /// e.g. "next unlock: 12% in 9d", not escaped
+    pub fn describe(&self) -> String {
+        let left = self.at - Utc::now();
+        let left = if left.num_days() > 0 {
+            format!("{}d", left.num_days())
+        } else {
+            format!("{}h", left.num_hours().max(1))
+        };
+
+        match self.percent {
+            Some(percent) => format!(
+                "next unlock: {}% in {left}",
+                percent.round_dp(1).normalize()
+            ),
+            None => format!("next unlock in {left}"),
+        }
+    }
+}
+
+/// Lowercased token address -> upcoming unlock
+pub type UnlockSchedule = HashMap<String, NextUnlock>;
+
+async fn retrieve_unlock_schedule(client: reqwest::Client) -> anyhow::Result<Arc<UnlockSchedule>> {
+    let unlocks = &caches().unlocks;
+    if let Some(schedule) = unlocks.get(&()).await {
+        return Ok(schedule);
+    }
+
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+    let url = format!("{}/emissions", app_config.provider_urls.defillama);
+    debug!("Going to hit url - {url}");
+
+    let emissions = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<LlamaEmission>>()
+        .await?;
+
+    let now = Utc::now();
+    let schedule: UnlockSchedule = emissions
+        .into_iter()
+        .filter_map(|emission| {
+            let address = emission.token?.split_once(':')?.1.to_lowercase();
+            let event = emission.next_event?;
+            let at = DateTime::from_timestamp(event.date, 0).filter(|at| *at > now)?;
+            let percent = match (event.to_unlock, emission.circ_supply) {
+                (Some(amount), Some(circ_supply)) if circ_supply > Decimal::ZERO => {
+                    Some(amount * Decimal::ONE_HUNDRED / circ_supply)
+                }
+                _ => None,
+            };
+            Some((address, NextUnlock { at, percent }))
+        })
+        .collect();
+
+    debug!("Loaded {} upcoming unlocks from DefiLlama", schedule.len());
+
+    let schedule = Arc::new(schedule);
+    unlocks.insert((), schedule.clone()).await;
+    Ok(schedule)
+}
+
+/// Next unlock of the token if DefiLlama tracks its vesting
+pub async fn retrieve_next_unlock(
+    token_ca: &str,
+    client: reqwest::Client,
+) -> anyhow::Result<Option<NextUnlock>> {
+    let schedule = retrieve_unlock_schedule(client).await?;
+    Ok(schedule.get(&token_ca.to_lowercase()).cloned())
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use teloxide::types::{ChatId, User, UserId};
+
+use crate::APP_CONFIG;
+
+#[derive(Debug)]
+struct UserTraffic {
+    window_start: Instant,
+    cards: usize,
+}
+
+/// Cards triggered by each user in each chat within the current window
+static TRAFFIC: LazyLock<Mutex<HashMap<(UserId, ChatId), UserTraffic>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The limit applies to regular users only, None means no limit
+fn limited_user(user: Option<&User>) -> Option<UserId> {
+    let app_cfg = APP_CONFIG.get().unwrap();
+    let config = &app_cfg.app_config().user_rate_limit;
+    if config.max_cards == 0 {
+        return None;
+    }
+
+    user.filter(|user| !app_cfg.is_admin(user))
+        .map(|user| user.id)
+}
+
+/// How many more cards the user may trigger in the chat, None when unlimited
+pub fn user_card_budget(chat_id: ChatId, user: Option<&User>) -> Option<usize> {
+    let user_id = limited_user(user)?;
+    let config = &APP_CONFIG.get().unwrap().app_config().user_rate_limit;
+    let window = Duration::from_secs(config.window_mins * 60);
+
+    let mut traffic = TRAFFIC.lock().unwrap();
+    traffic.retain(|_, user_traffic| user_traffic.window_start.elapsed() < window);
+
+    let used = traffic
+        .get(&(user_id, chat_id))
+        .map_or(0, |user_traffic| user_traffic.cards);
+    Some(config.max_cards.saturating_sub(used))
+}
+
+/// Counts the cards triggered by the user towards their limit
+pub fn record_user_cards(chat_id: ChatId, user: Option<&User>, cards: usize) {
+    let Some(user_id) = limited_user(user) else {
+        return;
+    };
+    if cards == 0 {
+        return;
+    }
+
+    let mut traffic = TRAFFIC.lock().unwrap();
+    let user_traffic = traffic.entry((user_id, chat_id)).or_insert(UserTraffic {
+        window_start: Instant::now(),
+        cards: 0,
+    });
+    user_traffic.cards += cards;
+}
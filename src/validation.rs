@@ -0,0 +1,180 @@
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+
+use crate::router::AddressKind;
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Solana public keys, both mints and wallets, are 32 bytes long
+const SOLANA_KEY_LEN: usize = 32;
+
+/// Tron addresses are a version byte, a 20 byte account id and a 4 byte checksum
+const TRON_ADDRESS_LEN: usize = 25;
+
+const TRON_VERSION_BYTE: u8 = 0x41;
+
+const TRON_CHECKSUM_LEN: usize = 4;
+
+/// Mixed-case addresses must match their EIP-55 checksum, all lowercase or
+/// all uppercase ones carry no checksum and are taken as is
+pub fn is_valid_evm_address(address: &str) -> bool {
+    let Some(hex) = address.strip_prefix("0x") else {
+        return false;
+    };
+    if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return false;
+    }
+
+    let has_lowercase = hex.chars().any(|c| c.is_ascii_lowercase());
+    let has_uppercase = hex.chars().any(|c| c.is_ascii_uppercase());
+    if !has_lowercase || !has_uppercase {
+        return true;
+    }
+
+    let hash = Keccak256::digest(hex.to_ascii_lowercase().as_bytes());
+    hex.chars().enumerate().all(|(i, c)| {
+        let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+        !c.is_ascii_alphabetic() || c.is_ascii_uppercase() == (nibble >= 8)
+    })
+}
+
+fn decode_base58(text: &str) -> Option<Vec<u8>> {
+    // little-endian digits of the decoded number
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in text.bytes() {
+        let mut carry = BASE58_ALPHABET.iter().position(|&a| a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    // every leading '1' stands for a leading zero byte
+    let leading_zeros = text.bytes().take_while(|&c| c == b'1').count();
+    bytes.resize(bytes.len() + leading_zeros, 0);
+    bytes.reverse();
+    Some(bytes)
+}
+
+/// Solana addresses are base58 encoded 32 byte public keys
+pub fn is_valid_solana_address(address: &str) -> bool {
+    decode_base58(address).is_some_and(|bytes| bytes.len() == SOLANA_KEY_LEN)
+}
+
+/// Tron addresses are base58check encoded: the checksum is the first 4 bytes
+/// of the double SHA-256 of the version byte and the account id
+pub fn is_valid_tron_address(address: &str) -> bool {
+    let Some(bytes) = decode_base58(address) else {
+        return false;
+    };
+    if bytes.len() != TRON_ADDRESS_LEN || bytes[0] != TRON_VERSION_BYTE {
+        return false;
+    }
+
+    let (payload, checksum) = bytes.split_at(TRON_ADDRESS_LEN - TRON_CHECKSUM_LEN);
+    Sha256::digest(Sha256::digest(payload))[..TRON_CHECKSUM_LEN] == *checksum
+}
+
+/// Cheap local checks ruling out strings which only look like addresses,
+/// so they don't burn provider quota
+pub fn is_plausible_address(kind: AddressKind, address: &str) -> bool {
+    match kind {
+        AddressKind::SolanaMint | AddressKind::SolanaWallet => is_valid_solana_address(address),
+        AddressKind::EvmToken | AddressKind::EvmWallet => is_valid_evm_address(address),
+        AddressKind::TronToken => is_valid_tron_address(address),
+        AddressKind::TonJetton | AddressKind::Pair | AddressKind::TxHash | AddressKind::Domain => {
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eip55_checksums() {
+        // test vectors from the EIP-55 spec
+        for address in [
+            "0x52908400098527886E0F7030069857D2E4169EE7",
+            "0x8617E340B3D01FA5F11F306F4090FD50E238070D",
+            "0xde709f2102306220921060314715629080e2fb77",
+            "0x27b1fdb04752bbc536007a920d24acb045561c26",
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            assert!(is_valid_evm_address(address), "{address}");
+        }
+    }
+
+    #[test]
+    fn rejects_bad_checksums() {
+        assert!(!is_valid_evm_address(
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"
+        ));
+        assert!(!is_valid_evm_address(
+            "0xfb6916095ca1df60bB79Ce92cE3Ea74c37c5d359"
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_evm_addresses() {
+        assert!(!is_valid_evm_address(
+            "5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        ));
+        assert!(!is_valid_evm_address(
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA"
+        ));
+        assert!(!is_valid_evm_address(
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeg"
+        ));
+    }
+
+    #[test]
+    fn tron_addresses() {
+        // USDT, USDC and WTRX
+        for address in [
+            "TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t",
+            "TEkxiTehnzSmSe2XqrBj4w32RUN966rdz8",
+            "TNUC9Qb1rRpS5CbWLmNMxXBjyFoydXjWFR",
+        ] {
+            assert!(is_valid_tron_address(address), "{address}");
+        }
+
+        // bad checksum
+        assert!(!is_valid_tron_address("TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6u"));
+        // valid checksum, but 0x42 version byte
+        assert!(!is_valid_tron_address("TZJozAg1ruapycCicgz31GxvYJ1G1qELV7"));
+        // too short
+        assert!(!is_valid_tron_address("TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6"));
+        // not base58
+        assert!(!is_valid_tron_address("TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj60"));
+        // Solana mint
+        assert!(!is_valid_tron_address(
+            "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+        ));
+    }
+
+    #[test]
+    fn solana_addresses() {
+        assert!(is_valid_solana_address(
+            "So11111111111111111111111111111111111111112"
+        ));
+        assert!(is_valid_solana_address(
+            "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+        ));
+        assert!(!is_valid_solana_address(
+            "So1111111111111111111111111111111"
+        ));
+        assert!(!is_valid_solana_address(
+            "0OIl1111111111111111111111111111111111111112"
+        ));
+    }
+}
@@ -0,0 +1,216 @@
+use log::{debug, warn};
+use rust_decimal::{Decimal, dec};
+use serde::Deserialize;
+
+use crate::APP_CONFIG;
+use crate::moralis_keys::{NATIVE_BALANCE_CU, WALLET_TOKENS_CU, send_moralis};
+use crate::providers::{Provider, guarded};
+use crate::render::{LinkTemplate, WalletCardContext};
+use crate::solana_rpc::{count_held_tokens, retrieve_sol_balance};
+use crate::token_info::{Chain, format_human_readable};
+
+const WEI_PER_ETHER: Decimal = dec!(1_000_000_000_000_000_000);
+
+#[derive(Debug, Deserialize)]
+struct MoralisBalance {
+    /// In wei
+    balance: Decimal,
+}
+
+/// Native balance and number of the held tokens on a single chain
+#[derive(Debug, Clone)]
+struct ChainHoldings {
+    native_symbol: &'static str,
+    balance: Option<Decimal>,
+    token_count: Option<usize>,
+}
+
+impl ChainHoldings {
+    fn describe_balance(&self) -> Option<String> {
+        let balance = self.balance?;
+        Some(format!(
+            "{} {}",
+            format_human_readable(balance, 2),
+            self.native_symbol
+        ))
+    }
+}
+
+fn native_symbol(chain: Chain) -> &'static str {
+    match chain {
+        Chain::Bsc => "BNB",
+        Chain::Base => "ETH",
+        Chain::Arbitrum => "ETH",
+        Chain::Polygon => "POL",
+    }
+}
+
+fn moralis_chain(chain: Chain) -> &'static str {
+    match chain {
+        Chain::Bsc => "bsc",
+        Chain::Base => "base",
+        Chain::Arbitrum => "arbitrum",
+        Chain::Polygon => "polygon",
+    }
+}
+
+async fn retrieve_evm_balance(
+    address: &str,
+    chain: Chain,
+    client: reqwest::Client,
+) -> anyhow::Result<Decimal> {
+    let cfg = APP_CONFIG.get().unwrap();
+    let url = format!(
+        "{}/api/v2.2/{address}/balance",
+        cfg.app_config().provider_urls.moralis
+    );
+    debug!("Going to hit url - {url}");
+
+    let request = client.get(url).query(&[("chain", moralis_chain(chain))]);
+    let balance = send_moralis(request, NATIVE_BALANCE_CU)
+        .await?
+        .error_for_status()?
+        .json::<MoralisBalance>()
+        .await?;
+
+    Ok(balance.balance / WEI_PER_ETHER)
+}
+
+async fn count_evm_tokens(
+    address: &str,
+    chain: Chain,
+    client: reqwest::Client,
+) -> anyhow::Result<usize> {
+    let cfg = APP_CONFIG.get().unwrap();
+    let url = format!(
+        "{}/api/v2.2/{address}/erc20",
+        cfg.app_config().provider_urls.moralis
+    );
+    debug!("Going to hit url - {url}");
+
+    let request = client.get(url).query(&[("chain", moralis_chain(chain))]);
+    let tokens = send_moralis(request, WALLET_TOKENS_CU)
+        .await?
+        .error_for_status()?
+        .json::<Vec<serde_json::Value>>()
+        .await?;
+
+    Ok(tokens.len())
+}
+
+async fn retrieve_evm_holdings(
+    address: &str,
+    chain: Chain,
+    client: reqwest::Client,
+) -> ChainHoldings {
+    let (balance, token_count) = tokio::join!(
+        guarded(
+            Provider::Moralis,
+            retrieve_evm_balance(address, chain, client.clone())
+        ),
+        guarded(Provider::Moralis, count_evm_tokens(address, chain, client)),
+    );
+
+    ChainHoldings {
+        native_symbol: native_symbol(chain),
+        balance: balance
+            .inspect_err(|e| warn!("Failed to retrieve balance of {address} on {chain:?} - {e:?}"))
+            .ok(),
+        token_count: token_count
+            .inspect_err(|e| warn!("Failed to count tokens of {address} on {chain:?} - {e:?}"))
+            .ok(),
+    }
+}
+
+fn wallet_card(
+    address: &str,
+    chain: &str,
+    holdings: &[ChainHoldings],
+    links: Vec<LinkTemplate>,
+) -> Option<WalletCardContext> {
+    let balances: Vec<String> = holdings
+        .iter()
+        .filter_map(ChainHoldings::describe_balance)
+        .collect();
+    let counts: Vec<usize> = holdings
+        .iter()
+        .filter_map(|holdings| holdings.token_count)
+        .collect();
+
+    // nothing could be retrieved, most likely the providers are down
+    if balances.is_empty() && counts.is_empty() {
+        return None;
+    }
+
+    Some(WalletCardContext {
+        address: address.to_owned(),
+        chain: chain.to_owned(),
+        balances,
+        token_count: (!counts.is_empty()).then(|| counts.iter().sum()),
+        links,
+    })
+}
+
+fn link(name: &str, url: String) -> LinkTemplate {
+    LinkTemplate {
+        name: name.to_owned(),
+        url,
+        icon: String::new(),
+    }
+}
+
+/// SOL balance, number of held tokens and links of the Solana wallet
+pub async fn retrieve_solana_wallet(
+    address: &str,
+    client: reqwest::Client,
+) -> Option<WalletCardContext> {
+    let (balance, token_count) = tokio::join!(
+        guarded(
+            Provider::SolanaRpc,
+            retrieve_sol_balance(address, client.clone())
+        ),
+        guarded(Provider::SolanaRpc, count_held_tokens(address, client)),
+    );
+
+    let holdings = ChainHoldings {
+        native_symbol: "SOL",
+        balance: balance
+            .inspect_err(|e| warn!("Failed to retrieve balance of {address} - {e:?}"))
+            .ok(),
+        token_count: token_count
+            .inspect_err(|e| warn!("Failed to count tokens of {address} - {e:?}"))
+            .ok(),
+    };
+
+    wallet_card(
+        address,
+        "SOL",
+        &[holdings],
+        vec![
+            link("GMGN", format!("https://gmgn.ai/sol/address/{address}")),
+            link("Solscan", format!("https://solscan.io/account/{address}")),
+        ],
+    )
+}
+
+/// BNB and ETH (Base) balances, number of held tokens and links of the EVM wallet
+pub async fn retrieve_evm_wallet(
+    address: &str,
+    client: reqwest::Client,
+) -> Option<WalletCardContext> {
+    let holdings = futures::future::join_all(
+        [Chain::Bsc, Chain::Base]
+            .map(|chain| retrieve_evm_holdings(address, chain, client.clone())),
+    )
+    .await;
+
+    wallet_card(
+        address,
+        "BSC/BASE",
+        &holdings,
+        vec![
+            link("GMGN", format!("https://gmgn.ai/bsc/address/{address}")),
+            link("DeBank", format!("https://debank.com/profile/{address}")),
+        ],
+    )
+}
@@ -0,0 +1,416 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use teloxide::Bot;
+use teloxide::prelude::Requester;
+use teloxide::types::{ChatId, Message, ThreadId, UserId};
+
+use crate::APP_CONFIG;
+use crate::delivery::{AlertDestination, deliver_alert};
+use crate::dexscreener::{Pair, retrieve_token_pair, retrieve_token_pairs};
+use crate::fmt::MessageBuilder;
+use crate::router::route_addresses;
+use crate::storage::{
+    count_user_watches, delete_watch, delete_watch_by_id, load_user_watches, load_watches,
+    save_watch,
+};
+use crate::token_info::format_human_readable;
+
+const USAGE: &str =
+    "Usage: /watch <ca> <target> [--dm], target is a multiple like 2x or an mcap like 500k";
+
+/// DexScreener accepts up to 30 addresses per request
+const MAX_TOKENS_PER_REQUEST: usize = 30;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WatchlistConfig {
+    /// How often the prices of the watched tokens are checked
+    pub poll_mins: u64,
+    pub max_watches_per_user: i64,
+}
+
+impl Default for WatchlistConfig {
+    fn default() -> Self {
+        Self {
+            poll_mins: 5,
+            max_watches_per_user: 20,
+        }
+    }
+}
+
+/// Price alert set up by `/watch`
+#[derive(Debug, Clone)]
+pub struct Watch {
+    pub id: i64,
+    pub user_id: UserId,
+    pub user_name: String,
+    pub chat_id: ChatId,
+    pub thread_id: Option<ThreadId>,
+    /// DexScreener chain id
+    pub chain_id: String,
+    pub token_ca: String,
+    pub symbol: String,
+    /// Mcap when the alert was set up, tells whether the target is above or below
+    pub base_mcap: Decimal,
+    pub target_mcap: Decimal,
+    pub destination: AlertDestination,
+}
+
+impl Watch {
+    fn is_reached(&self, mcap: Decimal) -> bool {
+        if self.target_mcap >= self.base_mcap {
+            mcap >= self.target_mcap
+        } else {
+            mcap <= self.target_mcap
+        }
+    }
+
+    /// MarkdownV2 alert pinging the user
+    fn alert_text(&self, mcap: Decimal) -> String {
+        MessageBuilder::new()
+            .text("🔔 ")
+            .link(&self.user_name, &format!("tg://user?id={}", self.user_id))
+            .text(" ")
+            .bold(&self.symbol)
+            .text(&format!(
+                " reached {} mcap (target {})",
+                format_human_readable(mcap, 1),
+                format_human_readable(self.target_mcap, 1)
+            ))
+            .line()
+            .code(&self.token_ca)
+            .build()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Target {
+    Multiple(Decimal),
+    Mcap(Decimal),
+}
+
+impl Target {
+    fn mcap(self, base_mcap: Decimal) -> Decimal {
+        match self {
+            Target::Multiple(multiple) => base_mcap * multiple,
+            Target::Mcap(mcap) => mcap,
+        }
+    }
+}
+
+/// `2x`, `0.5x`, `500k`, `$1.2m`, `3b` or a plain number
+fn parse_target(text: &str) -> Option<Target> {
+    let text = text.trim().trim_start_matches('$').to_lowercase();
+
+    if let Some(multiple) = text.strip_suffix('x') {
+        return multiple
+            .parse::<Decimal>()
+            .ok()
+            .filter(|multiple| *multiple > Decimal::ZERO)
+            .map(Target::Multiple);
+    }
+
+    let (number, scale) = match text.chars().last()? {
+        'k' => (&text[..text.len() - 1], Decimal::ONE_THOUSAND),
+        'm' => (&text[..text.len() - 1], Decimal::from(1_000_000)),
+        'b' => (&text[..text.len() - 1], Decimal::from(1_000_000_000)),
+        _ => (text.as_str(), Decimal::ONE),
+    };
+
+    number
+        .parse::<Decimal>()
+        .ok()
+        .map(|number| number * scale)
+        .filter(|mcap| *mcap > Decimal::ZERO)
+        .map(Target::Mcap)
+}
+
+fn pair_mcap(pair: &Pair) -> Option<Decimal> {
+    pair.market_cap
+        .or(pair.fdv)
+        .filter(|mcap| *mcap > Decimal::ZERO)
+}
+
+/// Finds the token on the chains its address may belong to, only pairs
+/// trading the token itself count
+async fn find_token(token_ca: &str, client: reqwest::Client) -> Option<(&'static str, Pair)> {
+    let routed = route_addresses(token_ca, client.clone()).await;
+    let chain_ids: &[&'static str] = if !routed.solana.is_empty() {
+        &["solana"]
+    } else if !routed.evm.is_empty() {
+        &["bsc", "base", "arbitrum", "polygon"]
+    } else if !routed.ton.is_empty() {
+        &["ton"]
+    } else if !routed.tron.is_empty() {
+        &["tron"]
+    } else {
+        return None;
+    };
+
+    for chain_id in chain_ids {
+        match retrieve_token_pair(chain_id, token_ca, client.clone()).await {
+            Ok(Some(pair)) => return Some((chain_id, pair)),
+            Ok(None) => {}
+            Err(e) => warn!("Failed to retrieve {token_ca} pairs on {chain_id} - {e:?}"),
+        }
+    }
+
+    None
+}
+
+/// Handles `/watch <ca> <target> [--dm]`
+pub async fn handle_watch(
+    bot: &Bot,
+    message: &Message,
+    storage: &SqlitePool,
+    client: reqwest::Client,
+    args: &str,
+) {
+    let text = match watch(message, storage, client, args).await {
+        Ok(text) | Err(text) => text,
+    };
+
+    if let Err(e) = bot.send_message(message.chat.id, text).await {
+        warn!("Failed to reply to /watch - {e:?}");
+    }
+}
+
+async fn watch(
+    message: &Message,
+    storage: &SqlitePool,
+    client: reqwest::Client,
+    args: &str,
+) -> Result<String, String> {
+    let Some(user) = &message.from else {
+        return Err("Only users may set up alerts".to_owned());
+    };
+
+    let mut args: Vec<&str> = args.split_whitespace().collect();
+    let destination = match args.iter().position(|arg| *arg == "--dm") {
+        Some(position) => {
+            args.remove(position);
+            AlertDestination::DirectMessage
+        }
+        None => AlertDestination::Chat,
+    };
+
+    let [token_ca, target] = args[..] else {
+        return Err(USAGE.to_owned());
+    };
+    let Some(target) = parse_target(target) else {
+        return Err(USAGE.to_owned());
+    };
+
+    let max_watches = APP_CONFIG
+        .get()
+        .unwrap()
+        .app_config()
+        .watchlist
+        .max_watches_per_user;
+    match count_user_watches(storage, user.id).await {
+        Ok(count) if count >= max_watches => {
+            return Err(format!("You can't have more than {max_watches} alerts"));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            warn!("Failed to count watches of {} - {e:?}", user.id);
+            return Err("Failed to save the alert".to_owned());
+        }
+    }
+
+    let Some((chain_id, pair)) = find_token(token_ca, client).await else {
+        return Err(format!("Couldn't find token {token_ca}"));
+    };
+    let Some(base_mcap) = pair_mcap(&pair) else {
+        return Err(format!("Mcap of {token_ca} is unknown"));
+    };
+
+    let watch = Watch {
+        id: 0,
+        user_id: user.id,
+        user_name: user.first_name.clone(),
+        chat_id: message.chat.id,
+        thread_id: message.thread_id,
+        chain_id: chain_id.to_owned(),
+        token_ca: pair.base_token.address,
+        symbol: pair.base_token.symbol,
+        base_mcap,
+        target_mcap: target.mcap(base_mcap),
+        destination,
+    };
+
+    if let Err(e) = save_watch(storage, &watch).await {
+        warn!("Failed to save watch of {} on {token_ca} - {e:?}", user.id);
+        return Err("Failed to save the alert".to_owned());
+    }
+
+    info!(
+        "User {} watches {} for {} mcap",
+        user.id, watch.token_ca, watch.target_mcap
+    );
+
+    Ok(format!(
+        "Watching ${} for {} mcap (now {})",
+        watch.symbol,
+        format_human_readable(watch.target_mcap, 1),
+        format_human_readable(base_mcap, 1)
+    ))
+}
+
+/// Handles `/unwatch <ca>`
+pub async fn handle_unwatch(bot: &Bot, message: &Message, storage: &SqlitePool, args: &str) {
+    let token_ca = args.trim();
+    let text = match &message.from {
+        _ if token_ca.is_empty() => "Usage: /unwatch <ca>".to_owned(),
+        Some(user) => match delete_watch(storage, user.id, message.chat.id, token_ca).await {
+            Ok(true) => format!("Alert on {token_ca} removed"),
+            Ok(false) => format!("You have no alert on {token_ca} in this chat"),
+            Err(e) => {
+                warn!(
+                    "Failed to delete watch of {} on {token_ca} - {e:?}",
+                    user.id
+                );
+                "Failed to remove the alert".to_owned()
+            }
+        },
+        None => "Only users may set up alerts".to_owned(),
+    };
+
+    if let Err(e) = bot.send_message(message.chat.id, text).await {
+        warn!("Failed to reply to /unwatch - {e:?}");
+    }
+}
+
+/// Handles `/alerts`, lists the user's alerts set up in the chat
+pub async fn handle_alerts(bot: &Bot, message: &Message, storage: &SqlitePool) {
+    let text = match &message.from {
+        Some(user) => match load_user_watches(storage, user.id, message.chat.id).await {
+            Ok(watches) if watches.is_empty() => "You have no alerts in this chat".to_owned(),
+            Ok(watches) => {
+                let watches = watches
+                    .iter()
+                    .map(|watch| {
+                        let dm = match watch.destination {
+                            AlertDestination::DirectMessage => " (DM)",
+                            AlertDestination::Chat => "",
+                        };
+                        format!(
+                            "${} {} → {}{dm}",
+                            watch.symbol,
+                            watch.token_ca,
+                            format_human_readable(watch.target_mcap, 1)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!("Your alerts:\n{watches}")
+            }
+            Err(e) => {
+                warn!("Failed to load watches of {} - {e:?}", user.id);
+                "Failed to load alerts".to_owned()
+            }
+        },
+        None => "Only users may set up alerts".to_owned(),
+    };
+
+    if let Err(e) = bot.send_message(message.chat.id, text).await {
+        warn!("Failed to reply to /alerts - {e:?}");
+    }
+}
+
+/// Current mcaps of the watched tokens keyed by chain id and CA
+async fn retrieve_mcaps(
+    watches: &[Watch],
+    client: reqwest::Client,
+) -> HashMap<(String, String), Decimal> {
+    let mut by_chain: HashMap<&str, Vec<&str>> = HashMap::new();
+    for watch in watches {
+        let token_cas = by_chain.entry(&watch.chain_id).or_default();
+        if !token_cas.contains(&watch.token_ca.as_str()) {
+            token_cas.push(&watch.token_ca);
+        }
+    }
+
+    let mut mcaps = HashMap::new();
+    for (chain_id, token_cas) in by_chain {
+        for chunk in token_cas.chunks(MAX_TOKENS_PER_REQUEST) {
+            match retrieve_token_pairs(chain_id, chunk, client.clone()).await {
+                Ok(pairs) => {
+                    for pair in pairs {
+                        if let Some(mcap) = pair_mcap(&pair) {
+                            mcaps.insert((chain_id.to_owned(), pair.base_token.address), mcap);
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to retrieve mcaps of watched {chain_id} tokens - {e:?}"),
+            }
+        }
+    }
+
+    mcaps
+}
+
+async fn check_watches(bot: &Bot, storage: &SqlitePool, client: reqwest::Client) {
+    let mut watches = match load_watches(storage).await {
+        Ok(watches) => watches,
+        Err(e) => {
+            warn!("Failed to load watches - {e:?}");
+            return;
+        }
+    };
+    // watches of paused chats are kept for when they are resumed
+    let paused_chats = APP_CONFIG.get().unwrap().app_config().paused_chats.clone();
+    watches.retain(|watch| !paused_chats.contains(&watch.chat_id.0));
+    if watches.is_empty() {
+        return;
+    }
+
+    let mcaps = retrieve_mcaps(&watches, client).await;
+
+    for watch in watches {
+        let key = (watch.chain_id.clone(), watch.token_ca.clone());
+        let Some(mcap) = mcaps.get(&key).copied() else {
+            debug!("No mcap of watched {} - skipping it", watch.token_ca);
+            continue;
+        };
+        if !watch.is_reached(mcap) {
+            continue;
+        }
+
+        info!(
+            "Watched {} reached {mcap} - alerting {}",
+            watch.token_ca, watch.user_id
+        );
+        deliver_alert(
+            bot,
+            storage,
+            watch.destination,
+            watch.user_id,
+            watch.chat_id,
+            watch.thread_id,
+            watch.alert_text(mcap),
+        )
+        .await;
+
+        if let Err(e) = delete_watch_by_id(storage, watch.id).await {
+            warn!("Failed to delete fired watch {} - {e:?}", watch.id);
+        }
+    }
+}
+
+/// Polls the prices of the watched tokens and alerts the users once their targets are crossed
+pub fn spawn_watch_poller(bot: Bot, storage: SqlitePool, client: reqwest::Client) {
+    tokio::spawn(async move {
+        loop {
+            let poll_mins = APP_CONFIG.get().unwrap().app_config().watchlist.poll_mins;
+            tokio::time::sleep(Duration::from_secs(poll_mins.max(1) * 60)).await;
+
+            check_watches(&bot, &storage, client.clone()).await;
+        }
+    });
+}
@@ -0,0 +1,190 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::Context;
+use axum_server::Handle;
+use axum_server::tls_rustls::RustlsConfig;
+use log::{info, warn};
+use serde::Deserialize;
+use teloxide::Bot;
+use teloxide::update_listeners::UpdateListener;
+use teloxide::update_listeners::webhooks::{Options, axum_to_router};
+use tokio::sync::Mutex;
+
+use crate::APP_CONFIG;
+
+/// In-flight updates get this long to be delivered on the old address after rebinding
+const REBIND_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// Telegram pushes the updates to `url` instead of the bot polling for them
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct WebhookConfig {
+    /// Public URL registered with Telegram, e.g. `https://bot.example.com:8443/webhook`
+    pub url: String,
+    /// Address to listen on, e.g. `0.0.0.0:8443`, rebound on SIGHUP
+    pub listen_addr: String,
+    /// PEM certificate chain and private key, both re-read on SIGHUP.
+    /// TLS is left to a reverse proxy unless both are set
+    pub tls_cert_path: Option<PathBuf>,
+    pub tls_key_path: Option<PathBuf>,
+}
+
+impl WebhookConfig {
+    fn tls_paths(&self) -> Option<(&PathBuf, &PathBuf)> {
+        self.tls_cert_path.as_ref().zip(self.tls_key_path.as_ref())
+    }
+}
+
+struct RunningListener {
+    config: WebhookConfig,
+    addr: SocketAddr,
+    tls: Option<RustlsConfig>,
+    handle: Handle<SocketAddr>,
+}
+
+/// Serves the webhook requests, set once the webhook is registered
+static ROUTER: OnceLock<axum::Router> = OnceLock::new();
+static LISTENER: Mutex<Option<RunningListener>> = Mutex::const_new(None);
+
+async fn load_tls(config: &WebhookConfig) -> anyhow::Result<Option<RustlsConfig>> {
+    let Some((cert, key)) = config.tls_paths() else {
+        return Ok(None);
+    };
+
+    let tls = RustlsConfig::from_pem_file(cert, key)
+        .await
+        .with_context(|| format!("Failed to load TLS certificate {cert:?} and key {key:?}"))?;
+    Ok(Some(tls))
+}
+
+/// Binds before returning, so a busy address is reported while the old listener keeps serving
+fn serve(addr: SocketAddr, tls: Option<RustlsConfig>) -> anyhow::Result<Handle<SocketAddr>> {
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+
+    let handle = Handle::new();
+    let app = ROUTER.get().unwrap().clone().into_make_service();
+    let server = handle.clone();
+    tokio::spawn(async move {
+        let served = match tls {
+            Some(tls) => match axum_server::from_tcp_rustls(listener, tls) {
+                Ok(server_builder) => server_builder.handle(server).serve(app).await,
+                Err(e) => Err(e),
+            },
+            None => match axum_server::from_tcp(listener) {
+                Ok(server_builder) => server_builder.handle(server).serve(app).await,
+                Err(e) => Err(e),
+            },
+        };
+        if let Err(e) = served {
+            warn!("Webhook listener on {addr} failed - {e:?}");
+        }
+    });
+
+    Ok(handle)
+}
+
+/// Registers the webhook and starts listening for the updates. Stopping the
+/// returned listener stops the server and deletes the webhook.
+pub async fn webhook_listener(
+    bot: Bot,
+    config: &WebhookConfig,
+) -> anyhow::Result<impl UpdateListener<Err = Infallible>> {
+    let url = config.url.parse().context("Invalid webhook url")?;
+    let addr: SocketAddr = config
+        .listen_addr
+        .parse()
+        .context("Invalid webhook listen_addr")?;
+    let tls = load_tls(config).await?;
+
+    let (listener, stop_flag, router) = axum_to_router(bot, Options::new(addr, url)).await?;
+    // This is safe as long as webhook_listener is called just once in the main fn
+    ROUTER.set(router).unwrap();
+
+    let handle = serve(addr, tls.clone())?;
+    *LISTENER.lock().await = Some(RunningListener {
+        config: config.clone(),
+        addr,
+        tls,
+        handle,
+    });
+    info!("Listening for webhook updates on {addr}");
+
+    tokio::spawn(async move {
+        stop_flag.await;
+        if let Some(running) = LISTENER.lock().await.take() {
+            running.handle.graceful_shutdown(Some(REBIND_GRACE_PERIOD));
+        }
+    });
+
+    Ok(listener)
+}
+
+/// Applies the reloaded webhook config: re-reads the TLS certificate in place and
+/// moves to the new listen address. The registration with Telegram is kept as is.
+pub async fn reload_webhook_listener() {
+    let mut listener = LISTENER.lock().await;
+    let Some(running) = listener.as_mut() else {
+        return;
+    };
+    let Some(config) = APP_CONFIG.get().unwrap().app_config().webhook.clone() else {
+        warn!("Webhook config removed - switching to polling needs a restart");
+        return;
+    };
+    if config.url != running.config.url {
+        warn!("Webhook url changed - registering it needs a restart");
+    }
+
+    let addr: SocketAddr = match config.listen_addr.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            warn!(
+                "Invalid webhook listen_addr, keeping {} - {e:?}",
+                running.addr
+            );
+            return;
+        }
+    };
+
+    // renewed certificates usually keep their paths, so they are re-read on every reload.
+    // Connections accepted from now on get the new one, open ones keep the old one
+    match (&running.tls, config.tls_paths()) {
+        (Some(tls), Some((cert, key))) if addr == running.addr => {
+            if let Err(e) = tls.reload_from_pem_file(cert, key).await {
+                warn!("Failed to reload TLS certificate {cert:?}, keeping the current one - {e:?}");
+                return;
+            }
+            info!("Reloaded webhook TLS certificate {cert:?}");
+        }
+        (None, None) if addr == running.addr => {}
+        _ => {
+            let tls = match load_tls(&config).await {
+                Ok(tls) => tls,
+                Err(e) => {
+                    warn!("Failed to reload webhook TLS, keeping the current listener - {e:?}");
+                    return;
+                }
+            };
+            let handle = match serve(addr, tls.clone()) {
+                Ok(handle) => handle,
+                Err(e) => {
+                    warn!(
+                        "Failed to listen on {addr}, keeping {} - {e:?}",
+                        running.addr
+                    );
+                    return;
+                }
+            };
+            running.handle.graceful_shutdown(Some(REBIND_GRACE_PERIOD));
+            info!("Moved the webhook listener from {} to {addr}", running.addr);
+            running.addr = addr;
+            running.tls = tls;
+            running.handle = handle;
+        }
+    }
+
+    running.config = config;
+}
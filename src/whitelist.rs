@@ -0,0 +1,183 @@
+use chrono::Utc;
+use log::{info, warn};
+use sqlx::SqlitePool;
+use teloxide::Bot;
+use teloxide::prelude::Requester;
+use teloxide::types::{ChatId, Message};
+
+use crate::APP_CONFIG;
+use crate::config::{persist_blocked_chats, persist_paused_chats, persist_whitelist};
+use crate::storage::{load_archived_chats, save_archived_chat};
+
+enum WhitelistAction {
+    Add(i64),
+    Remove(i64),
+    /// Keeps the chat whitelisted along with its data, but ignores its messages
+    Pause(i64),
+    Resume(i64),
+    /// Removes the chat from the whitelist, its data is deleted after the retention period
+    Archive(i64),
+    List,
+}
+
+const USAGE: &str = "Usage: /whitelist add [chat_id] | /whitelist remove <chat_id> | \
+    /whitelist pause <chat_id> | /whitelist resume <chat_id> | /whitelist archive <chat_id> | \
+    /whitelist list";
+
+fn parse_action(args: &str, current_chat: ChatId) -> Option<WhitelistAction> {
+    let mut args = args.split_whitespace();
+    let action = args.next()?;
+    let chat_id = args.next().map(str::parse::<i64>);
+
+    if args.next().is_some() {
+        return None;
+    }
+
+    match (action.to_lowercase().as_str(), chat_id) {
+        ("add", None) => Some(WhitelistAction::Add(current_chat.0)),
+        ("add", Some(Ok(id))) => Some(WhitelistAction::Add(id)),
+        ("remove", Some(Ok(id))) => Some(WhitelistAction::Remove(id)),
+        ("pause", Some(Ok(id))) => Some(WhitelistAction::Pause(id)),
+        ("resume", Some(Ok(id))) => Some(WhitelistAction::Resume(id)),
+        ("archive", Some(Ok(id))) => Some(WhitelistAction::Archive(id)),
+        ("list", None) => Some(WhitelistAction::List),
+        _ => None,
+    }
+}
+
+/// Handles `/whitelist`, the caller must be checked to be an admin beforehand
+pub async fn handle_whitelist(bot: &Bot, message: &Message, storage: &SqlitePool, args: &str) {
+    let text = match parse_action(args, message.chat.id) {
+        Some(WhitelistAction::List) => list_whitelist(storage).await,
+        Some(WhitelistAction::Archive(id)) => archive_chat(storage, id).await,
+        Some(action) => update_whitelist(action),
+        None => USAGE.to_owned(),
+    };
+
+    if let Err(e) = bot.send_message(message.chat.id, text).await {
+        warn!("Failed to reply to /whitelist - {e:?}");
+    }
+}
+
+async fn list_whitelist(storage: &SqlitePool) -> String {
+    let app_config = APP_CONFIG.get().unwrap().app_config();
+
+    let mut sections = Vec::new();
+    if !app_config.whitelisted_chats.is_empty() {
+        let chats = app_config
+            .whitelisted_chats
+            .iter()
+            .map(|id| {
+                if app_config.paused_chats.contains(id) {
+                    format!("{id} (paused)")
+                } else {
+                    id.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        sections.push(format!("Whitelisted chats:\n{chats}"));
+    }
+
+    match load_archived_chats(storage).await {
+        Ok(archived) if !archived.is_empty() => {
+            let chats = archived
+                .iter()
+                .map(|(chat_id, archived_at)| {
+                    format!("{chat_id} (since {})", archived_at.format("%Y-%m-%d"))
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            sections.push(format!("Archived chats:\n{chats}"));
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Failed to load archived chats - {e:?}"),
+    }
+
+    if sections.is_empty() {
+        return "Whitelist is empty".to_owned();
+    }
+    sections.join("\n\n")
+}
+
+/// Whitelists the chat and persists the whitelist, returns the outcome for the admin
+pub fn add_to_whitelist(chat_id: ChatId) -> String {
+    update_whitelist(WhitelistAction::Add(chat_id.0))
+}
+
+async fn archive_chat(storage: &SqlitePool, id: i64) -> String {
+    let mut reply = update_whitelist(WhitelistAction::Archive(id));
+
+    if let Err(e) = save_archived_chat(storage, ChatId(id), Utc::now()).await {
+        warn!("Failed to save archived chat {id} - {e:?}");
+        reply.push_str(", but its data won't be cleaned up");
+    }
+
+    reply
+}
+
+fn update_whitelist(action: WhitelistAction) -> String {
+    let cfg = APP_CONFIG.get().unwrap();
+    let mut reply = String::new();
+
+    cfg.update_app_config(|config| match action {
+        WhitelistAction::Add(id) if config.whitelisted_chats.contains(&id) => {
+            reply = format!("Chat {id} is already whitelisted");
+        }
+        WhitelistAction::Add(id) => {
+            config.whitelisted_chats.push(id);
+            // a chat can't be both, whitelisting overrides an earlier block
+            config.blocked_chats.retain(|chat| *chat != id);
+            reply = format!(
+                "Chat {id} added to the whitelist, reply with /import to its exported history to seed the call history"
+            );
+        }
+        WhitelistAction::Remove(id) if !config.whitelisted_chats.contains(&id) => {
+            reply = format!("Chat {id} is not whitelisted");
+        }
+        WhitelistAction::Remove(id) => {
+            config.whitelisted_chats.retain(|chat| *chat != id);
+            config.paused_chats.retain(|chat| *chat != id);
+            reply = format!("Chat {id} removed from the whitelist");
+        }
+        WhitelistAction::Pause(id) if !config.whitelisted_chats.contains(&id) => {
+            reply = format!("Chat {id} is not whitelisted");
+        }
+        WhitelistAction::Pause(id) if config.paused_chats.contains(&id) => {
+            reply = format!("Chat {id} is already paused");
+        }
+        WhitelistAction::Pause(id) => {
+            config.paused_chats.push(id);
+            reply = format!("Chat {id} paused, its data is kept until it's resumed");
+        }
+        WhitelistAction::Resume(id) if !config.paused_chats.contains(&id) => {
+            reply = format!("Chat {id} is not paused");
+        }
+        WhitelistAction::Resume(id) => {
+            config.paused_chats.retain(|chat| *chat != id);
+            reply = format!("Chat {id} resumed");
+        }
+        WhitelistAction::Archive(id) => {
+            config.whitelisted_chats.retain(|chat| *chat != id);
+            config.paused_chats.retain(|chat| *chat != id);
+            let days = config.retention.archived_chat_days;
+            reply = format!(
+                "Chat {id} archived, its data is deleted in {days} days unless it's whitelisted again"
+            );
+        }
+        WhitelistAction::List => {}
+    });
+
+    info!("{reply}");
+
+    let app_config = cfg.app_config();
+    let persisted = persist_whitelist(&cfg.config_path, &app_config.whitelisted_chats)
+        .and_then(|()| persist_paused_chats(&cfg.config_path, &app_config.paused_chats))
+        .and_then(|()| persist_blocked_chats(&cfg.config_path, &app_config.blocked_chats));
+    if let Err(e) = persisted {
+        warn!("Failed to persist whitelist - {e:?}");
+        reply.push_str(" (not saved to the config file, it will be lost on restart)");
+    }
+
+    reply
+}